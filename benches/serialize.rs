@@ -0,0 +1,253 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+//! Compares `to_variant` against glib's `ToVariant`, which builds every child as a separate
+//! variant and collects them in a `Vec` before copying them into its parent, and
+//! `VariantType::variant_type` against glib's `StaticVariantType`, which builds the type string
+//! on every call.
+//!
+//! Rust allocations are counted by a global allocator. On glibc, `malloc`, `calloc` and
+//! `realloc` are also replaced to count every allocation in the process, which includes the
+//! variants and builders GLib allocates with `g_malloc`; the `glib allocs/iter` column is the
+//! difference. GLib before 2.76 takes small blocks from its slice allocator instead, so run
+//! with `G_SLICE=always-malloc` there. Elsewhere the column is left empty.
+//!
+//! Run with `cargo bench --bench serialize`. To compare with the serializer that collected
+//! children in a `Vec`, run the same command on the commit before it was replaced.
+
+use glib::{StaticVariantType, ToVariant};
+use glib_serde::{VariantType, VariantTypeNode};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    borrow::Cow,
+    collections::HashMap,
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Counts calls into the C allocator, forwarding them to glibc's own implementation. Rust's
+/// `System` allocator goes through these too.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+mod c_heap {
+    use std::{
+        ffi::c_void,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static MALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" {
+        fn __libc_malloc(size: usize) -> *mut c_void;
+        fn __libc_calloc(count: usize, size: usize) -> *mut c_void;
+        fn __libc_realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn malloc(size: usize) -> *mut c_void {
+        MALLOCS.fetch_add(1, Ordering::Relaxed);
+        __libc_malloc(size)
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn calloc(count: usize, size: usize) -> *mut c_void {
+        MALLOCS.fetch_add(1, Ordering::Relaxed);
+        __libc_calloc(count, size)
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+        MALLOCS.fetch_add(1, Ordering::Relaxed);
+        __libc_realloc(ptr, size)
+    }
+
+    pub fn count() -> Option<usize> {
+        Some(MALLOCS.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+mod c_heap {
+    pub fn count() -> Option<usize> {
+        None
+    }
+}
+
+fn bench<T>(name: &str, iterations: u32, mut func: impl FnMut() -> T) {
+    for _ in 0..iterations / 10 {
+        black_box(func());
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let mallocs = c_heap::count();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(func());
+    }
+    let elapsed = start.elapsed() / iterations;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let glib_allocations = mallocs
+        .zip(c_heap::count())
+        .map(|(before, after)| {
+            ((after - before).saturating_sub(allocations) / iterations as usize).to_string()
+        })
+        .unwrap_or_default();
+    println!(
+        "{:<40} {:>12?}/iter {:>8} allocs/iter {:>8} glib allocs/iter",
+        name,
+        elapsed,
+        allocations / iterations as usize,
+        glib_allocations,
+    );
+}
+
+type Record = (String, u32, HashMap<String, String>);
+
+fn records(count: usize) -> Vec<Record> {
+    (0..count)
+        .map(|i| {
+            let props = HashMap::from([
+                (String::from("name"), format!("item{}", i)),
+                (String::from("kind"), String::from("record")),
+            ]);
+            (format!("/org/example/{}", i), i as u32, props)
+        })
+        .collect()
+}
+
+/// Builds the node for `Vec<Record>` the way `VariantType` did before nodes were cached, with a
+/// new type and node for every container on every call.
+fn uncached_records_node() -> Cow<'static, VariantTypeNode<'static>> {
+    fn leaf<T: StaticVariantType>() -> Cow<'static, VariantTypeNode<'static>> {
+        Cow::Owned(VariantTypeNode::new(T::static_variant_type(), []))
+    }
+    fn container(
+        ty: &str,
+        children: impl IntoIterator<Item = Cow<'static, VariantTypeNode<'static>>>,
+    ) -> Cow<'static, VariantTypeNode<'static>> {
+        let ty = glib::VariantType::new(ty).unwrap();
+        Cow::Owned(VariantTypeNode::new(Cow::Owned(ty), children))
+    }
+    let entry = container("{ss}", [leaf::<String>(), leaf::<String>()]);
+    let props = container("a{ss}", entry.child_types().to_owned());
+    let record = container("(sua{ss})", [leaf::<String>(), leaf::<u32>(), props]);
+    container("a(sua{ss})", [record])
+}
+
+#[derive(Clone, Copy, glib_serde::VariantType, serde::Serialize, serde::Deserialize)]
+#[glib_serde_fixed_size]
+#[repr(C)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(glib_serde::VariantType, serde::Serialize)]
+enum Shape {
+    Circle(f64, f64, f64),
+    Rect { x: f64, y: f64, w: f64, h: f64 },
+    Label(String),
+}
+
+fn main() {
+    let iterations = 200;
+
+    bench("type: glib::StaticVariantType", 100000, || {
+        <Vec<Record>>::static_variant_type()
+    });
+    bench(
+        "type: uncached VariantTypeNode",
+        100000,
+        uncached_records_node,
+    );
+    bench("type: glib_serde::VariantType", 100000, || {
+        <Vec<Record>>::variant_type()
+    });
+
+    let value = vec![(String::from("a"), 1u32, HashMap::<String, String>::new())];
+    bench("small records: glib::ToVariant", 100000, || {
+        value.to_variant()
+    });
+    bench("small records: glib_serde::to_variant", 100000, || {
+        glib_serde::to_variant(&value).unwrap()
+    });
+
+    let value = records(1000);
+    bench("records: glib::ToVariant", iterations, || {
+        value.to_variant()
+    });
+    bench("records: glib_serde::to_variant", iterations, || {
+        glib_serde::to_variant(&value).unwrap()
+    });
+
+    let value: Vec<Vec<(u32, String)>> = (0..100)
+        .map(|i| (0..100).map(|j| (i * j, j.to_string())).collect())
+        .collect();
+    bench("nested arrays: glib::ToVariant", iterations, || {
+        value.to_variant()
+    });
+    bench("nested arrays: glib_serde::to_variant", iterations, || {
+        glib_serde::to_variant(&value).unwrap()
+    });
+
+    let value: Vec<Vec<Shape>> = (0..100)
+        .map(|i| {
+            (0..100)
+                .map(|j| match j % 3 {
+                    0 => Shape::Circle(i as f64, j as f64, 1.0),
+                    1 => Shape::Label(format!("{}:{}", i, j)),
+                    _ => Shape::Rect {
+                        x: i as f64,
+                        y: j as f64,
+                        w: 2.0,
+                        h: 3.0,
+                    },
+                })
+                .collect()
+        })
+        .collect();
+    bench("nested enums: glib_serde::to_variant", iterations, || {
+        glib_serde::to_variant(&value).unwrap()
+    });
+
+    let value: Vec<Point> = (0..10000)
+        .map(|i| Point {
+            x: i as f64,
+            y: -i as f64,
+        })
+        .collect();
+    bench("points: glib_serde::to_variant", iterations, || {
+        glib_serde::to_variant(&value).unwrap()
+    });
+    let value = glib_serde::FixedArray::from(value);
+    bench("points: FixedArray to_variant", iterations, || {
+        glib_serde::to_variant(&value).unwrap()
+    });
+    let variant = glib_serde::to_variant(&value).unwrap();
+    bench("points: glib_serde::from_variant", iterations, || {
+        glib_serde::from_variant::<Vec<Point>>(&variant).unwrap()
+    });
+    bench("points: FixedArray from_variant", iterations, || {
+        glib_serde::from_variant::<glib_serde::FixedArray<Point>>(&variant).unwrap()
+    });
+}
@@ -53,15 +53,131 @@ fn setup_and_get_repr_attr(
     repr_attr
 }
 
+/// Returns `true` for `#[glib_serde_repr(array)]`, and `false` for a plain `#[glib_serde_repr]`.
+pub(crate) fn is_array_repr(attr: &syn::Attribute) -> bool {
+    match attr.parse_meta() {
+        Ok(syn::Meta::Path(_)) => false,
+        Ok(syn::Meta::List(list)) => {
+            let mut nested = list.nested.iter();
+            match (nested.next(), nested.next()) {
+                (Some(syn::NestedMeta::Meta(syn::Meta::Path(path))), None)
+                    if path.is_ident("array") =>
+                {
+                    true
+                }
+                _ => abort!(
+                    list,
+                    "Expected #[glib_serde_repr] or #[glib_serde_repr(array)]"
+                ),
+            }
+        }
+        Ok(meta) => abort!(
+            meta,
+            "Expected #[glib_serde_repr] or #[glib_serde_repr(array)]"
+        ),
+        Err(e) => abort!(attr, "{}", e),
+    }
+}
+
+fn setup_and_get_enum_repr_attr(
+    input: &syn::DeriveInput,
+    derive_name: TokenStream,
+) -> Option<&syn::Attribute> {
+    let repr_attr = setup_and_get_repr_attr(input, derive_name);
+    if let Some(attr) = repr_attr {
+        if is_array_repr(attr) {
+            abort!(
+                attr,
+                "#[glib_serde_repr(array)] is only available for flags"
+            );
+        }
+    }
+    repr_attr
+}
+
+fn get_other_variant(input: &syn::DeriveInput) -> Option<&syn::Ident> {
+    let e = match &input.data {
+        syn::Data::Enum(e) => e,
+        _ => return None,
+    };
+    let mut other = None;
+    for variant in &e.variants {
+        for attr in &variant.attrs {
+            if !attr.path.is_ident("glib_serde") {
+                continue;
+            }
+            let list = match attr.parse_meta() {
+                Ok(syn::Meta::List(list)) => list,
+                Ok(meta) => abort!(meta, "Expected #[glib_serde(...)]"),
+                Err(e) => abort!(attr, "{}", e),
+            };
+            for nested in &list.nested {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("other") => {
+                        if other.is_some() {
+                            abort!(path, "Only one variant may be marked #[glib_serde(other)]");
+                        }
+                        if !matches!(variant.fields, syn::Fields::Unit) {
+                            abort!(path, "#[glib_serde(other)] variant must be a unit variant");
+                        }
+                        other.replace(&variant.ident);
+                    }
+                    _ => abort!(nested, "Unknown #[glib_serde] variant attribute"),
+                }
+            }
+        }
+    }
+    other
+}
+
+/// Parses `#[glib_serde(lenient)]` or `#[glib_serde(lenient, ignore_case)]`. Returns `None` if
+/// strict parsing is used, otherwise whether to ignore case.
+fn get_lenient(input: &syn::DeriveInput) -> Option<bool> {
+    let mut lenient = false;
+    let mut ignore_case = None;
+    for attr in &input.attrs {
+        if !attr.path.is_ident("glib_serde") {
+            continue;
+        }
+        let list = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list,
+            Ok(meta) => abort!(meta, "Expected #[glib_serde(...)]"),
+            Err(e) => abort!(attr, "{}", e),
+        };
+        for nested in &list.nested {
+            match nested {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("lenient") => {
+                    lenient = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("ignore_case") => {
+                    ignore_case.replace(path.clone());
+                }
+                _ => abort!(nested, "Unknown #[glib_serde] attribute"),
+            }
+        }
+    }
+    match (lenient, ignore_case) {
+        (false, Some(path)) => abort!(path, "`ignore_case` requires #[glib_serde(lenient)]"),
+        (false, None) => None,
+        (true, ignore_case) => Some(ignore_case.is_some()),
+    }
+}
+
 pub fn impl_enum_serialize(input: syn::DeriveInput) -> TokenStream {
     let ident = &input.ident;
     let crate_path = super::crate_path();
-    let repr_attr = setup_and_get_repr_attr(&input, quote! { EnumSerialize });
+    let repr_attr = setup_and_get_enum_repr_attr(&input, quote! { EnumSerialize });
 
-    let (getter, serialize) = if repr_attr.is_some() {
-        (quote! { value }, quote! { serialize_i32 })
+    let serialize = if repr_attr.is_some() {
+        quote! { serializer.serialize_i32(enum_value.value()) }
     } else {
-        (quote! { nick }, quote! { serialize_str })
+        quote! {
+            if #crate_path::serde::Serializer::is_human_readable(&serializer) {
+                serializer.serialize_str(enum_value.nick())
+            } else {
+                serializer.serialize_i32(enum_value.value())
+            }
+        }
     };
 
     quote! {
@@ -93,7 +209,7 @@ pub fn impl_enum_serialize(input: syn::DeriveInput) -> TokenStream {
                         )
                     }
                 };
-                serializer.#serialize(enum_value.#getter())
+                #serialize
             }
         }
     }
@@ -102,13 +218,44 @@ pub fn impl_enum_serialize(input: syn::DeriveInput) -> TokenStream {
 pub fn impl_enum_deserialize(input: syn::DeriveInput) -> TokenStream {
     let ident = &input.ident;
     let crate_path = super::crate_path();
-    let repr_attr = setup_and_get_repr_attr(&input, quote! { EnumDeseralize });
+    let repr_attr = setup_and_get_enum_repr_attr(&input, quote! { EnumDeseralize });
+    let other = get_other_variant(&input);
+    let lenient = get_lenient(&input);
 
-    let deserialize = if repr_attr.is_some() {
+    let deserialize = if lenient.is_some() {
+        quote! { deserialize_any }
+    } else if repr_attr.is_some() {
         quote! { deserialize_i32 }
     } else {
         quote! { deserialize_str }
     };
+    let unknown_int = match other {
+        Some(other) => quote! { ::std::result::Result::Ok(#ident::#other) },
+        None => quote! {
+            ::std::result::Result::Err(
+                 #crate_path::serde::de::Error::invalid_value(
+                     #crate_path::serde::de::Unexpected::Signed(v as i64),
+                     &self
+                )
+            )
+        },
+    };
+    let parse = match lenient {
+        Some(ignore_case) => quote! {
+            #crate_path::EnumValue::<#ident>::from_str_lenient(v, #ignore_case)
+        },
+        None => quote! { str::parse::<#crate_path::EnumValue<#ident>>(v) },
+    };
+    let (err, unknown_str) = match other {
+        Some(other) => (
+            quote! { _ },
+            quote! { ::std::result::Result::Ok(#ident::#other) },
+        ),
+        None => (
+            quote! { err },
+            quote! { ::std::result::Result::Err(#crate_path::serde::de::Error::custom(err)) },
+        ),
+    };
 
     quote! {
         impl<'de> #crate_path::serde::Deserialize<'de> for #ident {
@@ -143,15 +290,7 @@ pub fn impl_enum_deserialize(input: syn::DeriveInput) -> TokenStream {
                                     }
                                 )
                             },
-                            ::std::option::Option::None => {
-                                let ty = #crate_path::glib::EnumClass::type_(&self.0);
-                                ::std::result::Result::Err(
-                                     #crate_path::serde::de::Error::invalid_value(
-                                         #crate_path::serde::de::Unexpected::Signed(v as i64),
-                                         &self
-                                    )
-                                )
-                            }
+                            ::std::option::Option::None => #unknown_int,
                         }
                     }
 
@@ -191,25 +330,14 @@ pub fn impl_enum_deserialize(input: syn::DeriveInput) -> TokenStream {
                     where
                         E: #crate_path::serde::de::Error,
                     {
-                        match #crate_path::glib::EnumClass::value_by_nick(&self.0, v) {
-                            ::std::option::Option::Some(value) => {
-                                ::std::result::Result::Ok(
-                                    unsafe {
-                                        #crate_path::glib::translate::from_glib(
-                                            #crate_path::glib::EnumValue::value(&value)
-                                        )
-                                    }
+                        match #parse {
+                            ::std::result::Result::Ok(value) => {
+                                #crate_path::serde::de::Visitor::visit_i32(
+                                    self,
+                                    #crate_path::EnumValue::raw_value(&value)
                                 )
                             },
-                            ::std::option::Option::None => {
-                                let ty = #crate_path::glib::EnumClass::type_(&self.0);
-                                ::std::result::Result::Err(
-                                     #crate_path::serde::de::Error::invalid_value(
-                                         #crate_path::serde::de::Unexpected::Str(v),
-                                         &self
-                                    )
-                                )
-                            }
+                            ::std::result::Result::Err(#err) => #unknown_str,
                         }
                     }
                 }
@@ -219,7 +347,11 @@ pub fn impl_enum_deserialize(input: syn::DeriveInput) -> TokenStream {
 
                 match enum_class {
                     ::std::option::Option::Some(enum_class) => {
-                        deserializer.#deserialize(EnumVisitor(enum_class))
+                        if #crate_path::serde::Deserializer::is_human_readable(&deserializer) {
+                            deserializer.#deserialize(EnumVisitor(enum_class))
+                        } else {
+                            deserializer.deserialize_i32(EnumVisitor(enum_class))
+                        }
                     },
                     ::std::option::Option::None => {
                         ::std::result::Result::Err(
@@ -239,19 +371,20 @@ pub fn impl_flags_serialize(input: syn::DeriveInput) -> TokenStream {
     let crate_path = super::crate_path();
     let repr_attr = setup_and_get_repr_attr(&input, quote! { FlagsSerialize });
 
-    let serialize = if repr_attr.is_some() {
-        quote! {
-            serialize_u32(
+    let serialize = match repr_attr {
+        Some(attr) if is_array_repr(attr) => quote! {
+            let value: #crate_path::FlagsArrayValue<#ident> = self.into();
+            #crate_path::serde::Serialize::serialize(&value, serializer)
+        },
+        Some(_) => quote! {
+            serializer.serialize_u32(
                 <Self as #crate_path::glib::translate::IntoGlib>::into_glib(*self)
             )
-        }
-    } else {
-        quote! {
-            serialize_str(&{
-                let value: #crate_path::FlagsValue<#ident> = self.into();
-                value.to_string()
-            })
-        }
+        },
+        None => quote! {
+            let value: #crate_path::FlagsValue<#ident> = self.into();
+            #crate_path::serde::Serialize::serialize(&value, serializer)
+        },
     };
 
     quote! {
@@ -260,7 +393,7 @@ pub fn impl_flags_serialize(input: syn::DeriveInput) -> TokenStream {
             where
                 S: #crate_path::serde::Serializer
             {
-                serializer.#serialize
+                #serialize
             }
         }
     }
@@ -270,11 +403,23 @@ pub fn impl_flags_deserialize(input: syn::DeriveInput) -> TokenStream {
     let ident = &input.ident;
     let crate_path = super::crate_path();
     let repr_attr = setup_and_get_repr_attr(&input, quote! { FlagsDeserialize });
+    let lenient = get_lenient(&input);
 
-    let deserialize = if repr_attr.is_some() {
-        quote! { deserialize_u32 }
-    } else {
-        quote! { deserialize_str }
+    let deserialize = match repr_attr {
+        _ if lenient.is_some() => quote! { deserialize_any },
+        Some(attr) if is_array_repr(attr) => quote! { deserialize_seq },
+        Some(_) => quote! { deserialize_u32 },
+        None => quote! { deserialize_str },
+    };
+    let (parse, parse_item) = match lenient {
+        Some(ignore_case) => (
+            quote! { #crate_path::FlagsValue::<#ident>::from_str_lenient(v, #ignore_case) },
+            quote! { #crate_path::FlagsArrayValue::<#ident>::from_str_lenient(v, #ignore_case) },
+        ),
+        None => (
+            quote! { str::parse::<#crate_path::FlagsValue<#ident>>(v) },
+            quote! { #crate_path::FlagsArrayValue::<#ident>::from_strs(::std::iter::once(v)) },
+        ),
     };
 
     quote! {
@@ -283,6 +428,24 @@ pub fn impl_flags_deserialize(input: syn::DeriveInput) -> TokenStream {
             where
                 D: #crate_path::serde::Deserializer<'de>,
             {
+                fn parse_flags(
+                    v: &str
+                ) -> ::std::result::Result<
+                    #crate_path::FlagsValue<#ident>,
+                    #crate_path::ParseFlagsError
+                > {
+                    #parse
+                }
+
+                fn parse_flags_item(
+                    v: &str
+                ) -> ::std::result::Result<
+                    #crate_path::FlagsArrayValue<#ident>,
+                    #crate_path::ParseFlagsError
+                > {
+                    #parse_item
+                }
+
                 struct FlagsVisitor(#crate_path::glib::FlagsClass);
 
                 impl<'de> #crate_path::serde::de::Visitor<'de> for FlagsVisitor {
@@ -327,19 +490,44 @@ pub fn impl_flags_deserialize(input: syn::DeriveInput) -> TokenStream {
                     where
                         E: #crate_path::serde::de::Error,
                     {
-                        ::std::result::Result::map_err(
-                            ::std::result::Result::map(
-                                str::parse::<#crate_path::FlagsValue<#ident>>(v),
-                                |v| #crate_path::FlagsValue::value(&v)
-                            ),
-                            |_| {
-                                let ty = #crate_path::glib::FlagsClass::type_(&self.0);
-                                #crate_path::serde::de::Error::invalid_value(
-                                    #crate_path::serde::de::Unexpected::Str(v),
-                                    &self
+                        match parse_flags(v) {
+                            ::std::result::Result::Ok(value) => {
+                                #crate_path::serde::de::Visitor::visit_u32(
+                                    self,
+                                    #crate_path::FlagsValue::raw_value(&value)
                                 )
                             }
-                        )
+                            ::std::result::Result::Err(err) => {
+                                ::std::result::Result::Err(
+                                    #crate_path::serde::de::Error::custom(err)
+                                )
+                            }
+                        }
+                    }
+
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: #crate_path::serde::de::SeqAccess<'de>,
+                    {
+                        let mut value = 0u32;
+                        while let ::std::option::Option::Some(item) =
+                            #crate_path::serde::de::SeqAccess::next_element::<::std::string::String>(
+                                &mut seq
+                            )?
+                        {
+                            let v: &str = &item;
+                            match parse_flags_item(v) {
+                                ::std::result::Result::Ok(v) => {
+                                    value |= #crate_path::FlagsArrayValue::raw_value(&v);
+                                }
+                                ::std::result::Result::Err(err) => {
+                                    return ::std::result::Result::Err(
+                                        #crate_path::serde::de::Error::custom(err)
+                                    );
+                                }
+                            }
+                        }
+                        #crate_path::serde::de::Visitor::visit_u32(self, value)
                     }
                 }
 
@@ -348,7 +536,11 @@ pub fn impl_flags_deserialize(input: syn::DeriveInput) -> TokenStream {
 
                 match flags_class {
                     ::std::option::Option::Some(flags_class) => {
-                        deserializer.#deserialize(FlagsVisitor(flags_class))
+                        if #crate_path::serde::Deserializer::is_human_readable(&deserializer) {
+                            deserializer.#deserialize(FlagsVisitor(flags_class))
+                        } else {
+                            deserializer.deserialize_u32(FlagsVisitor(flags_class))
+                        }
                     },
                     ::std::option::Option::None => {
                         ::std::result::Result::Err(
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 
 mod enums;
+mod literals;
 mod variant_type;
 
 use proc_macro::TokenStream;
@@ -19,7 +20,19 @@ fn crate_path() -> proc_macro2::TokenStream {
 }
 
 /// Generates `VariantType` trait so this type can be serialized. Supports structs and enums.
-#[proc_macro_derive(VariantType, attributes(glib_serde_variant_index))]
+///
+/// Types without generic parameters also get a `VARIANT_TYPE_STR` constant holding their type
+/// string, which is joined at compile time. Using it is an error if a field type does not
+/// provide `VariantType::TYPE_STR`, and the build fails if the joined string is not a valid
+/// type.
+///
+/// On a `#[repr(C)]` struct whose fields all implement `FixedArrayElement`, the
+/// `#[glib_serde_fixed_size]` attribute also implements `FixedArrayElement`, so the struct can
+/// be used in a `FixedArray`. Compilation fails if the struct contains padding.
+#[proc_macro_derive(
+    VariantType,
+    attributes(glib_serde_variant_index, glib_serde_fixed_size)
+)]
 #[proc_macro_error]
 pub fn variant_type_derive(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
@@ -27,7 +40,11 @@ pub fn variant_type_derive(input: TokenStream) -> TokenStream {
 }
 
 /// Implements `serde::Deserialize` for types using `#[derive(glib::Enum)]`.
-#[proc_macro_derive(EnumDeserialize, attributes(glib_serde_repr))]
+///
+/// A unit variant marked with `#[glib_serde(other)]` is used for unknown values. Use
+/// `#[glib_serde(lenient)]` to also accept names and integers, and
+/// `#[glib_serde(lenient, ignore_case)]` to match case-insensitively.
+#[proc_macro_derive(EnumDeserialize, attributes(glib_serde_repr, glib_serde))]
 #[proc_macro_error]
 pub fn enum_deserialize_derive(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
@@ -35,7 +52,9 @@ pub fn enum_deserialize_derive(input: TokenStream) -> TokenStream {
 }
 
 /// Implements `serde::Serialize` for types using `#[derive(glib::Enum)]`.
-#[proc_macro_derive(EnumSerialize, attributes(glib_serde_repr))]
+///
+/// Serializes as the value nick, or as `i32` in formats that are not human-readable.
+#[proc_macro_derive(EnumSerialize, attributes(glib_serde_repr, glib_serde))]
 #[proc_macro_error]
 pub fn enum_serialize_derive(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
@@ -43,7 +62,10 @@ pub fn enum_serialize_derive(input: TokenStream) -> TokenStream {
 }
 
 /// Implements `serde::Deserialize` for types using `#[glib::flags]`.
-#[proc_macro_derive(FlagsDeserialize, attributes(glib_serde_repr))]
+///
+/// Use `#[glib_serde(lenient)]` to also accept names and integers, and
+/// `#[glib_serde(lenient, ignore_case)]` to match case-insensitively.
+#[proc_macro_derive(FlagsDeserialize, attributes(glib_serde_repr, glib_serde))]
 #[proc_macro_error]
 pub fn flags_deserialize_derive(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
@@ -51,9 +73,38 @@ pub fn flags_deserialize_derive(input: TokenStream) -> TokenStream {
 }
 
 /// Implements `serde::Serialize` for types using `#[glib::flags]`.
-#[proc_macro_derive(FlagsSerialize, attributes(glib_serde_repr))]
+///
+/// Serializes as the `|`-separated value nicks, or as `u32` in formats that are not
+/// human-readable.
+#[proc_macro_derive(FlagsSerialize, attributes(glib_serde_repr, glib_serde))]
 #[proc_macro_error]
 pub fn flags_serialize_derive(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
     enums::impl_flags_serialize(input).into()
 }
+
+/// Creates a `&'static glib::VariantTy` from a string literal that is checked at compile time.
+#[proc_macro]
+#[proc_macro_error]
+pub fn variant_type(input: TokenStream) -> TokenStream {
+    let lit = syn::parse_macro_input!(input as syn::LitStr);
+    literals::impl_variant_type(lit).into()
+}
+
+/// Creates a `SignatureRef<'static>` from a string literal that is checked at compile time.
+/// It can be used in const contexts.
+#[proc_macro]
+#[proc_macro_error]
+pub fn signature(input: TokenStream) -> TokenStream {
+    let lit = syn::parse_macro_input!(input as syn::LitStr);
+    literals::impl_signature(lit).into()
+}
+
+/// Creates an `ObjectPathRef<'static>` from a string literal that is checked at compile time.
+/// It can be used in const contexts.
+#[proc_macro]
+#[proc_macro_error]
+pub fn object_path(input: TokenStream) -> TokenStream {
+    let lit = syn::parse_macro_input!(input as syn::LitStr);
+    literals::impl_object_path(lit).into()
+}
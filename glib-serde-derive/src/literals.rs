@@ -0,0 +1,95 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::quote;
+
+/// Containers may be nested this deep, as in GLib.
+const MAX_DEPTH: usize = 128;
+
+/// Returns the length of the complete type at the start of `s`, following the rules of
+/// `g_variant_type_string_scan`.
+fn scan_type(s: &[u8], depth: usize) -> Option<usize> {
+    let first = *s.first()?;
+    if matches!(first, b'a' | b'm' | b'(' | b'{') && depth >= MAX_DEPTH {
+        return None;
+    }
+    match first {
+        b'a' | b'm' => Some(1 + scan_type(&s[1..], depth + 1)?),
+        b'(' => {
+            let mut len = 1;
+            while *s.get(len)? != b')' {
+                len += scan_type(&s[len..], depth + 1)?;
+            }
+            Some(len + 1)
+        }
+        b'{' => {
+            if !b"bynqiuxthdsog?".contains(s.get(1)?) {
+                return None;
+            }
+            let len = 2 + scan_type(&s[2..], depth + 1)?;
+            if *s.get(len)? == b'}' {
+                Some(len + 1)
+            } else {
+                None
+            }
+        }
+        b'b' | b'y' | b'n' | b'q' | b'i' | b'u' | b'x' | b't' | b'h' | b'd' | b's' | b'o'
+        | b'g' | b'v' | b'r' | b'*' | b'?' => Some(1),
+        _ => None,
+    }
+}
+
+fn is_type(s: &str) -> bool {
+    scan_type(s.as_bytes(), 0) == Some(s.len())
+}
+
+/// Checks a signature the way `Signature::new` does: a single complete type without maybe or
+/// indefinite types.
+fn is_signature(s: &str) -> bool {
+    s.bytes().all(|b| b"ybnqiuxthdvasog(){}".contains(&b)) && is_type(s)
+}
+
+fn is_object_path(s: &str) -> bool {
+    match s.strip_prefix('/') {
+        Some("") => true,
+        Some(rest) => rest.split('/').all(|element| {
+            !element.is_empty()
+                && element
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'_')
+        }),
+        None => false,
+    }
+}
+
+pub fn impl_variant_type(lit: syn::LitStr) -> TokenStream {
+    let crate_path = super::crate_path();
+    if !is_type(&lit.value()) {
+        abort!(lit, "Invalid variant type: {:?}", lit.value());
+    }
+    quote! {
+        #crate_path::__private::literal_variant_ty(#lit)
+    }
+}
+
+pub fn impl_signature(lit: syn::LitStr) -> TokenStream {
+    let crate_path = super::crate_path();
+    if !is_signature(&lit.value()) {
+        abort!(lit, "Invalid signature: {:?}", lit.value());
+    }
+    quote! {
+        #crate_path::__private::literal_signature(#lit)
+    }
+}
+
+pub fn impl_object_path(lit: syn::LitStr) -> TokenStream {
+    let crate_path = super::crate_path();
+    if !is_object_path(&lit.value()) {
+        abort!(lit, "Invalid object path: {:?}", lit.value());
+    }
+    quote! {
+        #crate_path::__private::literal_object_path(#lit)
+    }
+}
@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
 // SPDX-License-Identifier: MIT
 
-use proc_macro2::TokenStream;
+use proc_macro2::{Literal, TokenStream};
 use proc_macro_error::abort;
 use quote::quote;
 
@@ -9,7 +9,17 @@ pub fn impl_variant_type(input: syn::DeriveInput) -> TokenStream {
     let crate_path = super::crate_path();
     let mut repr_attr = None;
     let mut index_attr = None;
+    let mut fixed_size_attr = None;
     for attr in &input.attrs {
+        if attr.path.is_ident("glib_serde_fixed_size") {
+            if fixed_size_attr.is_some() {
+                abort!(
+                    attr,
+                    "Only one of #[glib_serde_fixed_size] may be specified"
+                );
+            }
+            fixed_size_attr.replace(attr);
+        }
         let is_index = attr.path.is_ident("glib_serde_variant_index");
         let is_repr = attr.path.is_ident("glib_serde_repr");
         if is_index || is_repr {
@@ -27,6 +37,10 @@ pub fn impl_variant_type(input: syn::DeriveInput) -> TokenStream {
         }
     }
     let name = &input.ident;
+    let mut fixed_size_impl = None;
+    let mut type_str = None;
+    let mut type_str_parts = None;
+    let validate;
     let (static_type, node) = match &input.data {
         syn::Data::Struct(s) => {
             if let Some(attr) = repr_attr {
@@ -38,17 +52,48 @@ pub fn impl_variant_type(input: syn::DeriveInput) -> TokenStream {
                     "#[glib_serde_variant_index] attribute not allowed on struct"
                 );
             }
-            impl_for_fields(&crate_path, name, &s.fields)
+            if let Some(attr) = fixed_size_attr {
+                fixed_size_impl = Some(impl_fixed_size(&crate_path, &input, attr, &s.fields));
+            }
+            validate = impl_validate_fields(&crate_path, &s.fields);
+            let (mut static_type, node) = impl_for_fields(&crate_path, name, &s.fields);
+            // field types that are generic parameters have no `VariantType` bound to rely on
+            if input.generics.params.is_empty() {
+                type_str_parts = Some(impl_type_str_parts(&crate_path, name, &s.fields));
+                type_str = Some(quote! {
+                    #crate_path::__private::JoinedTypeStr::<Self>::TYPE_STR
+                });
+                static_type = quote! {
+                    match <Self as #crate_path::VariantType>::TYPE_STR {
+                        // SAFETY: `JoinedTypeStr` fails to compile unless this is a valid type
+                        ::std::option::Option::Some(s) => ::std::borrow::Cow::Borrowed(
+                            unsafe { #crate_path::glib::VariantTy::from_str_unchecked(s) }
+                        ),
+                        ::std::option::Option::None => #static_type,
+                    }
+                };
+            }
+            (static_type, node)
         }
         syn::Data::Enum(e) => {
+            if let Some(attr) = fixed_size_attr {
+                abort!(
+                    attr,
+                    "#[glib_serde_fixed_size] attribute not allowed on enum"
+                );
+            }
             let (tag, tag_str) = repr_attr
-                .map(|_| {
+                .map(|repr_attr| {
                     for attr in &input.attrs {
                         if attr.path.is_ident("repr") {
                             abort!(attr, "#[glib_serde_repr] cannot be used with #[repr]");
                         }
                     }
-                    (quote! { INT32 }, "i")
+                    if super::enums::is_array_repr(repr_attr) {
+                        (quote! { STRING_ARRAY }, "as")
+                    } else {
+                        (quote! { INT32 }, "i")
+                    }
                 })
                 .or_else(|| index_attr.map(|_| (quote! { UINT32 }, "u")))
                 .unwrap_or_else(|| (quote! { STRING }, "s"));
@@ -58,7 +103,15 @@ pub fn impl_variant_type(input: syn::DeriveInput) -> TokenStream {
                 .iter()
                 .any(|v| !matches!(v.fields, syn::Fields::Unit));
             if has_data {
+                if tag_str == "as" {
+                    abort!(
+                        repr_attr.unwrap(),
+                        "#[glib_serde_repr(array)] attribute not allowed on enum with data"
+                    );
+                }
+                validate = impl_validate_enum(&crate_path, &input, e, tag_str);
                 let static_type_str = format!("({}v)", tag_str);
+                type_str = Some(quote! { ::std::option::Option::Some(#static_type_str) });
                 let children = e.variants.iter().map(|variant| {
                     let (_, node) = impl_for_fields(&crate_path, name, &variant.fields);
                     node
@@ -83,6 +136,19 @@ pub fn impl_variant_type(input: syn::DeriveInput) -> TokenStream {
                     ),
                 )
             } else {
+                validate = quote! {
+                    const VALIDATES_VALUES: bool = true;
+
+                    fn validate_value(
+                        variant: &#crate_path::glib::Variant,
+                    ) -> ::std::result::Result<(), #crate_path::Error> {
+                        use #crate_path::__private::{
+                            ValidateWithDeserialize as _, ValidateWithoutDeserialize as _,
+                        };
+                        (&#crate_path::__private::DeserializeProbe::<Self>::new()).validate(variant)
+                    }
+                };
+                type_str = Some(quote! { ::std::option::Option::Some(#tag_str) });
                 (
                     quote! { ::std::borrow::Cow::Borrowed(#tag) },
                     impl_lazy(
@@ -106,6 +172,45 @@ pub fn impl_variant_type(input: syn::DeriveInput) -> TokenStream {
         }
     };
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let (type_str_const, type_str_impl) = match type_str {
+        Some(type_str) => {
+            let doc = format!(
+                "The GVariant type string of `{}`, for use in const contexts.",
+                name
+            );
+            let message = format!(
+                "{} has a field whose type string is unknown or too long to join at compile time",
+                name
+            );
+            // evaluates the type string even when nothing uses it, so invalid ones always fail
+            let check = input.generics.params.is_empty().then(|| {
+                quote! {
+                    const _: ::std::option::Option<&'static str> =
+                        <#name as #crate_path::VariantType>::TYPE_STR;
+                }
+            });
+            (
+                quote! {
+                    const TYPE_STR: ::std::option::Option<&'static str> = #type_str;
+                },
+                quote! {
+                    impl #impl_generics #name #ty_generics #where_clause {
+                        #[doc = #doc]
+                        pub const VARIANT_TYPE_STR: &'static str =
+                            match <Self as #crate_path::VariantType>::TYPE_STR {
+                                ::std::option::Option::Some(s) => s,
+                                ::std::option::Option::None => ::std::panic!(#message),
+                            };
+                    }
+
+                    #check
+
+                    #type_str_parts
+                },
+            )
+        }
+        None => (quote! {}, quote! {}),
+    };
 
     quote! {
         impl #impl_generics #crate_path::glib::StaticVariantType for #name #ty_generics #where_clause {
@@ -115,10 +220,196 @@ pub fn impl_variant_type(input: syn::DeriveInput) -> TokenStream {
         }
 
         impl #impl_generics #crate_path::VariantType for #name #ty_generics #where_clause {
+            #type_str_const
+
             fn variant_type() -> ::std::borrow::Cow<'static, #crate_path::VariantTypeNode<'static>> {
                 #node
             }
+
+            #validate
+        }
+
+        #type_str_impl
+
+        #fixed_size_impl
+    }
+}
+
+/// Builds `VariantType::validate_value` for a struct, checking each field.
+fn impl_validate_fields(crate_path: &TokenStream, fields: &syn::Fields) -> TokenStream {
+    let types = fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    let body = match types.as_slice() {
+        [] => return quote! {},
+        [ty] => quote! { <#ty as #crate_path::VariantType>::validate_value(variant) },
+        _ => {
+            let indices = (0..types.len()).map(Literal::usize_unsuffixed);
+            quote! {
+                #(
+                    <#types as #crate_path::VariantType>::validate_value(
+                        &#crate_path::glib::Variant::child_value(variant, #indices)
+                    )?;
+                )*
+                ::std::result::Result::Ok(())
+            }
         }
+    };
+    quote! {
+        const VALIDATES_VALUES: bool =
+            false #(|| <#types as #crate_path::VariantType>::VALIDATES_VALUES)*;
+
+        fn validate_value(
+            variant: &#crate_path::glib::Variant,
+        ) -> ::std::result::Result<(), #crate_path::Error> {
+            #body
+        }
+    }
+}
+
+/// Builds `VariantType::validate_value` for an enum with data, checking the tag and then the
+/// payload of the variant it names. The payload is boxed, so its type is checked here too.
+fn impl_validate_enum(
+    crate_path: &TokenStream,
+    input: &syn::DeriveInput,
+    e: &syn::DataEnum,
+    tag_str: &str,
+) -> TokenStream {
+    let name = input.ident.to_string();
+    let indices = (0..e.variants.len() as i64)
+        .map(Literal::i64_unsuffixed)
+        .collect::<Vec<_>>();
+    let index = match tag_str {
+        "s" => {
+            let len = e.variants.len();
+            quote! {
+                {
+                    use #crate_path::__private::{
+                        ValidateWithDeserialize as _, ValidateWithoutDeserialize as _,
+                    };
+                    let probe = &#crate_path::__private::DeserializeProbe::<Self>::new();
+                    let tag = #crate_path::glib::Variant::str(&tag).unwrap_or_default();
+                    match probe.variant_index(tag, #len)? {
+                        ::std::option::Option::Some(index) => index as i64,
+                        ::std::option::Option::None => return probe.validate(variant),
+                    }
+                }
+            }
+        }
+        "u" => quote! { i64::from(#crate_path::glib::Variant::get::<u32>(&tag).unwrap()) },
+        _ => quote! { i64::from(#crate_path::glib::Variant::get::<i32>(&tag).unwrap()) },
+    };
+    let checks = e.variants.iter().map(|variant| {
+        let types = variant.fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+        match (&variant.fields, types.as_slice()) {
+            (syn::Fields::Unnamed(_), [ty]) => quote! {
+                #crate_path::validate::<#ty>(&value)
+            },
+            _ => {
+                let len = types.len();
+                let children = (0..len).map(Literal::usize_unsuffixed);
+                quote! {
+                    #crate_path::__private::check_tuple(&value, #len)?;
+                    #(
+                        #crate_path::validate::<#types>(
+                            &#crate_path::glib::Variant::child_value(&value, #children)
+                        )?;
+                    )*
+                    ::std::result::Result::Ok(())
+                }
+            }
+        }
+    });
+    quote! {
+        const VALIDATES_VALUES: bool = true;
+
+        fn validate_value(
+            variant: &#crate_path::glib::Variant,
+        ) -> ::std::result::Result<(), #crate_path::Error> {
+            let tag = #crate_path::glib::Variant::child_value(variant, 0);
+            let value = #crate_path::glib::Variant::child_value(variant, 1);
+            let value = #crate_path::glib::Variant::as_variant(&value).unwrap();
+            match #index {
+                #(#indices => { #checks })*
+                index => ::std::result::Result::Err(#crate_path::Error::Custom(
+                    ::std::format!("Invalid variant index {} for {}", index, #name)
+                )),
+            }
+        }
+    }
+}
+
+/// Lists the parts of the type string of a struct, which `JoinedTypeStr` joins at compile time.
+fn impl_type_str_parts(
+    crate_path: &TokenStream,
+    name: &syn::Ident,
+    fields: &syn::Fields,
+) -> TokenStream {
+    let field_parts = fields.iter().map(|field| {
+        let ty = &field.ty;
+        quote! { <#ty as #crate_path::VariantType>::TYPE_STR }
+    });
+    let parts = match fields {
+        syn::Fields::Unit => quote! { ::std::option::Option::Some("()") },
+        _ if fields.len() == 1 => quote! { #(#field_parts)* },
+        _ => quote! {
+            ::std::option::Option::Some("("),
+            #(#field_parts,)*
+            ::std::option::Option::Some(")")
+        },
+    };
+    quote! {
+        impl #crate_path::__private::TypeStrParts for #name {
+            const TYPE_STR_PARTS: &'static [::std::option::Option<&'static str>] = &[#parts];
+        }
+    }
+}
+
+fn impl_fixed_size(
+    crate_path: &TokenStream,
+    input: &syn::DeriveInput,
+    attr: &syn::Attribute,
+    fields: &syn::Fields,
+) -> TokenStream {
+    if !input.generics.params.is_empty() {
+        abort!(
+            input.generics,
+            "#[glib_serde_fixed_size] attribute not allowed on generic struct"
+        );
+    }
+    if fields.is_empty() {
+        abort!(
+            attr,
+            "#[glib_serde_fixed_size] attribute not allowed on unit struct"
+        );
+    }
+    let is_repr_c = input.attrs.iter().any(|attr| {
+        if !attr.path.is_ident("repr") {
+            return false;
+        }
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list.nested.iter().any(|nested| {
+                matches!(nested, syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("C"))
+            }),
+            _ => false,
+        }
+    });
+    if !is_repr_c {
+        abort!(attr, "#[glib_serde_fixed_size] requires #[repr(C)]");
+    }
+    let name = &input.ident;
+    let types = fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    let message = format!("{} contains padding", name);
+    quote! {
+        unsafe impl #crate_path::FixedArrayElement for #name {}
+
+        const _: fn() = || {
+            fn assert_element<T: #crate_path::FixedArrayElement>() {}
+            #(assert_element::<#types>();)*
+        };
+
+        const _: () = assert!(
+            ::std::mem::size_of::<#name>() == 0 #(+ ::std::mem::size_of::<#types>())*,
+            #message
+        );
     }
 }
 
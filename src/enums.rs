@@ -1,13 +1,70 @@
 // SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
 // SPDX-License-Identifier: MIT
 
+use crate::GType;
 use glib::{
     translate::{FromGlib, IntoGlib},
-    EnumClass, StaticType,
+    EnumClass, StaticType, ToVariant,
 };
 use std::marker::PhantomData;
 
-/// Wrapper type for FFI enums. Serializes as `str`.
+pub(crate) fn write_valid_nicks(f: &mut std::fmt::Formatter, nicks: &[String]) -> std::fmt::Result {
+    if nicks.is_empty() {
+        return Ok(());
+    }
+    f.write_str(", expected one of ")?;
+    for (i, nick) in nicks.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "`{}`", nick)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn enum_to_string(class: &EnumClass, value: i32) -> String {
+    match class.value(value) {
+        Some(v) => v.nick().to_owned(),
+        None => value.to_string(),
+    }
+}
+
+pub(crate) fn enum_from_str(class: &EnumClass, s: &str) -> Option<i32> {
+    class
+        .value_by_nick(s)
+        .map(|v| v.value())
+        .or_else(|| s.parse().ok())
+}
+
+pub(crate) fn enum_from_str_lenient(class: &EnumClass, s: &str, ignore_case: bool) -> Option<i32> {
+    let eq = |a: &str| {
+        if ignore_case {
+            a.eq_ignore_ascii_case(s)
+        } else {
+            a == s
+        }
+    };
+    let values = class.values();
+    values
+        .iter()
+        .find(|v| eq(v.nick()))
+        .or_else(|| values.iter().find(|v| eq(v.name())))
+        .map(|v| v.value())
+        .or_else(|| s.parse().ok())
+}
+
+fn check_enum_value(class: &EnumClass, value: i32) -> Result<&glib::EnumValue, crate::Error> {
+    class.value(value).ok_or(crate::Error::InvalidEnumValue {
+        type_: class.type_(),
+        value,
+    })
+}
+
+/// Wrapper type for FFI enums. Serializes as `str`, or as `i32` in formats that are not
+/// human-readable.
+///
+/// Values not registered in the enum class are preserved, and serialize as their decimal integer
+/// string.
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
 pub struct EnumValue<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> {
     value: i32,
@@ -22,10 +79,39 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> EnumValue<T> {
     pub fn value(&self) -> T {
         unsafe { T::from_glib(self.value) }
     }
+    /// Returns the value, or `None` if it is not registered in the enum class.
+    pub fn try_value(&self) -> Option<T> {
+        check_enum_value(&Self::enum_class(), self.value).ok()?;
+        Some(self.value())
+    }
+    #[inline]
+    pub fn from_raw(value: i32) -> Self {
+        Self {
+            value,
+            phantom: PhantomData,
+        }
+    }
+    #[inline]
+    pub fn raw_value(&self) -> i32 {
+        self.value
+    }
     pub fn enum_class() -> EnumClass {
         EnumClass::new(T::static_type())
             .unwrap_or_else(|| panic!("Invalid enum {}", T::static_type()))
     }
+    /// Converts to a variant, failing if the value is not registered in the enum class.
+    pub fn try_to_variant(&self) -> Result<glib::Variant, crate::Error> {
+        let class = Self::enum_class();
+        check_enum_value(&class, self.value)?;
+        Ok(glib::ToVariant::to_variant(self))
+    }
+    /// Parses a value by nick, then by name, then as an integer.
+    pub fn from_str_lenient(s: &str, ignore_case: bool) -> Result<Self, ParseEnumError> {
+        let class = Self::enum_class();
+        let value = enum_from_str_lenient(&class, s, ignore_case)
+            .ok_or_else(|| ParseEnumError::new(&class, s))?;
+        Ok(Self::from_raw(value))
+    }
 }
 
 impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32> + Default> Default for EnumValue<T> {
@@ -65,15 +151,7 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> glib::StaticVaria
 
 impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> glib::ToVariant for EnumValue<T> {
     fn to_variant(&self) -> glib::Variant {
-        let class = Self::enum_class();
-        let value = class.value(self.value).unwrap_or_else(|| {
-            panic!(
-                "Invalid value '{}' for enum {}",
-                self.value,
-                T::static_type()
-            )
-        });
-        value.nick().to_variant()
+        enum_to_string(&Self::enum_class(), self.value).to_variant()
     }
 }
 
@@ -82,21 +160,25 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> glib::FromVariant
         let class = Self::enum_class();
         variant
             .str()
-            .and_then(|s| class.value_by_nick(s))
-            .map(|v| Self {
-                value: v.value(),
-                phantom: PhantomData,
-            })
+            .and_then(|s| enum_from_str(&class, s))
+            .map(Self::from_raw)
     }
 }
 
-impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> super::VariantType for EnumValue<T> {}
+impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> super::VariantType for EnumValue<T> {
+    const TYPE_STR: Option<&'static str> = Some("s");
+    const VALIDATES_VALUES: bool = true;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        let nick = variant.str().unwrap_or_default();
+        <Self as std::str::FromStr>::from_str(nick)
+            .map(drop)
+            .map_err(|e| crate::Error::Custom(e.to_string()))
+    }
+}
 
 impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> std::fmt::Display for EnumValue<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let class = Self::enum_class();
-        let value = class.value(self.value).ok_or(std::fmt::Error)?;
-        value.nick().fmt(f)
+        enum_to_string(&Self::enum_class(), self.value).fmt(f)
     }
 }
 
@@ -105,13 +187,8 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> std::str::FromStr
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let class = Self::enum_class();
-        let value = class.value_by_nick(s).ok_or_else(|| ParseEnumError {
-            token: s.to_owned(),
-        })?;
-        Ok(Self {
-            value: value.value(),
-            phantom: PhantomData,
-        })
+        let value = enum_from_str(&class, s).ok_or_else(|| ParseEnumError::new(&class, s))?;
+        Ok(Self::from_raw(value))
     }
 }
 
@@ -122,15 +199,11 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> serde::ser::Seria
     where
         S: serde::ser::Serializer,
     {
-        let class = Self::enum_class();
-        let value = class.value(self.value).ok_or_else(|| {
-            serde::ser::Error::custom(format!(
-                "Invalid value '{}' for enum {}",
-                self.value,
-                T::static_type()
-            ))
-        })?;
-        serializer.serialize_str(value.nick())
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&enum_to_string(&Self::enum_class(), self.value))
+        } else {
+            serializer.serialize_i32(self.value)
+        }
     }
 }
 
@@ -149,22 +222,31 @@ impl<'de, T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> serde::de::D
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 write!(formatter, "a valid string for enum {}", self.0.type_())
             }
+            fn visit_i32<E>(self, v: i32) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(v)
+            }
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
             {
-                let value = self.0.value_by_nick(v).ok_or_else(|| {
+                enum_from_str(&self.0, v).ok_or_else(|| {
                     serde::de::Error::invalid_value(
                         serde::de::Unexpected::Str(v),
                         &format!("valid string for enum {}", self.0.type_()).as_str(),
                     )
-                })?;
-                Ok(value.value())
+                })
             }
         }
 
         let class = Self::enum_class();
-        let value = deserializer.deserialize_str(EnumVisitor(class))?;
+        let value = if deserializer.is_human_readable() {
+            deserializer.deserialize_str(EnumVisitor(class))?
+        } else {
+            deserializer.deserialize_i32(EnumVisitor(class))?
+        };
         Ok(EnumValue {
             value,
             phantom: PhantomData,
@@ -173,6 +255,8 @@ impl<'de, T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> serde::de::D
 }
 
 /// Wrapper type for FFI enums. Serializes as `i32`.
+///
+/// Values not registered in the enum class are preserved.
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
 pub struct EnumReprValue<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> {
     value: i32,
@@ -187,10 +271,39 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> EnumReprValue<T>
     pub fn value(&self) -> T {
         unsafe { T::from_glib(self.value) }
     }
+    /// Returns the value, or `None` if it is not registered in the enum class.
+    pub fn try_value(&self) -> Option<T> {
+        check_enum_value(&Self::enum_class(), self.value).ok()?;
+        Some(self.value())
+    }
+    #[inline]
+    pub fn from_raw(value: i32) -> Self {
+        Self {
+            value,
+            phantom: PhantomData,
+        }
+    }
+    #[inline]
+    pub fn raw_value(&self) -> i32 {
+        self.value
+    }
     pub fn enum_class() -> EnumClass {
         EnumClass::new(T::static_type())
             .unwrap_or_else(|| panic!("Invalid enum {}", T::static_type()))
     }
+    /// Converts to a variant, failing if the value is not registered in the enum class.
+    pub fn try_to_variant(&self) -> Result<glib::Variant, crate::Error> {
+        let class = Self::enum_class();
+        check_enum_value(&class, self.value)?;
+        Ok(glib::ToVariant::to_variant(self))
+    }
+    /// Parses a value by nick, then by name, then as an integer.
+    pub fn from_str_lenient(s: &str, ignore_case: bool) -> Result<Self, ParseEnumError> {
+        let class = Self::enum_class();
+        let value = enum_from_str_lenient(&class, s, ignore_case)
+            .ok_or_else(|| ParseEnumError::new(&class, s))?;
+        Ok(Self::from_raw(value))
+    }
 }
 
 impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32> + Default> Default
@@ -236,15 +349,7 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> glib::ToVariant
     for EnumReprValue<T>
 {
     fn to_variant(&self) -> glib::Variant {
-        let class = Self::enum_class();
-        let value = class.value(self.value).unwrap_or_else(|| {
-            panic!(
-                "Invalid value '{}' for enum {}",
-                self.value,
-                T::static_type()
-            )
-        });
-        value.value().to_variant()
+        self.value.to_variant()
     }
 }
 
@@ -252,14 +357,7 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> glib::FromVariant
     for EnumReprValue<T>
 {
     fn from_variant(variant: &glib::Variant) -> Option<Self> {
-        let class = Self::enum_class();
-        variant
-            .get::<i32>()
-            .and_then(|s| class.value(s))
-            .map(|v| Self {
-                value: v.value(),
-                phantom: PhantomData,
-            })
+        variant.get::<i32>().map(Self::from_raw)
     }
 }
 
@@ -272,9 +370,7 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> std::fmt::Display
     for EnumReprValue<T>
 {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let class = Self::enum_class();
-        let value = class.value(self.value).ok_or(std::fmt::Error)?;
-        value.nick().fmt(f)
+        enum_to_string(&Self::enum_class(), self.value).fmt(f)
     }
 }
 
@@ -285,13 +381,8 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> std::str::FromStr
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let class = Self::enum_class();
-        let value = class.value_by_nick(s).ok_or_else(|| ParseEnumError {
-            token: s.to_owned(),
-        })?;
-        Ok(Self {
-            value: value.value(),
-            phantom: PhantomData,
-        })
+        let value = enum_from_str(&class, s).ok_or_else(|| ParseEnumError::new(&class, s))?;
+        Ok(Self::from_raw(value))
     }
 }
 
@@ -302,15 +393,7 @@ impl<T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> serde::ser::Seria
     where
         S: serde::ser::Serializer,
     {
-        let class = Self::enum_class();
-        let value = class.value(self.value).ok_or_else(|| {
-            serde::ser::Error::custom(format!(
-                "Invalid value '{}' for enum {}",
-                self.value,
-                T::static_type()
-            ))
-        })?;
-        serializer.serialize_i32(value.value())
+        serializer.serialize_i32(self.value)
     }
 }
 
@@ -333,13 +416,7 @@ impl<'de, T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> serde::de::D
             where
                 E: serde::de::Error,
             {
-                let value = self.0.value(v).ok_or_else(|| {
-                    serde::de::Error::invalid_value(
-                        serde::de::Unexpected::Signed(v as i64),
-                        &format!("valid integer for enum {}", self.0.type_()).as_str(),
-                    )
-                })?;
-                Ok(value.value())
+                Ok(v)
             }
         }
 
@@ -352,13 +429,174 @@ impl<'de, T: StaticType + FromGlib<i32> + IntoGlib<GlibType = i32>> serde::de::D
     }
 }
 
+/// Wrapper type for enums only known at runtime. Serializes as `(ss)`, containing the type name
+/// and the value nick. The nick is replaced by `i32` in formats that are not human-readable.
+///
+/// Values not registered in the enum class are preserved, and serialize as their decimal integer
+/// string. If `type_` is not an enum type, the value is always written as that integer string,
+/// and read back from one.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct DynEnumValue {
+    pub type_: glib::Type,
+    pub value: i32,
+}
+
+impl DynEnumValue {
+    pub fn new(type_: glib::Type, value: i32) -> Self {
+        Self { type_, value }
+    }
+    pub fn enum_class(&self) -> Option<EnumClass> {
+        EnumClass::new(self.type_)
+    }
+    /// Converts to a variant, failing if the type is not an enum or the value is not registered in
+    /// the enum class.
+    pub fn try_to_variant(&self) -> Result<glib::Variant, crate::Error> {
+        let class = self
+            .enum_class()
+            .ok_or_else(|| crate::Error::Custom(format!("No such enum {}", self.type_)))?;
+        let value = check_enum_value(&class, self.value)?;
+        Ok((self.type_.name(), value.nick()).to_variant())
+    }
+    fn to_nick(self) -> String {
+        match self.enum_class() {
+            Some(class) => enum_to_string(&class, self.value),
+            None => self.value.to_string(),
+        }
+    }
+    /// Parses a string written by `to_nick`.
+    fn from_nick(type_: glib::Type, nick: &str) -> Result<Self, crate::Error> {
+        let value = match EnumClass::new(type_) {
+            Some(class) => enum_from_str(&class, nick).ok_or_else(|| {
+                crate::Error::Custom(ParseEnumError::new(&class, nick).to_string())
+            })?,
+            None => nick
+                .parse()
+                .map_err(|_| crate::Error::Custom(format!("No such enum {}", type_)))?,
+        };
+        Ok(Self::new(type_, value))
+    }
+}
+
+impl glib::StaticVariantType for DynEnumValue {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(unsafe { glib::VariantTy::from_str_unchecked("(ss)") })
+    }
+}
+
+impl glib::ToVariant for DynEnumValue {
+    fn to_variant(&self) -> glib::Variant {
+        (self.type_.name(), self.to_nick()).to_variant()
+    }
+}
+
+impl glib::FromVariant for DynEnumValue {
+    fn from_variant(variant: &glib::Variant) -> Option<Self> {
+        let (type_, nick) = variant.get::<(GType, String)>()?;
+        Self::from_nick(type_.into(), &nick).ok()
+    }
+}
+
+impl super::VariantType for DynEnumValue {
+    const TYPE_STR: Option<&'static str> = Some("(ss)");
+    const VALIDATES_VALUES: bool = true;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        let type_ = variant.child_value(0);
+        let type_ = type_.str().unwrap_or_default();
+        let type_ = glib::Type::from_name(type_)
+            .ok_or_else(|| crate::Error::Custom(format!("No such type {}", type_)))?;
+        let nick = variant.child_value(1);
+        Self::from_nick(type_, nick.str().unwrap_or_default()).map(drop)
+    }
+}
+
+impl std::fmt::Display for DynEnumValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.to_nick().fmt(f)
+    }
+}
+
+impl serde::ser::Serialize for DynEnumValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        use serde::ser::SerializeTuple;
+
+        let human_readable = serializer.is_human_readable();
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&GType::from(self.type_))?;
+        if human_readable {
+            tuple.serialize_element(&self.to_nick())?;
+        } else {
+            tuple.serialize_element(&self.value)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> serde::de::Deserialize<'de> for DynEnumValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        struct EnumVisitor {
+            human_readable: bool,
+        }
+
+        impl<'de> serde::de::Visitor<'de> for EnumVisitor {
+            type Value = DynEnumValue;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a tuple of enum type name and value string")
+            }
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let type_ = seq
+                    .next_element::<GType>()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+                if !self.human_readable {
+                    let value = seq
+                        .next_element::<i32>()?
+                        .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                    return Ok(DynEnumValue::new(type_.into(), value));
+                }
+                let nick = seq
+                    .next_element::<String>()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                DynEnumValue::from_nick(type_.into(), &nick).map_err(serde::de::Error::custom)
+            }
+        }
+
+        let human_readable = deserializer.is_human_readable();
+        deserializer.deserialize_tuple(2, EnumVisitor { human_readable })
+    }
+}
+
 /// Error type for [`EnumValue`].
+#[derive(Debug)]
 pub struct ParseEnumError {
     token: String,
+    nicks: Vec<String>,
+}
+
+impl ParseEnumError {
+    fn new(class: &EnumClass, token: &str) -> Self {
+        Self {
+            token: token.to_owned(),
+            nicks: class.values().iter().map(|v| v.nick().to_owned()).collect(),
+        }
+    }
+    /// Nicks of all values registered in the enum class.
+    pub fn valid_nicks(&self) -> &[String] {
+        &self.nicks
+    }
 }
 
 impl std::fmt::Display for ParseEnumError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Invalid enum value `{}`", self.token)
+        write!(f, "Invalid enum value `{}`", self.token)?;
+        write_valid_nicks(f, &self.nicks)
     }
 }
@@ -10,13 +10,30 @@ use glib::{variant::VariantTypeMismatchError, BoolError};
 pub enum Error {
     Bool(BoolError),
     Mismatch(VariantTypeMismatchError),
+    TypeMismatch {
+        actual: glib::VariantType,
+        expected: glib::VariantType,
+        path: Vec<usize>,
+    },
     Int(TryFromIntError),
     StrMismatch(glib::VariantType),
     InvalidTag(glib::VariantType),
     UnsupportedType(glib::VariantType),
     ExpectedChar(String),
     InvalidType(String),
-    LengthMismatch { actual: usize, expected: usize },
+    LengthMismatch {
+        actual: usize,
+        expected: usize,
+    },
+    InvalidEnumValue {
+        type_: glib::Type,
+        value: i32,
+    },
+    InvalidFlagsValue {
+        type_: glib::Type,
+        value: u32,
+    },
+    NonNormalData(glib::VariantType),
     Custom(String),
 }
 
@@ -26,6 +43,28 @@ impl Display for Error {
             Self::Bool(e) => e.fmt(f),
             Self::Mismatch(e) => e.fmt(f),
             Self::Int(e) => e.fmt(f),
+            Self::TypeMismatch {
+                actual,
+                expected,
+                path,
+            } => {
+                write!(
+                    f,
+                    "Type mismatch: Expected '{}', got '{}'",
+                    expected, actual
+                )?;
+                if !path.is_empty() {
+                    let (actual, expected) = (subtype(actual, path), subtype(expected, path));
+                    write!(
+                        f,
+                        " at child {:?}: Expected '{}', got '{}'",
+                        path,
+                        expected.map(|t| t.as_str()).unwrap_or("nothing"),
+                        actual.map(|t| t.as_str()).unwrap_or("nothing"),
+                    )?;
+                }
+                Ok(())
+            }
             Self::StrMismatch(actual) => {
                 write!(
                     f,
@@ -56,11 +95,39 @@ impl Display for Error {
                     expected, actual
                 )
             }
+            Self::InvalidEnumValue { type_, value } => {
+                write!(f, "Invalid value '{}' for enum {}", value, type_)
+            }
+            Self::InvalidFlagsValue { type_, value } => {
+                write!(f, "Invalid bits '{:#x}' for flags {}", value, type_)
+            }
+            Self::NonNormalData(ty) => {
+                write!(f, "Serialized data for type '{}' is not in normal form", ty)
+            }
             Self::Custom(e) => e.fmt(f),
         }
     }
 }
 
+/// Walks `path` through the children of `ty`, returning `None` if a child is missing.
+fn subtype<'t>(ty: &'t glib::VariantTy, path: &[usize]) -> Option<&'t glib::VariantTy> {
+    let mut ty = ty;
+    for &index in path {
+        ty = if ty.is_array() || ty.is_maybe() {
+            ty.element()
+        } else if (ty.is_tuple() && ty != glib::VariantTy::TUPLE) || ty.is_dict_entry() {
+            let mut child = ty.first();
+            for _ in 0..index {
+                child = child?.next();
+            }
+            child?
+        } else {
+            return None;
+        };
+    }
+    Some(ty)
+}
+
 impl From<BoolError> for Error {
     fn from(e: BoolError) -> Self {
         Self::Bool(e)
@@ -0,0 +1,202 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, VariantType};
+use gio::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+};
+
+thread_local! {
+    static FD_LIST: RefCell<Option<gio::UnixFDList>> = const { RefCell::new(None) };
+}
+
+struct FdListGuard(Option<gio::UnixFDList>);
+
+impl Drop for FdListGuard {
+    fn drop(&mut self) {
+        FD_LIST.with(|l| l.replace(self.0.take()));
+    }
+}
+
+fn with_fd_list<R>(list: gio::UnixFDList, func: impl FnOnce() -> R) -> R {
+    let _guard = FdListGuard(FD_LIST.with(|l| l.replace(Some(list))));
+    func()
+}
+
+fn serialize_fd<S>(fd: BorrowedFd<'_>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let handle = FD_LIST.with(|l| match &*l.borrow() {
+        Some(list) => list
+            .append(fd.as_raw_fd())
+            .map_err(serde::ser::Error::custom),
+        None => Err(serde::ser::Error::custom(
+            "File descriptors can only be serialized with to_variant_with_fds",
+        )),
+    })?;
+    serializer.serialize_i32(handle)
+}
+
+fn deserialize_fd<'de, D>(deserializer: D) -> Result<OwnedFd, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct FdVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for FdVisitor {
+        type Value = OwnedFd;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a file descriptor handle")
+        }
+        fn visit_i32<E>(self, v: i32) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            let fd = FD_LIST.with(|l| match &*l.borrow() {
+                Some(list) => list.get(v).map_err(serde::de::Error::custom),
+                None => Err(serde::de::Error::custom(
+                    "File descriptors can only be deserialized with from_variant_with_fds",
+                )),
+            })?;
+            Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+        }
+    }
+
+    deserializer.deserialize_i32(FdVisitor)
+}
+
+/// Serializes `T` into a [`glib::Variant`](struct@glib::Variant). Any [`Fd`] values are
+/// appended to the returned [`gio::UnixFDList`], and serialized as `h` indices into it.
+pub fn to_variant_with_fds<T>(value: &T) -> Result<(glib::Variant, gio::UnixFDList), Error>
+where
+    T: Serialize + VariantType,
+{
+    let list = gio::UnixFDList::new();
+    let variant = with_fd_list(list.clone(), || crate::to_variant(value))?;
+    Ok((variant, list))
+}
+
+/// Deserializes `T` from a [`glib::Variant`](struct@glib::Variant). Any [`Fd`] values are
+/// duplicated from `fds`, using the `h` values as indices.
+pub fn from_variant_with_fds<'de, T>(
+    variant: &'de glib::Variant,
+    fds: &gio::UnixFDList,
+) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    with_fd_list(fds.clone(), || crate::from_variant(variant))
+}
+
+/// Wrapper type for [`OwnedFd`]. Serializes as `h`, containing an index into the
+/// [`gio::UnixFDList`] used by [`to_variant_with_fds`] and [`from_variant_with_fds`].
+///
+/// Serializing fails outside of those functions.
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct Fd(OwnedFd);
+
+impl Fd {
+    pub fn from_borrowed(fd: BorrowedFd<'_>) -> std::io::Result<Self> {
+        fd.try_clone_to_owned().map(Self)
+    }
+    pub fn into_inner(self) -> OwnedFd {
+        self.0
+    }
+}
+
+impl std::ops::Deref for Fd {
+    type Target = OwnedFd;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<OwnedFd> for Fd {
+    fn from(fd: OwnedFd) -> Self {
+        Self(fd)
+    }
+}
+
+impl From<Fd> for OwnedFd {
+    fn from(fd: Fd) -> Self {
+        fd.0
+    }
+}
+
+impl AsFd for Fd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl AsRawFd for Fd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for Fd {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl glib::StaticVariantType for Fd {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(glib::VariantTy::HANDLE)
+    }
+}
+
+impl VariantType for Fd {
+    const TYPE_STR: Option<&'static str> = Some("h");
+}
+
+impl Serialize for Fd {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialize_fd(self.0.as_fd(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Fd {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_fd(deserializer).map(Self)
+    }
+}
+
+/// Serializes [`OwnedFd`], [`BorrowedFd`] or any other [`AsFd`] as `h`, the same way as [`Fd`].
+///
+/// Use with `#[serde(with = "glib_serde::fd_handle")]`, or with `serialize_with` for borrowed
+/// fields. The containing type has to implement [`VariantType`] by hand, as the standard fd
+/// types have no variant type of their own.
+pub mod fd_handle {
+    use serde::{Deserializer, Serializer};
+    use std::os::unix::io::{AsFd, OwnedFd};
+
+    pub fn serialize<F, S>(fd: &F, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        F: AsFd,
+        S: Serializer,
+    {
+        super::serialize_fd(fd.as_fd(), serializer)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: From<OwnedFd>,
+    {
+        super::deserialize_fd(deserializer).map(Into::into)
+    }
+}
@@ -1,13 +1,107 @@
 // SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
 // SPDX-License-Identifier: MIT
 
+use crate::GType;
 use glib::{
     translate::{FromGlib, IntoGlib},
-    FlagsClass, StaticType,
+    FlagsClass, StaticType, ToVariant,
 };
 use std::marker::PhantomData;
 
-/// Wrapper type for FFI flags. Serializes as `str`.
+pub(crate) fn flags_to_strings(class: &FlagsClass, mut value: u32) -> Vec<String> {
+    let mut items = Vec::new();
+    for val in class.values() {
+        let v = val.value();
+        if (value & v) == v {
+            value &= !v;
+            items.push(val.nick().to_owned());
+        }
+    }
+    if value != 0 {
+        items.push(format!("{:#x}", value));
+    }
+    items
+}
+
+pub(crate) fn flags_to_string(class: &FlagsClass, value: u32) -> String {
+    flags_to_strings(class, value).join("|")
+}
+
+pub(crate) fn flags_from_strs<'a>(
+    class: &FlagsClass,
+    items: impl IntoIterator<Item = &'a str>,
+) -> Result<u32, ParseFlagsError> {
+    let mut value = 0u32;
+    for item in items {
+        value |= class
+            .value_by_nick(item)
+            .map(|v| v.value())
+            .or_else(|| parse_flags_bits(item))
+            .ok_or_else(|| ParseFlagsError::new(class, item))?;
+    }
+    Ok(value)
+}
+
+pub(crate) fn flags_from_str(class: &FlagsClass, s: &str) -> Result<u32, ParseFlagsError> {
+    if s.is_empty() {
+        return Ok(0);
+    }
+    flags_from_strs(class, s.split('|'))
+}
+
+pub(crate) fn flags_from_strs_lenient<'a>(
+    class: &FlagsClass,
+    items: impl IntoIterator<Item = &'a str>,
+    ignore_case: bool,
+) -> Result<u32, ParseFlagsError> {
+    let values = class.values();
+    let mut value = 0u32;
+    for item in items {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+        let eq = |a: &str| {
+            if ignore_case {
+                a.eq_ignore_ascii_case(item)
+            } else {
+                a == item
+            }
+        };
+        value |= values
+            .iter()
+            .find(|v| eq(v.nick()))
+            .or_else(|| values.iter().find(|v| eq(v.name())))
+            .map(|v| v.value())
+            .or_else(|| parse_flags_bits(item))
+            .ok_or_else(|| ParseFlagsError::new(class, item))?;
+    }
+    Ok(value)
+}
+
+fn parse_flags_bits(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn check_flags_value(class: &FlagsClass, value: u32) -> Result<(), crate::Error> {
+    let mask = class.values().iter().fold(0, |mask, v| mask | v.value());
+    if value & !mask != 0 {
+        return Err(crate::Error::InvalidFlagsValue {
+            type_: class.type_(),
+            value: value & !mask,
+        });
+    }
+    Ok(())
+}
+
+/// Wrapper type for FFI flags. Serializes as `str`, or as `u32` in formats that are not
+/// human-readable.
+///
+/// Bits not registered in the flags class are preserved, and serialize as a trailing hexadecimal
+/// integer.
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
 pub struct FlagsValue<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> {
     value: u32,
@@ -22,10 +116,37 @@ impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> FlagsValue<T> {
     pub fn value(&self) -> T {
         unsafe { T::from_glib(self.value) }
     }
+    /// Returns the value, or `None` if any bits are not registered in the flags class, where
+    /// [`value`](Self::value) would drop them.
+    pub fn try_value(&self) -> Option<T> {
+        check_flags_value(&Self::flags_class(), self.value).ok()?;
+        Some(self.value())
+    }
+    #[inline]
+    pub fn from_raw(value: u32) -> Self {
+        Self {
+            value,
+            phantom: PhantomData,
+        }
+    }
+    #[inline]
+    pub fn raw_value(&self) -> u32 {
+        self.value
+    }
     pub fn flags_class() -> FlagsClass {
         FlagsClass::new(T::static_type())
             .unwrap_or_else(|| panic!("Invalid flags {}", T::static_type()))
     }
+    /// Converts to a variant, failing if any bits are not registered in the flags class.
+    pub fn try_to_variant(&self) -> Result<glib::Variant, crate::Error> {
+        check_flags_value(&Self::flags_class(), self.value)?;
+        Ok(glib::ToVariant::to_variant(self))
+    }
+    /// Parses `|`-separated flags by nick, then by name, then as an integer.
+    pub fn from_str_lenient(s: &str, ignore_case: bool) -> Result<Self, ParseFlagsError> {
+        let value = flags_from_strs_lenient(&Self::flags_class(), s.split('|'), ignore_case)?;
+        Ok(Self::from_raw(value))
+    }
 }
 
 impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32> + Default> Default for FlagsValue<T> {
@@ -78,62 +199,32 @@ impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> glib::FromVariant
 impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> super::VariantType
     for FlagsValue<T>
 {
+    const VALIDATES_VALUES: bool = true;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        flags_from_str(&Self::flags_class(), variant.str().unwrap_or_default())
+            .map(drop)
+            .map_err(|e| crate::Error::Custom(e.to_string()))
+    }
 }
 
 impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> std::fmt::Display for FlagsValue<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let class = Self::flags_class();
-        let mut s = String::new();
-        let mut value = self.value;
-        for val in class.values() {
-            let v = val.value();
-            if (value & v) == v {
-                value &= !v;
-                if !s.is_empty() {
-                    s.push('|');
-                }
-                s.push_str(val.nick());
-            }
-        }
-        s.fmt(f)
+        flags_to_string(&Self::flags_class(), self.value).fmt(f)
     }
 }
 
 impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> std::str::FromStr for FlagsValue<T> {
     type Err = ParseFlagsError;
 
-    fn from_str(mut s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.is_empty() {
             return Ok(Self {
                 value: 0,
                 phantom: PhantomData,
             });
         }
-        let class = Self::flags_class();
-        let mut count = 0usize;
-        let mut cur_start = s;
-        let mut value = 0u32;
-        loop {
-            let is_end = s.is_empty();
-            if is_end || &s[0..1] == "|" {
-                let item = &cur_start[0..count];
-                let v = class.value_by_nick(item).ok_or_else(|| ParseFlagsError {
-                    token: item.to_owned(),
-                })?;
-                value |= v.value();
-                if is_end {
-                    break;
-                }
-                count = 0;
-                s = &s[1..];
-                cur_start = s;
-            } else {
-                s = &s[1..];
-                count += 1;
-            }
-        }
         Ok(Self {
-            value,
+            value: flags_from_str(&Self::flags_class(), s)?,
             phantom: PhantomData,
         })
     }
@@ -146,7 +237,11 @@ impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> serde::ser::Seria
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            serializer.serialize_u32(self.value)
+        }
     }
 }
 
@@ -167,6 +262,12 @@ impl<'de, T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> serde::de::D
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 write!(formatter, "a valid string for flags {}", self.0.type_())
             }
+            fn visit_u32<E>(self, v: u32) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(FlagsValue::from_raw(v))
+            }
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
@@ -181,11 +282,17 @@ impl<'de, T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> serde::de::D
         }
 
         let class = Self::flags_class();
-        deserializer.deserialize_str(FlagsVisitor::<T>(class, PhantomData))
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(FlagsVisitor::<T>(class, PhantomData))
+        } else {
+            deserializer.deserialize_u32(FlagsVisitor::<T>(class, PhantomData))
+        }
     }
 }
 
 /// Wrapper type for FFI flags. Serializes as `u32`.
+///
+/// Bits not registered in the flags class are preserved.
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
 pub struct FlagsReprValue<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> {
     value: u32,
@@ -200,10 +307,37 @@ impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> FlagsReprValue<T>
     pub fn value(&self) -> T {
         unsafe { T::from_glib(self.value) }
     }
+    /// Returns the value, or `None` if any bits are not registered in the flags class, where
+    /// [`value`](Self::value) would drop them.
+    pub fn try_value(&self) -> Option<T> {
+        check_flags_value(&Self::flags_class(), self.value).ok()?;
+        Some(self.value())
+    }
+    #[inline]
+    pub fn from_raw(value: u32) -> Self {
+        Self {
+            value,
+            phantom: PhantomData,
+        }
+    }
+    #[inline]
+    pub fn raw_value(&self) -> u32 {
+        self.value
+    }
     pub fn flags_class() -> FlagsClass {
         FlagsClass::new(T::static_type())
             .unwrap_or_else(|| panic!("Invalid flags {}", T::static_type()))
     }
+    /// Converts to a variant, failing if any bits are not registered in the flags class.
+    pub fn try_to_variant(&self) -> Result<glib::Variant, crate::Error> {
+        check_flags_value(&Self::flags_class(), self.value)?;
+        Ok(glib::ToVariant::to_variant(self))
+    }
+    /// Parses `|`-separated flags by nick, then by name, then as an integer.
+    pub fn from_str_lenient(s: &str, ignore_case: bool) -> Result<Self, ParseFlagsError> {
+        let value = flags_from_strs_lenient(&Self::flags_class(), s.split('|'), ignore_case)?;
+        Ok(Self::from_raw(value))
+    }
 }
 
 impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32> + Default> Default
@@ -273,20 +407,7 @@ impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> std::fmt::Display
     for FlagsReprValue<T>
 {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let class = Self::flags_class();
-        let mut s = String::new();
-        let mut value = self.value;
-        for val in class.values() {
-            let v = val.value();
-            if (value & v) == v {
-                value &= !v;
-                if !s.is_empty() {
-                    s.push('|');
-                }
-                s.push_str(val.nick());
-            }
-        }
-        s.fmt(f)
+        flags_to_string(&Self::flags_class(), self.value).fmt(f)
     }
 }
 
@@ -295,38 +416,15 @@ impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> std::str::FromStr
 {
     type Err = ParseFlagsError;
 
-    fn from_str(mut s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.is_empty() {
             return Ok(Self {
                 value: 0,
                 phantom: PhantomData,
             });
         }
-        let class = Self::flags_class();
-        let mut count = 0usize;
-        let mut cur_start = s;
-        let mut value = 0u32;
-        loop {
-            let is_end = s.is_empty();
-            if is_end || &s[0..1] == "|" {
-                let item = &cur_start[0..count];
-                let v = class.value_by_nick(item).ok_or_else(|| ParseFlagsError {
-                    token: item.to_owned(),
-                })?;
-                value |= v.value();
-                if is_end {
-                    break;
-                }
-                count = 0;
-                s = &s[1..];
-                cur_start = s;
-            } else {
-                s = &s[1..];
-                count += 1;
-            }
-        }
         Ok(Self {
-            value,
+            value: flags_from_str(&Self::flags_class(), s)?,
             phantom: PhantomData,
         })
     }
@@ -379,13 +477,389 @@ impl<'de, T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> serde::de::D
     }
 }
 
+/// Wrapper type for FFI flags. Serializes as `as`, containing the flag nicks, or as `u32` in
+/// formats that are not human-readable.
+///
+/// This matches the representation used by GSettings for flags keys. Bits not registered in the
+/// flags class are preserved, and serialize as a hexadecimal integer.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+pub struct FlagsArrayValue<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> {
+    value: u32,
+    phantom: PhantomData<T>,
+}
+
+unsafe impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> Send for FlagsArrayValue<T> {}
+unsafe impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> Sync for FlagsArrayValue<T> {}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> FlagsArrayValue<T> {
+    #[inline]
+    pub fn value(&self) -> T {
+        unsafe { T::from_glib(self.value) }
+    }
+    /// Returns the value, or `None` if any bits are not registered in the flags class, where
+    /// [`value`](Self::value) would drop them.
+    pub fn try_value(&self) -> Option<T> {
+        check_flags_value(&Self::flags_class(), self.value).ok()?;
+        Some(self.value())
+    }
+    #[inline]
+    pub fn from_raw(value: u32) -> Self {
+        Self {
+            value,
+            phantom: PhantomData,
+        }
+    }
+    #[inline]
+    pub fn raw_value(&self) -> u32 {
+        self.value
+    }
+    pub fn flags_class() -> FlagsClass {
+        FlagsClass::new(T::static_type())
+            .unwrap_or_else(|| panic!("Invalid flags {}", T::static_type()))
+    }
+    /// Converts to a variant, failing if any bits are not registered in the flags class.
+    pub fn try_to_variant(&self) -> Result<glib::Variant, crate::Error> {
+        check_flags_value(&Self::flags_class(), self.value)?;
+        Ok(glib::ToVariant::to_variant(self))
+    }
+    /// Parses flags from items that are each a single nick or an integer.
+    pub fn from_strs<'a>(
+        items: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, ParseFlagsError> {
+        let value = flags_from_strs(&Self::flags_class(), items)?;
+        Ok(Self::from_raw(value))
+    }
+    /// Parses a single flag by nick, then by name, then as an integer. Unlike
+    /// [`FlagsValue::from_str_lenient`], `|` is not treated as a separator.
+    pub fn from_str_lenient(s: &str, ignore_case: bool) -> Result<Self, ParseFlagsError> {
+        let value = flags_from_strs_lenient(&Self::flags_class(), [s], ignore_case)?;
+        Ok(Self::from_raw(value))
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32> + Default> Default
+    for FlagsArrayValue<T>
+{
+    fn default() -> Self {
+        Self {
+            value: <T as Default>::default().into_glib(),
+            phantom: Default::default(),
+        }
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> From<T> for FlagsArrayValue<T> {
+    fn from(value: T) -> Self {
+        Self {
+            value: value.into_glib(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32> + Copy> From<&T>
+    for FlagsArrayValue<T>
+{
+    fn from(value: &T) -> Self {
+        Self {
+            value: value.into_glib(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> glib::StaticVariantType
+    for FlagsArrayValue<T>
+{
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(glib::VariantTy::STRING_ARRAY)
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> glib::ToVariant
+    for FlagsArrayValue<T>
+{
+    fn to_variant(&self) -> glib::Variant {
+        flags_to_strings(&Self::flags_class(), self.value).to_variant()
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> glib::FromVariant
+    for FlagsArrayValue<T>
+{
+    fn from_variant(variant: &glib::Variant) -> Option<Self> {
+        let items = variant.get::<Vec<String>>()?;
+        flags_from_strs(&Self::flags_class(), items.iter().map(|s| s.as_str()))
+            .ok()
+            .map(Self::from_raw)
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> super::VariantType
+    for FlagsArrayValue<T>
+{
+    const VALIDATES_VALUES: bool = true;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        let class = Self::flags_class();
+        for i in 0..variant.n_children() {
+            let item = variant.child_value(i);
+            flags_from_strs(&class, [item.str().unwrap_or_default()])
+                .map_err(|e| crate::Error::Custom(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> std::fmt::Display
+    for FlagsArrayValue<T>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        flags_to_string(&Self::flags_class(), self.value).fmt(f)
+    }
+}
+
+impl<T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> serde::ser::Serialize
+    for FlagsArrayValue<T>
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        use serde::ser::SerializeSeq;
+
+        if !serializer.is_human_readable() {
+            return serializer.serialize_u32(self.value);
+        }
+        let items = flags_to_strings(&Self::flags_class(), self.value);
+        let mut seq = serializer.serialize_seq(Some(items.len()))?;
+        for item in &items {
+            seq.serialize_element(item)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, T: StaticType + FromGlib<u32> + IntoGlib<GlibType = u32>> serde::de::Deserialize<'de>
+    for FlagsArrayValue<T>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        struct FlagsVisitor(FlagsClass);
+
+        impl<'de> serde::de::Visitor<'de> for FlagsVisitor {
+            type Value = u32;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(
+                    formatter,
+                    "a valid array of strings for flags {}",
+                    self.0.type_()
+                )
+            }
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut value = 0u32;
+                while let Some(item) = seq.next_element::<String>()? {
+                    value |= flags_from_strs(&self.0, [item.as_str()]).map_err(|_| {
+                        serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Str(&item),
+                            &format!("valid string for flags {}", self.0.type_()).as_str(),
+                        )
+                    })?;
+                }
+                Ok(value)
+            }
+            fn visit_u32<E>(self, v: u32) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(v)
+            }
+        }
+
+        let class = Self::flags_class();
+        let value = if deserializer.is_human_readable() {
+            deserializer.deserialize_seq(FlagsVisitor(class))?
+        } else {
+            deserializer.deserialize_u32(FlagsVisitor(class))?
+        };
+        Ok(Self::from_raw(value))
+    }
+}
+
+/// Wrapper type for flags only known at runtime. Serializes as `(ss)`, containing the type name
+/// and the flag nicks. The nicks are replaced by `u32` in formats that are not human-readable.
+///
+/// Bits not registered in the flags class are preserved, and serialize as a trailing hexadecimal
+/// integer. If `type_` is not a flags type, the value is always written as that integer alone,
+/// and read back from one.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct DynFlagsValue {
+    pub type_: glib::Type,
+    pub value: u32,
+}
+
+impl DynFlagsValue {
+    pub fn new(type_: glib::Type, value: u32) -> Self {
+        Self { type_, value }
+    }
+    pub fn flags_class(&self) -> Option<FlagsClass> {
+        FlagsClass::new(self.type_)
+    }
+    /// Converts to a variant, failing if the type is not a flags type or any bits are not
+    /// registered in the flags class.
+    pub fn try_to_variant(&self) -> Result<glib::Variant, crate::Error> {
+        let class = self
+            .flags_class()
+            .ok_or_else(|| crate::Error::Custom(format!("No such flags {}", self.type_)))?;
+        check_flags_value(&class, self.value)?;
+        Ok((self.type_.name(), flags_to_string(&class, self.value)).to_variant())
+    }
+    fn to_nicks(self) -> String {
+        match self.flags_class() {
+            Some(class) => flags_to_string(&class, self.value),
+            None if self.value == 0 => String::new(),
+            None => format!("{:#x}", self.value),
+        }
+    }
+    /// Parses a string written by `to_nicks`.
+    fn from_nicks(type_: glib::Type, nicks: &str) -> Result<Self, crate::Error> {
+        let value = match FlagsClass::new(type_) {
+            Some(class) => {
+                flags_from_str(&class, nicks).map_err(|e| crate::Error::Custom(e.to_string()))?
+            }
+            None if nicks.is_empty() => 0,
+            None => parse_flags_bits(nicks)
+                .ok_or_else(|| crate::Error::Custom(format!("No such flags {}", type_)))?,
+        };
+        Ok(Self::new(type_, value))
+    }
+}
+
+impl glib::StaticVariantType for DynFlagsValue {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(unsafe { glib::VariantTy::from_str_unchecked("(ss)") })
+    }
+}
+
+impl glib::ToVariant for DynFlagsValue {
+    fn to_variant(&self) -> glib::Variant {
+        (self.type_.name(), self.to_nicks()).to_variant()
+    }
+}
+
+impl glib::FromVariant for DynFlagsValue {
+    fn from_variant(variant: &glib::Variant) -> Option<Self> {
+        let (type_, nicks) = variant.get::<(GType, String)>()?;
+        Self::from_nicks(type_.into(), &nicks).ok()
+    }
+}
+
+impl super::VariantType for DynFlagsValue {
+    const TYPE_STR: Option<&'static str> = Some("(ss)");
+    const VALIDATES_VALUES: bool = true;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        let type_ = variant.child_value(0);
+        let type_ = type_.str().unwrap_or_default();
+        let type_ = glib::Type::from_name(type_)
+            .ok_or_else(|| crate::Error::Custom(format!("No such type {}", type_)))?;
+        let nicks = variant.child_value(1);
+        Self::from_nicks(type_, nicks.str().unwrap_or_default()).map(drop)
+    }
+}
+
+impl std::fmt::Display for DynFlagsValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.to_nicks().fmt(f)
+    }
+}
+
+impl serde::ser::Serialize for DynFlagsValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        use serde::ser::SerializeTuple;
+
+        let human_readable = serializer.is_human_readable();
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&GType::from(self.type_))?;
+        if human_readable {
+            tuple.serialize_element(&self.to_nicks())?;
+        } else {
+            tuple.serialize_element(&self.value)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> serde::de::Deserialize<'de> for DynFlagsValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        struct FlagsVisitor {
+            human_readable: bool,
+        }
+
+        impl<'de> serde::de::Visitor<'de> for FlagsVisitor {
+            type Value = DynFlagsValue;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a tuple of flags type name and flags string")
+            }
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let type_ = seq
+                    .next_element::<GType>()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+                if !self.human_readable {
+                    let value = seq
+                        .next_element::<u32>()?
+                        .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                    return Ok(DynFlagsValue::new(type_.into(), value));
+                }
+                let value = seq
+                    .next_element::<String>()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                DynFlagsValue::from_nicks(type_.into(), &value).map_err(serde::de::Error::custom)
+            }
+        }
+
+        let human_readable = deserializer.is_human_readable();
+        deserializer.deserialize_tuple(2, FlagsVisitor { human_readable })
+    }
+}
+
 /// Error type for [`FlagsValue`].
+#[derive(Debug)]
 pub struct ParseFlagsError {
     token: String,
+    nicks: Vec<String>,
+}
+
+impl ParseFlagsError {
+    fn new(class: &FlagsClass, token: &str) -> Self {
+        Self {
+            token: token.to_owned(),
+            nicks: class.values().iter().map(|v| v.nick().to_owned()).collect(),
+        }
+    }
+    /// Nicks of all values registered in the flags class.
+    pub fn valid_nicks(&self) -> &[String] {
+        &self.nicks
+    }
 }
 
 impl std::fmt::Display for ParseFlagsError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Invalid flag value `{}`", self.token)
+        write!(f, "Invalid flag value `{}`", self.token)?;
+        crate::enums::write_valid_nicks(f, &self.nicks)
     }
 }
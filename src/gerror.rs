@@ -0,0 +1,136 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use crate::VariantType;
+use glib::{translate::*, ToVariant};
+use serde::ser::SerializeTupleStruct;
+
+pub(crate) const STRUCT_NAME: &str = "glib_serde::$GError";
+
+/// Wrapper type for [`glib::Error`]. Serializes as `(sis)`, containing the error domain, code and
+/// message.
+#[repr(transparent)]
+#[derive(Clone, Debug)]
+pub struct GError(glib::Error);
+
+impl GError {
+    pub fn new(domain: glib::Quark, code: i32, message: &str) -> Self {
+        unsafe {
+            Self(from_glib_full(glib::ffi::g_error_new_literal(
+                domain.into_glib(),
+                code,
+                message.to_glib_none().0,
+            )))
+        }
+    }
+    pub fn code(&self) -> i32 {
+        unsafe { (*self.0.to_glib_none().0).code }
+    }
+    pub fn into_inner(self) -> glib::Error {
+        self.0
+    }
+}
+
+impl std::ops::Deref for GError {
+    type Target = glib::Error;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<glib::Error> for GError {
+    fn from(error: glib::Error) -> Self {
+        Self(error)
+    }
+}
+
+impl From<GError> for glib::Error {
+    fn from(error: GError) -> Self {
+        error.0
+    }
+}
+
+impl std::fmt::Display for GError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for GError {}
+
+impl glib::StaticVariantType for GError {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(unsafe { glib::VariantTy::from_str_unchecked("(sis)") })
+    }
+}
+
+impl ToVariant for GError {
+    fn to_variant(&self) -> glib::Variant {
+        (self.domain().as_str(), self.code(), self.message()).to_variant()
+    }
+}
+
+impl glib::FromVariant for GError {
+    fn from_variant(variant: &glib::Variant) -> Option<Self> {
+        let (domain, code, message) = variant.get::<(String, i32, String)>()?;
+        Some(Self::new(glib::Quark::from_str(&domain), code, &message))
+    }
+}
+
+impl VariantType for GError {
+    const TYPE_STR: Option<&'static str> = Some("(sis)");
+}
+
+impl serde::ser::Serialize for GError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple_struct(STRUCT_NAME, 3)?;
+        tuple.serialize_field(self.domain().as_str())?;
+        tuple.serialize_field(&self.code())?;
+        tuple.serialize_field(self.message())?;
+        tuple.end()
+    }
+}
+
+impl<'de> serde::de::Deserialize<'de> for GError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        struct ErrorVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ErrorVisitor {
+            type Value = GError;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a GError tuple of domain, code and message")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let domain = seq.next_element::<String>()?.ok_or_else(|| {
+                    serde::de::Error::invalid_length(0, &"tuple struct GError with 3 elements")
+                })?;
+                let code = seq.next_element::<i32>()?.ok_or_else(|| {
+                    serde::de::Error::invalid_length(1, &"tuple struct GError with 3 elements")
+                })?;
+                let message = seq.next_element::<String>()?.ok_or_else(|| {
+                    serde::de::Error::invalid_length(2, &"tuple struct GError with 3 elements")
+                })?;
+                if domain.is_empty() {
+                    return Err(serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Str(&domain),
+                        &"a non-empty error domain",
+                    ));
+                }
+                Ok(GError::new(glib::Quark::from_str(&domain), code, &message))
+            }
+        }
+
+        deserializer.deserialize_tuple_struct(STRUCT_NAME, 3, ErrorVisitor)
+    }
+}
@@ -0,0 +1,110 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use crate::VariantType;
+use glib::ToVariant;
+
+/// Wrapper type for [`glib::Type`]. Serializes as `str`, containing the registered type name.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GType(glib::Type);
+
+impl GType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        glib::Type::from_name(name).map(Self)
+    }
+    pub fn into_inner(self) -> glib::Type {
+        self.0
+    }
+}
+
+impl std::ops::Deref for GType {
+    type Target = glib::Type;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<glib::Type> for GType {
+    fn from(ty: glib::Type) -> Self {
+        Self(ty)
+    }
+}
+
+impl From<GType> for glib::Type {
+    fn from(ty: GType) -> Self {
+        ty.0
+    }
+}
+
+impl std::fmt::Display for GType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl glib::StaticVariantType for GType {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(glib::VariantTy::STRING)
+    }
+}
+
+impl ToVariant for GType {
+    fn to_variant(&self) -> glib::Variant {
+        self.0.name().to_variant()
+    }
+}
+
+impl glib::FromVariant for GType {
+    fn from_variant(variant: &glib::Variant) -> Option<Self> {
+        variant.str().and_then(Self::from_name)
+    }
+}
+
+impl VariantType for GType {
+    const TYPE_STR: Option<&'static str> = Some("s");
+    const VALIDATES_VALUES: bool = true;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        let name = variant.str().unwrap_or_default();
+        match Self::from_name(name) {
+            Some(_) => Ok(()),
+            None => Err(crate::Error::Custom(format!("No such type {}", name))),
+        }
+    }
+}
+
+impl serde::ser::Serialize for GType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_str(self.0.name())
+    }
+}
+
+impl<'de> serde::de::Deserialize<'de> for GType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        struct TypeVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for TypeVisitor {
+            type Value = GType;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a registered GType name")
+            }
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                GType::from_name(v).ok_or_else(|| {
+                    serde::de::Error::invalid_value(serde::de::Unexpected::Str(v), &self)
+                })
+            }
+        }
+
+        deserializer.deserialize_str(TypeVisitor)
+    }
+}
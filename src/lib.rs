@@ -47,6 +47,17 @@
 //! let value: Direction = glib_serde::from_variant(&variant).unwrap();
 //! assert_eq!(value, Direction::South);
 //! ```
+//!
+//! Type strings, signatures and object paths can be checked at compile time:
+//!
+//! ```
+//! const TYPE: &glib::VariantTy = glib_serde::variant_type!("(sa{sv})");
+//! const SIGNATURE: glib_serde::SignatureRef = glib_serde::signature!("a{sv}");
+//! let path = glib_serde::object_path!("/org/gnome/Foo");
+//! assert_eq!(TYPE.as_str(), "(sa{sv})");
+//! assert_eq!(SIGNATURE.as_str(), "a{sv}");
+//! assert_eq!(path.as_str(), "/org/gnome/Foo");
+//! ```
 
 pub use glib;
 pub use glib_serde_derive::*;
@@ -56,16 +67,30 @@ mod enums;
 pub use enums::*;
 mod error;
 pub use error::*;
+#[cfg(all(unix, feature = "gio"))]
+mod fd;
+#[cfg(all(unix, feature = "gio"))]
+pub use fd::*;
 mod flags;
 pub use flags::*;
+mod gerror;
+pub use gerror::*;
+mod gtype;
+pub use gtype::*;
 mod object_path;
 pub use object_path::*;
 mod signature;
 pub use signature::*;
 mod variant;
-pub use variant::{from_variant, to_variant, Variant};
+pub use variant::{
+    cast, from_bytes, from_bytes_with_type, from_variant, from_variant_checked,
+    from_variant_with_options, iter_variant, json, to_bytes, to_bytes_with_type, to_variant,
+    to_variant_via_bytes, update_from_variant, validate, CastError, CastErrorKind, CastRules,
+    DeserializeOptions, FixedArray, FixedArrayElement, LazyVariant, Variant, VariantMap,
+    VariantSeq,
+};
 mod variant_builder;
-use variant_builder::*;
+pub use variant_builder::*;
 mod variant_dict;
 pub use variant_dict::*;
 mod variant_type;
@@ -74,6 +99,7 @@ pub use variant_type::*;
 /// Extension traits for variants and serializable types.
 pub mod prelude {
     pub use super::variant::GlibVariantExt;
+    pub use super::VariantBuilderExt;
 
     /// Alternative to [`ToVariant`](glib::ToVariant) for [`serde::Serialize`] types.
     pub trait ToVariantExt {
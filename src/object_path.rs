@@ -32,6 +32,77 @@ impl ObjectPath {
     }
 }
 
+/// A borrowed [`ObjectPath`], such as one checked at compile time by
+/// [`object_path!`](crate::object_path). Unlike `ObjectPath`, this can be created in const
+/// contexts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectPathRef<'a>(&'a str);
+
+impl<'a> ObjectPathRef<'a> {
+    /// # Safety
+    ///
+    /// `s` must be a valid object path.
+    pub const unsafe fn from_str_unchecked(s: &'a str) -> Self {
+        Self(s)
+    }
+    pub const fn as_str(&self) -> &'a str {
+        self.0
+    }
+    pub fn to_object_path(&self) -> ObjectPath {
+        ObjectPath(self.0.into())
+    }
+}
+
+impl<'a> From<ObjectPathRef<'a>> for ObjectPath {
+    fn from(path: ObjectPathRef<'a>) -> Self {
+        path.to_object_path()
+    }
+}
+
+impl<'a> PartialEq<ObjectPath> for ObjectPathRef<'a> {
+    fn eq(&self, other: &ObjectPath) -> bool {
+        self.0 == other.as_str()
+    }
+}
+
+impl<'a> PartialEq<ObjectPathRef<'a>> for ObjectPath {
+    fn eq(&self, other: &ObjectPathRef<'a>) -> bool {
+        self.as_str() == other.0
+    }
+}
+
+impl<'a> glib::StaticVariantType for ObjectPathRef<'a> {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(glib::VariantTy::OBJECT_PATH)
+    }
+}
+
+impl<'a> glib::ToVariant for ObjectPathRef<'a> {
+    fn to_variant(&self) -> glib::Variant {
+        // the string needs a nul terminator
+        glib::ToVariant::to_variant(&self.to_object_path())
+    }
+}
+
+impl<'a> VariantType for ObjectPathRef<'a> {
+    const TYPE_STR: Option<&'static str> = Some("o");
+}
+
+impl<'a> std::fmt::Display for ObjectPathRef<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'a> serde::ser::Serialize for ObjectPathRef<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(STRUCT_NAME, self.0)
+    }
+}
+
 impl glib::StaticVariantType for ObjectPath {
     fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
         std::borrow::Cow::Borrowed(glib::VariantTy::OBJECT_PATH)
@@ -54,7 +125,9 @@ impl glib::FromVariant for ObjectPath {
     }
 }
 
-impl VariantType for ObjectPath {}
+impl VariantType for ObjectPath {
+    const TYPE_STR: Option<&'static str> = Some("o");
+}
 
 impl std::fmt::Display for ObjectPath {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
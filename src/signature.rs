@@ -24,6 +24,91 @@ impl Signature {
             Ok(Self(glib::VariantType::from_string(s)?))
         }
     }
+    /// # Safety
+    ///
+    /// `s` must be a valid signature holding a single complete type.
+    pub unsafe fn new_unchecked(s: impl Into<glib::GString>) -> Self {
+        let s = s.into();
+        Self(glib::VariantTy::from_str_unchecked(s.as_str()).to_owned())
+    }
+}
+
+/// A borrowed [`Signature`], such as one checked at compile time by
+/// [`signature!`](crate::signature). Unlike `Signature`, this can be created in const contexts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignatureRef<'a>(&'a glib::VariantTy);
+
+impl<'a> SignatureRef<'a> {
+    /// # Safety
+    ///
+    /// `s` must be a valid signature holding a single complete type.
+    pub const unsafe fn from_str_unchecked(s: &'a str) -> Self {
+        Self(glib::VariantTy::from_str_unchecked(s))
+    }
+    pub fn as_str(&self) -> &'a str {
+        self.0.as_str()
+    }
+    pub fn to_signature(&self) -> Signature {
+        Signature(self.0.to_owned())
+    }
+}
+
+impl<'a> std::ops::Deref for SignatureRef<'a> {
+    type Target = glib::VariantTy;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<'a> From<SignatureRef<'a>> for Signature {
+    fn from(sig: SignatureRef<'a>) -> Self {
+        sig.to_signature()
+    }
+}
+
+impl<'a> PartialEq<Signature> for SignatureRef<'a> {
+    fn eq(&self, other: &Signature) -> bool {
+        self.0 == &*other.0
+    }
+}
+
+impl<'a> PartialEq<SignatureRef<'a>> for Signature {
+    fn eq(&self, other: &SignatureRef<'a>) -> bool {
+        &*self.0 == other.0
+    }
+}
+
+impl<'a> glib::StaticVariantType for SignatureRef<'a> {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(glib::VariantTy::SIGNATURE)
+    }
+}
+
+impl<'a> glib::ToVariant for SignatureRef<'a> {
+    fn to_variant(&self) -> glib::Variant {
+        // the string needs a nul terminator
+        glib::ToVariant::to_variant(&self.to_signature())
+    }
+}
+
+impl<'a> VariantType for SignatureRef<'a> {
+    const TYPE_STR: Option<&'static str> = Some("g");
+}
+
+impl<'a> std::fmt::Display for SignatureRef<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'a> serde::ser::Serialize for SignatureRef<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(STRUCT_NAME, self.as_str())
+    }
 }
 
 impl std::ops::Deref for Signature {
@@ -78,7 +163,9 @@ impl glib::FromVariant for Signature {
     }
 }
 
-impl VariantType for Signature {}
+impl VariantType for Signature {
+    const TYPE_STR: Option<&'static str> = Some("g");
+}
 
 impl std::fmt::Display for Signature {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
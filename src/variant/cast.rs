@@ -0,0 +1,327 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use super::GlibVariantExt;
+use crate::{ObjectPath, Signature, VariantBuilder, VariantBuilderExt};
+use glib::{ToVariant, VariantClass, VariantTy};
+
+/// Lossy conversions allowed by [`cast`]. Lossless conversions are always allowed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CastRules {
+    /// Convert between any integer types, failing if a value is out of range.
+    pub narrow_integers: bool,
+    /// Convert `x` and `t` to `d`, which may lose precision.
+    pub integers_to_doubles: bool,
+    /// Convert `d` to integer types, failing if a value is not integral or out of range.
+    pub doubles_to_integers: bool,
+}
+
+impl CastRules {
+    /// Returns rules allowing every lossy conversion.
+    pub fn lossy() -> Self {
+        Self {
+            narrow_integers: true,
+            integers_to_doubles: true,
+            doubles_to_integers: true,
+        }
+    }
+}
+
+/// Reason for a [`CastError`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum CastErrorKind {
+    /// No conversion exists between the two types.
+    Incompatible,
+    /// The conversion may lose data and is not enabled in the [`CastRules`].
+    Lossy,
+    /// The integer does not fit in the requested type.
+    OutOfRange(i128),
+    /// The double has a fractional part or is not finite.
+    NotIntegral(f64),
+    /// The string is not a valid object path or signature.
+    InvalidString(String),
+}
+
+/// Error returned by [`cast`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CastError {
+    /// Type of the value that could not be converted.
+    pub from: glib::VariantType,
+    /// Requested type for the value.
+    pub to: glib::VariantType,
+    /// Child indices leading to the value, starting from the variant passed to [`cast`].
+    pub path: Vec<usize>,
+    /// Reason the conversion failed.
+    pub kind: CastErrorKind,
+}
+
+impl std::fmt::Display for CastError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot cast '{}' to '{}'", self.from, self.to)?;
+        if !self.path.is_empty() {
+            write!(f, " at child {:?}", self.path)?;
+        }
+        match &self.kind {
+            CastErrorKind::Incompatible => f.write_str(": no conversion exists"),
+            CastErrorKind::Lossy => f.write_str(": conversion may lose data"),
+            CastErrorKind::OutOfRange(v) => write!(f, ": value {} is out of range", v),
+            CastErrorKind::NotIntegral(v) => write!(f, ": value {} is not integral", v),
+            CastErrorKind::InvalidString(s) => write!(f, ": invalid value '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for CastError {}
+
+/// Converts `variant` to type `ty`.
+///
+/// Integers widen, any value can be boxed into `v` and `v` is unboxed, `o` and `g` convert to
+/// `s` and back, and containers convert child by child: arrays, maybes and tuples convert to
+/// arrays, values convert to maybes, arrays and tuples of the right length convert to tuples,
+/// and 2-tuples convert to dict entries. Tuples convert to dictionaries keyed by child index.
+/// Conversions that can lose data must be enabled in `rules`.
+pub fn cast(
+    variant: &glib::Variant,
+    ty: &VariantTy,
+    rules: CastRules,
+) -> Result<glib::Variant, CastError> {
+    Caster {
+        rules,
+        path: Vec::new(),
+    }
+    .cast(variant, ty)
+}
+
+fn integer(v: &glib::Variant) -> Option<i128> {
+    match v.classify() {
+        VariantClass::Byte => v.get::<u8>().map(Into::into),
+        VariantClass::Int16 => v.get::<i16>().map(Into::into),
+        VariantClass::Uint16 => v.get::<u16>().map(Into::into),
+        VariantClass::Int32 => v.get::<i32>().map(Into::into),
+        VariantClass::Uint32 => v.get::<u32>().map(Into::into),
+        VariantClass::Int64 => v.get::<i64>().map(Into::into),
+        VariantClass::Uint64 => v.get::<u64>().map(Into::into),
+        _ => None,
+    }
+}
+
+fn integer_bounds(ty: &VariantTy) -> Option<(i128, i128)> {
+    Some(match ty.as_str() {
+        "y" => (u8::MIN.into(), u8::MAX.into()),
+        "n" => (i16::MIN.into(), i16::MAX.into()),
+        "q" => (u16::MIN.into(), u16::MAX.into()),
+        "i" => (i32::MIN.into(), i32::MAX.into()),
+        "u" => (u32::MIN.into(), u32::MAX.into()),
+        "x" => (i64::MIN.into(), i64::MAX.into()),
+        "t" => (u64::MIN.into(), u64::MAX.into()),
+        _ => return None,
+    })
+}
+
+fn integer_to_variant(ty: &VariantTy, value: i128) -> glib::Variant {
+    match ty.as_str() {
+        "y" => (value as u8).to_variant(),
+        "n" => (value as i16).to_variant(),
+        "q" => (value as u16).to_variant(),
+        "i" => (value as i32).to_variant(),
+        "u" => (value as u32).to_variant(),
+        "x" => (value as i64).to_variant(),
+        "t" => (value as u64).to_variant(),
+        _ => unreachable!(),
+    }
+}
+
+struct Caster {
+    rules: CastRules,
+    path: Vec<usize>,
+}
+
+impl Caster {
+    fn error(&self, v: &glib::Variant, ty: &VariantTy, kind: CastErrorKind) -> CastError {
+        CastError {
+            from: v.type_().to_owned(),
+            to: ty.to_owned(),
+            path: self.path.clone(),
+            kind,
+        }
+    }
+    fn child(
+        &mut self,
+        index: usize,
+        v: &glib::Variant,
+        ty: &VariantTy,
+    ) -> Result<glib::Variant, CastError> {
+        self.path.push(index);
+        let ret = self.cast(v, ty);
+        self.path.pop();
+        ret
+    }
+    fn cast(&mut self, v: &glib::Variant, ty: &VariantTy) -> Result<glib::Variant, CastError> {
+        if v.is_of_type(ty).is_ok() {
+            return Ok(v.clone());
+        }
+        if !ty.is_definite() {
+            return Err(self.error(v, ty, CastErrorKind::Incompatible));
+        }
+        if let Some(inner) = v.as_variant() {
+            return self.child(0, &inner, ty);
+        }
+        if ty == VariantTy::VARIANT {
+            return Ok(v.to_variant());
+        }
+        if let Some(bounds) = integer_bounds(ty) {
+            return self.cast_integer(v, ty, bounds);
+        }
+        match ty.as_str() {
+            "d" => self.cast_double(v, ty),
+            "s" => match v.classify() {
+                VariantClass::ObjectPath | VariantClass::Signature => {
+                    Ok(v.str().unwrap().to_variant())
+                }
+                _ => Err(self.error(v, ty, CastErrorKind::Incompatible)),
+            },
+            "o" | "g" => {
+                let s = match v.classify() {
+                    VariantClass::String | VariantClass::ObjectPath | VariantClass::Signature => {
+                        v.str().unwrap()
+                    }
+                    _ => return Err(self.error(v, ty, CastErrorKind::Incompatible)),
+                };
+                let ret = if ty == VariantTy::OBJECT_PATH {
+                    ObjectPath::new(s).map(|o| o.to_variant())
+                } else {
+                    Signature::new(s).map(|g| g.to_variant())
+                };
+                ret.map_err(|_| self.error(v, ty, CastErrorKind::InvalidString(s.to_owned())))
+            }
+            _ if ty.is_maybe() => self.cast_maybe(v, ty),
+            _ if ty.is_array() => self.cast_array(v, ty),
+            _ if ty.is_tuple() || ty.is_dict_entry() => self.cast_tuple(v, ty),
+            _ => Err(self.error(v, ty, CastErrorKind::Incompatible)),
+        }
+    }
+    fn cast_integer(
+        &mut self,
+        v: &glib::Variant,
+        ty: &VariantTy,
+        (min, max): (i128, i128),
+    ) -> Result<glib::Variant, CastError> {
+        let value = if let Some(value) = integer(v) {
+            let (src_min, src_max) = integer_bounds(v.type_()).unwrap();
+            if !self.rules.narrow_integers && (src_min < min || src_max > max) {
+                return Err(self.error(v, ty, CastErrorKind::Lossy));
+            }
+            value
+        } else if let Some(value) = v.get::<f64>() {
+            if !self.rules.doubles_to_integers {
+                return Err(self.error(v, ty, CastErrorKind::Lossy));
+            }
+            if value.fract() != 0.0 || !value.is_finite() || value.abs() >= 2f64.powi(64) {
+                return Err(self.error(v, ty, CastErrorKind::NotIntegral(value)));
+            }
+            value as i128
+        } else {
+            return Err(self.error(v, ty, CastErrorKind::Incompatible));
+        };
+        if value < min || value > max {
+            return Err(self.error(v, ty, CastErrorKind::OutOfRange(value)));
+        }
+        Ok(integer_to_variant(ty, value))
+    }
+    fn cast_double(
+        &mut self,
+        v: &glib::Variant,
+        ty: &VariantTy,
+    ) -> Result<glib::Variant, CastError> {
+        const EXACT: i128 = 1 << f64::MANTISSA_DIGITS;
+
+        let value = integer(v).ok_or_else(|| self.error(v, ty, CastErrorKind::Incompatible))?;
+        let (src_min, src_max) = integer_bounds(v.type_()).unwrap();
+        if !self.rules.integers_to_doubles && (src_min < -EXACT || src_max > EXACT) {
+            return Err(self.error(v, ty, CastErrorKind::Lossy));
+        }
+        Ok((value as f64).to_variant())
+    }
+    fn cast_maybe(
+        &mut self,
+        v: &glib::Variant,
+        ty: &VariantTy,
+    ) -> Result<glib::Variant, CastError> {
+        let elem = ty.element();
+        match v.maybe() {
+            Some(Some(inner)) => {
+                let inner = self.child(0, &inner, elem)?;
+                Ok(glib::Variant::from_some(&inner))
+            }
+            Some(None) => Ok(glib::Variant::from_none(elem)),
+            None => {
+                let inner = self.cast(v, elem)?;
+                Ok(glib::Variant::from_some(&inner))
+            }
+        }
+    }
+    fn cast_array(
+        &mut self,
+        v: &glib::Variant,
+        ty: &VariantTy,
+    ) -> Result<glib::Variant, CastError> {
+        let elem = ty.element();
+        let vty = v.type_();
+        let mut builder = VariantBuilder::new(ty);
+        if vty.is_array() || vty.is_maybe() {
+            for i in 0..v.n_children() {
+                let child = self.child(i, &v.child_value(i), elem)?;
+                builder
+                    .add_value(&child)
+                    .map_err(|_| self.error(v, ty, CastErrorKind::Incompatible))?;
+            }
+        } else if vty.is_tuple() && elem.is_dict_entry() {
+            for i in 0..v.n_children() {
+                let key = match integer_bounds(elem.key()) {
+                    _ if elem.key() == VariantTy::STRING => i.to_string().to_variant(),
+                    Some((_, max)) if i as i128 <= max => integer_to_variant(elem.key(), i as i128),
+                    _ => return Err(self.error(v, ty, CastErrorKind::Incompatible)),
+                };
+                let value = self.child(i, &v.child_value(i), elem.value())?;
+                builder
+                    .add_value(&glib::Variant::from_dict_entry(&key, &value))
+                    .map_err(|_| self.error(v, ty, CastErrorKind::Incompatible))?;
+            }
+        } else if vty.is_tuple() {
+            for i in 0..v.n_children() {
+                let child = self.child(i, &v.child_value(i), elem)?;
+                builder
+                    .add_value(&child)
+                    .map_err(|_| self.error(v, ty, CastErrorKind::Incompatible))?;
+            }
+        } else {
+            return Err(self.error(v, ty, CastErrorKind::Incompatible));
+        }
+        builder
+            .end()
+            .map_err(|_| self.error(v, ty, CastErrorKind::Incompatible))
+    }
+    fn cast_tuple(
+        &mut self,
+        v: &glib::Variant,
+        ty: &VariantTy,
+    ) -> Result<glib::Variant, CastError> {
+        let vty = v.type_();
+        let len = ty.n_items();
+        if !(vty.is_tuple() || vty.is_dict_entry() || vty.is_array()) || v.n_children() != len {
+            return Err(self.error(v, ty, CastErrorKind::Incompatible));
+        }
+        let mut children = Vec::with_capacity(len);
+        let mut iter = ty.first();
+        for i in 0..len {
+            let elem = iter.unwrap();
+            children.push(self.child(i, &v.child_value(i), elem)?);
+            iter = elem.next();
+        }
+        if ty.is_dict_entry() {
+            Ok(glib::Variant::from_dict_entry(&children[0], &children[1]))
+        } else {
+            Ok(glib::Variant::tuple_from_iter(children))
+        }
+    }
+}
@@ -0,0 +1,806 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use super::{
+    deserializer::VariantDeserializer,
+    encoder::{align_to, alignment, children, fixed_size},
+    GlibVariantExt,
+};
+use crate::{Error, VariantType, VariantTypeNode};
+use glib::{variant::VariantTypeMismatchError, VariantTy};
+use serde::{
+    de::{self, IntoDeserializer, Visitor},
+    Deserialize,
+};
+
+/// Same as `G_VARIANT_MAX_RECURSION_DEPTH`.
+const MAX_DEPTH: usize = 128;
+
+/// Deserializes `T` from GVariant data of type [`VariantType::variant_type`], without
+/// constructing any [`glib::Variant`](struct@glib::Variant)s.
+///
+/// See [`from_bytes_with_type`].
+pub fn from_bytes<'de, T>(data: &'de [u8]) -> Result<T, Error>
+where
+    T: VariantType + Deserialize<'de>,
+{
+    let ty = T::variant_type();
+    from_bytes_with_type(data, &ty)
+}
+
+/// Deserializes `T` from GVariant data of the definite type `node`, without constructing any
+/// [`glib::Variant`](struct@glib::Variant)s.
+///
+/// `data` does not need to be in normal form or come from a trusted source. Values that are
+/// malformed or out of bounds are read as the default value of their type, following the same
+/// rules as GLib, so the result always matches the normal form that GLib would produce for
+/// `data`. Strings and byte arrays are borrowed from `data`.
+///
+/// Fields of type [`Variant`](crate::Variant) and [`LazyVariant`](crate::LazyVariant) are
+/// still read into a [`glib::Variant`](struct@glib::Variant).
+pub fn from_bytes_with_type<'de, T>(data: &'de [u8], node: &VariantTypeNode) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    let ty: &VariantTy = node.type_();
+    if !ty.is_definite() {
+        return Err(Error::UnsupportedType(ty.to_owned()));
+    }
+    T::deserialize(Decoder::new(data, ty, 0))
+}
+
+/// Returns the width of the framing offsets in a container of `size` bytes.
+fn offset_width(size: usize) -> usize {
+    match size as u64 {
+        0 => 0,
+        0x1..=0xff => 1,
+        0x100..=0xffff => 2,
+        0x1_0000..=0xffff_ffff => 4,
+        _ => 8,
+    }
+}
+
+fn read_offset(data: &[u8], pos: usize, size: usize) -> usize {
+    let mut buf = [0u8; 8];
+    buf[..size].copy_from_slice(&data[pos..pos + size]);
+    u64::from_le_bytes(buf) as usize
+}
+
+/// Returns the nesting depth of `ty`, counting basic types as 1.
+fn type_depth(ty: &VariantTy) -> usize {
+    match ty.as_str().as_bytes()[0] {
+        b'm' | b'a' => 1 + type_depth(ty.element()),
+        b'(' | b'{' => 1 + children(ty).map(type_depth).max().unwrap_or(0),
+        _ => 1,
+    }
+}
+
+/// Reads one value of type `ty` from `data`.
+#[derive(Clone, Copy)]
+struct Decoder<'t, 'de: 't> {
+    data: &'de [u8],
+    ty: &'t VariantTy,
+    depth: usize,
+}
+
+impl<'t, 'de: 't> Decoder<'t, 'de> {
+    fn new(data: &'de [u8], ty: &'t VariantTy, depth: usize) -> Self {
+        // fixed-size values of the wrong size read as zeroes
+        let data: &'de [u8] = match fixed_size(ty) {
+            Some(size) if size != data.len() => &[],
+            _ => data,
+        };
+        Self { data, ty, depth }
+    }
+    fn mismatch(&self, expected: &VariantTy) -> Error {
+        Error::Mismatch(VariantTypeMismatchError::new(
+            self.ty.to_owned(),
+            expected.to_owned(),
+        ))
+    }
+    fn expect(&self, ty: &VariantTy) -> Result<(), Error> {
+        if self.ty == ty {
+            Ok(())
+        } else {
+            Err(self.mismatch(ty))
+        }
+    }
+    fn unsupported(&self) -> Error {
+        Error::UnsupportedType(self.ty.to_owned())
+    }
+    fn read<const N: usize>(&self, ty: &VariantTy) -> Result<[u8; N], Error> {
+        self.expect(ty)?;
+        Ok(self.data.try_into().unwrap_or([0; N]))
+    }
+    fn read_pair(&self, ty: &VariantTy) -> Result<([u8; 8], [u8; 8]), Error> {
+        self.expect(ty)?;
+        match self.data.len() {
+            16 => Ok((
+                self.data[..8].try_into().unwrap(),
+                self.data[8..].try_into().unwrap(),
+            )),
+            len => Err(Error::LengthMismatch {
+                actual: if len % 8 == 0 { len / 8 } else { 0 },
+                expected: 2,
+            }),
+        }
+    }
+    fn str(&self) -> Result<&'de str, Error> {
+        let default = match self.ty.as_str() {
+            "s" | "g" => "",
+            "o" => "/",
+            _ => return Err(Error::StrMismatch(self.ty.to_owned())),
+        };
+        Ok(self.checked_str().unwrap_or(default))
+    }
+    fn checked_str(&self) -> Option<&'de str> {
+        let (last, s) = self.data.split_last()?;
+        if *last != 0 || s.contains(&0) {
+            return None;
+        }
+        let s = std::str::from_utf8(s).ok()?;
+        // data is nul-terminated at this point
+        let ptr = self.data.as_ptr() as *const _;
+        let valid = match self.ty.as_str() {
+            "o" => unsafe { glib::ffi::g_variant_is_object_path(ptr) },
+            "g" => unsafe { glib::ffi::g_variant_is_signature(ptr) },
+            _ => glib::ffi::GTRUE,
+        };
+        (valid != glib::ffi::GFALSE).then_some(s)
+    }
+    /// Returns the child of a maybe value.
+    fn maybe(&self) -> Option<Self> {
+        let element = self.ty.element();
+        let data = match fixed_size(element) {
+            Some(size) => (self.data.len() == size).then_some(self.data)?,
+            None => self.data.split_last()?.1,
+        };
+        Some(Self::new(data, element, self.depth + 1))
+    }
+    /// Returns the contents of a `v` value, or a unit value if the contents are invalid.
+    fn boxed(&self) -> Self {
+        let depth = self.depth + 1;
+        if let Some(sep) = self.data.iter().rposition(|b| *b == 0) {
+            let (data, type_str) = (&self.data[..sep], &self.data[sep + 1..]);
+            let ty = std::str::from_utf8(type_str)
+                .ok()
+                .and_then(|s| VariantTy::new(s).ok())
+                .filter(|ty| ty.is_definite())
+                .filter(|ty| fixed_size(ty).unwrap_or(data.len()) == data.len())
+                .filter(|ty| self.depth + type_depth(ty) < MAX_DEPTH);
+            if let Some(ty) = ty {
+                return Self::new(data, ty, depth);
+            }
+        }
+        Self::new(&[], VariantTy::UNIT, depth)
+    }
+    fn to_glib(self) -> glib::Variant {
+        glib::Variant::from_bytes_with_type(&glib::Bytes::from(self.data), self.ty)
+    }
+}
+
+impl<'t, 'de: 't> de::Deserializer<'de> for Decoder<'t, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.ty.as_str().as_bytes()[0] {
+            b'b' => self.deserialize_bool(visitor),
+            b'y' => self.deserialize_u8(visitor),
+            b'n' => self.deserialize_i16(visitor),
+            b'q' => self.deserialize_u16(visitor),
+            b'i' | b'h' => self.deserialize_i32(visitor),
+            b'u' => self.deserialize_u32(visitor),
+            b'x' => self.deserialize_i64(visitor),
+            b't' => self.deserialize_u64(visitor),
+            b'd' => self.deserialize_f64(visitor),
+            b's' | b'o' | b'g' => self.deserialize_str(visitor),
+            b'v' => self.boxed().deserialize_any(visitor),
+            b'm' => self.deserialize_option(visitor),
+            b'a' => {
+                let elem = self.ty.element();
+                if elem == VariantTy::BYTE {
+                    self.deserialize_bytes(visitor)
+                } else if elem.is_dict_entry() {
+                    self.deserialize_map(visitor)
+                } else {
+                    self.deserialize_seq(visitor)
+                }
+            }
+            b'(' => {
+                let len = self.ty.n_items();
+                if len > 0 {
+                    self.deserialize_tuple(len, visitor)
+                } else {
+                    self.deserialize_unit(visitor)
+                }
+            }
+            b'{' => self.deserialize_tuple(2, visitor),
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let [b] = self.read(VariantTy::BOOLEAN)?;
+        visitor.visit_bool(b != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let v = i16::from_ne_bytes(self.read(VariantTy::INT16)?);
+        visitor.visit_i8(v.try_into()?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i16(i16::from_ne_bytes(self.read(VariantTy::INT16)?))
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let ty = if self.ty == VariantTy::HANDLE {
+            VariantTy::HANDLE
+        } else {
+            VariantTy::INT32
+        };
+        visitor.visit_i32(i32::from_ne_bytes(self.read(ty)?))
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(i64::from_ne_bytes(self.read(VariantTy::INT64)?))
+    }
+
+    serde::serde_if_integer128! {
+        fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>
+        {
+            let (hi, lo) = self.read_pair(VariantTy::new("ax").unwrap())?;
+            let v = ((u64::from_ne_bytes(hi) as u128) << 64) | u64::from_ne_bytes(lo) as u128;
+            visitor.visit_i128(v as i128)
+        }
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let [v] = self.read(VariantTy::BYTE)?;
+        visitor.visit_u8(v)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u16(u16::from_ne_bytes(self.read(VariantTy::UINT16)?))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u32(u32::from_ne_bytes(self.read(VariantTy::UINT32)?))
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u64(u64::from_ne_bytes(self.read(VariantTy::UINT64)?))
+    }
+
+    serde::serde_if_integer128! {
+        fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>
+        {
+            let (hi, lo) = self.read_pair(VariantTy::new("at").unwrap())?;
+            let v = ((u64::from_ne_bytes(hi) as u128) << 64) | u64::from_ne_bytes(lo) as u128;
+            visitor.visit_u128(v)
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f32(f64::from_ne_bytes(self.read(VariantTy::DOUBLE)?) as f32)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f64(f64::from_ne_bytes(self.read(VariantTy::DOUBLE)?))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let s = self.str()?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(Error::ExpectedChar(s.to_owned())),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.str()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.str()?.to_owned())
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.expect(VariantTy::BYTE_STRING)?;
+        visitor.visit_borrowed_bytes(self.data)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.expect(VariantTy::BYTE_STRING)?;
+        visitor.visit_byte_buf(self.data.to_owned())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if !self.ty.is_maybe() {
+            return Err(self.mismatch(VariantTy::MAYBE));
+        }
+        match self.maybe() {
+            Some(child) => visitor.visit_some(child),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.expect(VariantTy::UNIT)?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if name == super::STRUCT_NAME {
+            self.expect(VariantTy::VARIANT)?;
+            let inner = self.boxed().to_glib();
+            visitor.visit_seq(VariantDeserializer::new(inner.as_serializable()))
+        } else if name == super::lazy::STRUCT_NAME {
+            super::handoff::offer(super::lazy::STRUCT_NAME, self.to_glib(), || {
+                visitor.visit_unit()
+            })
+        } else if name == super::fixed::STRUCT_NAME {
+            visitor.visit_seq(ArrayDataDecoder {
+                decoder: self,
+                index: 0,
+            })
+        } else {
+            visitor.visit_newtype_struct(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.ty.is_array() {
+            visitor.visit_seq(ArrayDecoder::new(self))
+        } else if self.ty.is_tuple() {
+            visitor.visit_seq(TupleDecoder::new(self))
+        } else {
+            Err(self.unsupported())
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if !self.ty.is_dict_entry() && !self.ty.is_tuple() {
+            return Err(self.mismatch(VariantTy::TUPLE));
+        }
+        if self.ty.n_items() != len {
+            return Err(Error::LengthMismatch {
+                actual: self.ty.n_items(),
+                expected: len,
+            });
+        }
+        visitor.visit_seq(TupleDecoder::new(self))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if name == super::STRUCT_NAME {
+            self.deserialize_newtype_struct(name, visitor)
+        } else {
+            self.deserialize_tuple(len, visitor)
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if !self.ty.is_subtype_of(VariantTy::DICTIONARY) {
+            return Err(self.mismatch(VariantTy::DICTIONARY));
+        }
+        visitor.visit_map(ArrayDecoder::new(self))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if self.ty.is_tuple() {
+            visitor.visit_enum(EnumDecoder(TupleDecoder::new(self)))
+        } else {
+            visitor.visit_enum(UnitEnumDecoder(self))
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.ty.as_str() {
+            "y" => self.deserialize_u8(visitor),
+            "n" => self.deserialize_i16(visitor),
+            "q" => self.deserialize_u16(visitor),
+            "i" => self.deserialize_i32(visitor),
+            "u" => self.deserialize_u32(visitor),
+            "x" => self.deserialize_i64(visitor),
+            "t" => self.deserialize_u64(visitor),
+            "s" => self.deserialize_str(visitor),
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+}
+
+/// Yields the type string and the data of an array with fixed-size elements, for
+/// [`FixedArray`](super::FixedArray).
+struct ArrayDataDecoder<'t, 'de: 't> {
+    decoder: Decoder<'t, 'de>,
+    index: usize,
+}
+
+impl<'t, 'de: 't> de::SeqAccess<'de> for ArrayDataDecoder<'t, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let Decoder { data, ty, .. } = self.decoder;
+        self.index += 1;
+        match self.index {
+            1 => seed.deserialize(ty.as_str().into_deserializer()).map(Some),
+            2 => {
+                let size = ty.is_array().then(|| fixed_size(ty.element())).flatten();
+                let data: &'de [u8] = match size {
+                    Some(size) if data.len() % size == 0 => data,
+                    _ => &[],
+                };
+                seed.deserialize(de::value::BorrowedBytesDeserializer::new(data))
+                    .map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some(2usize.saturating_sub(self.index))
+    }
+}
+
+/// Reads the elements of an array, or the entries of a dictionary.
+struct ArrayDecoder<'t, 'de: 't> {
+    data: &'de [u8],
+    element: &'t VariantTy,
+    depth: usize,
+    fixed_size: Option<usize>,
+    alignment: usize,
+    offset_size: usize,
+    /// Start of the framing offsets.
+    table: usize,
+    len: usize,
+    index: usize,
+    /// Framing offset of the previous element.
+    end: usize,
+    ordered: bool,
+    entry: Option<TupleDecoder<'t, 'de>>,
+}
+
+impl<'t, 'de: 't> ArrayDecoder<'t, 'de> {
+    fn new(decoder: Decoder<'t, 'de>) -> Self {
+        let Decoder { data, ty, depth } = decoder;
+        let element = ty.element();
+        let fixed_size = fixed_size(element);
+        let offset_size = offset_width(data.len());
+        let (len, table) = match fixed_size {
+            Some(size) if data.len() % size == 0 => (data.len() / size, data.len()),
+            Some(_) => (0, 0),
+            None if data.is_empty() => (0, 0),
+            None => {
+                let table = read_offset(data, data.len() - offset_size, offset_size);
+                if table <= data.len() && (data.len() - table) % offset_size == 0 {
+                    ((data.len() - table) / offset_size, table)
+                } else {
+                    (0, 0)
+                }
+            }
+        };
+        Self {
+            data,
+            element,
+            depth,
+            fixed_size,
+            alignment: alignment(element),
+            offset_size,
+            table,
+            len,
+            index: 0,
+            end: 0,
+            ordered: true,
+            entry: None,
+        }
+    }
+    fn next_child(&mut self) -> Option<Decoder<'t, 'de>> {
+        if self.index >= self.len {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        let data: &'de [u8] = match self.fixed_size {
+            Some(size) => &self.data[index * size..(index + 1) * size],
+            None => {
+                let pos = self.table + index * self.offset_size;
+                let end = read_offset(self.data, pos, self.offset_size);
+                // elements after an offset that goes backwards are all empty
+                self.ordered &= end >= self.end;
+                let start = align_to(self.end.min(self.table), self.alignment);
+                self.end = end;
+                if self.ordered && start < end && end <= self.table {
+                    &self.data[start..end]
+                } else {
+                    &[]
+                }
+            }
+        };
+        Some(Decoder::new(data, self.element, self.depth + 1))
+    }
+}
+
+impl<'t, 'de: 't> de::SeqAccess<'de> for ArrayDecoder<'t, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.next_child() {
+            Some(child) => seed.deserialize(child).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.index)
+    }
+}
+
+impl<'t, 'de: 't> de::MapAccess<'de> for ArrayDecoder<'t, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let mut entry = match self.next_child() {
+            Some(entry) => TupleDecoder::new(entry),
+            None => return Ok(None),
+        };
+        let key = seed.deserialize(entry.child()?)?;
+        self.entry = Some(entry);
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let mut entry = self
+            .entry
+            .take()
+            .ok_or_else(|| Error::Custom(String::from("Value requested before key")))?;
+        seed.deserialize(entry.child()?)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        <Self as de::SeqAccess>::size_hint(self)
+    }
+}
+
+/// Reads the children of a tuple or dictionary entry.
+struct TupleDecoder<'t, 'de: 't> {
+    data: &'de [u8],
+    ty: &'t VariantTy,
+    child: Option<&'t VariantTy>,
+    depth: usize,
+    offset_size: usize,
+    /// Number of framing offsets read so far.
+    offsets: usize,
+    /// End of the previous child.
+    end: usize,
+    ordered: bool,
+}
+
+impl<'t, 'de: 't> TupleDecoder<'t, 'de> {
+    fn new(decoder: Decoder<'t, 'de>) -> Self {
+        let Decoder { data, ty, depth } = decoder;
+        Self {
+            data,
+            ty,
+            child: ty.first(),
+            depth,
+            offset_size: offset_width(data.len()),
+            offsets: 0,
+            end: 0,
+            ordered: true,
+        }
+    }
+    fn next_child(&mut self) -> Option<Decoder<'t, 'de>> {
+        let ty = self.child?;
+        self.child = ty.next();
+        let len = self.data.len();
+        let start = align_to(self.end, alignment(ty));
+        let end = match fixed_size(ty) {
+            Some(size) => Some(start + size),
+            None if self.child.is_none() => len.checked_sub(self.offset_size * self.offsets),
+            None => {
+                self.offsets += 1;
+                let pos = len.checked_sub(self.offset_size * self.offsets);
+                // the child can't end inside the framing offsets
+                pos.and_then(|pos| {
+                    let end = read_offset(self.data, pos, self.offset_size);
+                    (end <= pos).then_some(end)
+                })
+            }
+        };
+        // children after one that is out of bounds are all empty
+        let end = end.filter(|end| self.ordered && start <= *end && *end <= len);
+        let data: &'de [u8] = match end {
+            Some(end) => {
+                self.end = end;
+                &self.data[start..end]
+            }
+            None => {
+                self.ordered = false;
+                &[]
+            }
+        };
+        Some(Decoder::new(data, ty, self.depth + 1))
+    }
+    fn child(&mut self) -> Result<Decoder<'t, 'de>, Error> {
+        self.next_child()
+            .ok_or_else(|| Error::UnsupportedType(self.ty.to_owned()))
+    }
+}
+
+impl<'t, 'de: 't> de::SeqAccess<'de> for TupleDecoder<'t, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.next_child() {
+            Some(child) => seed.deserialize(child).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Reads an enum variant stored as a `(tag, value)` tuple.
+struct EnumDecoder<'t, 'de: 't>(TupleDecoder<'t, 'de>);
+
+impl<'t, 'de: 't> de::EnumAccess<'de> for EnumDecoder<'t, 'de> {
+    type Error = Error;
+    type Variant = VariantDecoder<'t, 'de>;
+
+    fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let tag = seed.deserialize(self.0.child()?)?;
+        let value = self.0.child()?;
+        value.expect(VariantTy::VARIANT)?;
+        Ok((tag, VariantDecoder(value.boxed())))
+    }
+}
+
+/// Reads the value of an enum variant.
+struct VariantDecoder<'t, 'de: 't>(Decoder<'t, 'de>);
+
+impl<'t, 'de: 't> de::VariantAccess<'de> for VariantDecoder<'t, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.0.expect(VariantTy::UNIT)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self.0, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self.0, visitor)
+    }
+}
+
+/// Reads an enum variant stored as a bare tag.
+struct UnitEnumDecoder<'t, 'de: 't>(Decoder<'t, 'de>);
+
+impl<'t, 'de: 't> de::EnumAccess<'de> for UnitEnumDecoder<'t, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(self.0)?;
+        Ok((value, self))
+    }
+}
+
+impl<'t, 'de: 't> de::VariantAccess<'de> for UnitEnumDecoder<'t, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        Err(self.0.unsupported())
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(self.0.unsupported())
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(self.0.unsupported())
+    }
+}
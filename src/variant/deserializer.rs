@@ -2,12 +2,13 @@
 // SPDX-License-Identifier: MIT
 
 use super::{GlibVariantExt, Variant};
-use crate::Error;
+use crate::{Error, VariantType};
 use glib::{FixedSizeVariantType, VariantClass, VariantTy};
 use serde::{
     de::{self, IntoDeserializer, Visitor},
     Deserialize, Deserializer,
 };
+use std::ops::Deref;
 
 /// Deserializes `T` from a [`glib::Variant`](struct@glib::Variant).
 pub fn from_variant<'de, T>(variant: &'de glib::Variant) -> Result<T, Error>
@@ -17,9 +18,233 @@ where
     T::deserialize(variant.as_serializable())
 }
 
+/// Deserializes `T` from a [`glib::Variant`](struct@glib::Variant), checking the type of the
+/// whole variant against [`VariantType::variant_type`] first.
+///
+/// On a mismatch, returns [`Error::TypeMismatch`] with both type strings and the path to the first
+/// child type that differs. When the types are equal, children are not checked again.
+pub fn from_variant_checked<'de, T>(variant: &'de glib::Variant) -> Result<T, Error>
+where
+    T: VariantType + Deserialize<'de>,
+{
+    let expected = T::variant_type();
+    super::check_type(variant, expected.type_())?;
+    let mut deserializer =
+        OptionsDeserializer::new(variant.as_serializable(), DeserializeOptions::default());
+    deserializer.typed = variant.type_() == &**expected.type_();
+    T::deserialize(deserializer)
+}
+
+/// Checks that `variant` would deserialize into `T`, without building a `T`.
+///
+/// The type of `variant` is compared with [`VariantType::variant_type`] before any values are
+/// read. Checks that depend on values, such as enum nicks and flag names, are then run by
+/// [`VariantType::validate_value`], which walks only the children whose types need them.
+pub fn validate<T>(variant: &glib::Variant) -> Result<(), Error>
+where
+    T: VariantType + ?Sized,
+{
+    super::check_type(variant, T::variant_type().type_())?;
+    T::validate_value(variant)
+}
+
+/// Options for [`from_variant_with_options`]. All coercions are disabled by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DeserializeOptions {
+    /// Accept any integer type for integer fields, failing if the value is out of range.
+    pub coerce_integers: bool,
+    /// Accept `d` for integer fields, failing if the value is not integral.
+    pub integral_doubles: bool,
+    /// Unbox `v` values when the field is not itself a variant.
+    pub unbox_variants: bool,
+    /// Accept `a(kv)` where `a{kv}` is expected.
+    pub tuple_arrays_as_dicts: bool,
+}
+
+impl DeserializeOptions {
+    /// Returns options with every coercion enabled.
+    pub fn lenient() -> Self {
+        Self {
+            coerce_integers: true,
+            integral_doubles: true,
+            unbox_variants: true,
+            tuple_arrays_as_dicts: true,
+        }
+    }
+}
+
+/// Deserializes `T` from a [`glib::Variant`](struct@glib::Variant), coercing mismatched values
+/// as allowed by `options`.
+pub fn from_variant_with_options<'de, T>(
+    variant: &'de glib::Variant,
+    options: DeserializeOptions,
+) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(OptionsDeserializer::new(variant.as_serializable(), options))
+}
+
+/// Deserializer for a [`Variant`] and its children that coerces mismatched values as allowed by
+/// `options`. Deserializing `&Variant` directly uses the default options.
+///
+/// `typed` is set when the type of the whole variant was found equal to the expected type, so
+/// children only need their class checked. It is cleared for the contents of boxed variants.
+#[derive(Clone, Copy)]
+struct OptionsDeserializer<'v> {
+    input: &'v Variant,
+    options: DeserializeOptions,
+    typed: bool,
+}
+
+impl<'v> OptionsDeserializer<'v> {
+    fn new(input: &'v Variant, options: DeserializeOptions) -> Self {
+        Self {
+            input,
+            options,
+            typed: false,
+        }
+    }
+    fn child<'c>(&self, input: &'c Variant) -> OptionsDeserializer<'c> {
+        OptionsDeserializer { input, ..*self }
+    }
+    fn boxed_child<'c>(&self, input: &'c Variant) -> OptionsDeserializer<'c> {
+        OptionsDeserializer::new(input, self.options)
+    }
+    fn check_type(&self, class: VariantClass, ty: &VariantTy) -> Result<(), Error> {
+        if !(self.typed && self.classify() == class) {
+            self.is_of_type(ty)?;
+        }
+        Ok(())
+    }
+    fn unbox(&self) -> Option<glib::Variant> {
+        if self.options.unbox_variants {
+            self.input.as_variant()
+        } else {
+            None
+        }
+    }
+    fn coerced_integer(&self) -> Result<Option<i128>, Error> {
+        let variant = self.input;
+        if self.options.coerce_integers {
+            let value = match variant.classify() {
+                VariantClass::Byte => variant.get::<u8>().map(Into::into),
+                VariantClass::Int16 => variant.get::<i16>().map(Into::into),
+                VariantClass::Uint16 => variant.get::<u16>().map(Into::into),
+                VariantClass::Int32 => variant.get::<i32>().map(Into::into),
+                VariantClass::Uint32 => variant.get::<u32>().map(Into::into),
+                VariantClass::Int64 => variant.get::<i64>().map(Into::into),
+                VariantClass::Uint64 => variant.get::<u64>().map(Into::into),
+                _ => None,
+            };
+            if value.is_some() {
+                return Ok(value);
+            }
+        }
+        if self.options.integral_doubles && variant.classify() == VariantClass::Double {
+            let value = variant.get::<f64>().unwrap();
+            if value.fract() != 0.0 || !value.is_finite() || value.abs() >= 2f64.powi(64) {
+                return Err(Error::Custom(format!(
+                    "Expected integral value, got '{}'",
+                    value
+                )));
+            }
+            return Ok(Some(value as i128));
+        }
+        Ok(None)
+    }
+}
+
+impl<'v> Deref for OptionsDeserializer<'v> {
+    type Target = Variant;
+    fn deref(&self) -> &Self::Target {
+        self.input
+    }
+}
+
+macro_rules! forward_with_default_options {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[inline]
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                OptionsDeserializer::new(self, DeserializeOptions::default())
+                    .$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
 impl<'de> de::Deserializer<'de> for &Variant {
     type Error = Error;
 
+    forward_with_default_options! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    serde::serde_if_integer128! {
+        forward_with_default_options! {
+            deserialize_i128();
+            deserialize_u128();
+        }
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+}
+
+macro_rules! try_unbox {
+    ($self:ident.$method:ident($($arg:expr),*)) => {
+        if let Some(inner) = $self.unbox() {
+            return $self.boxed_child(inner.as_serializable()).$method($($arg),*);
+        }
+    };
+}
+
+macro_rules! try_coerce {
+    ($self:ident, $visitor:ident.$visit:ident) => {
+        if let Some(value) = $self.coerced_integer()? {
+            return $visitor.$visit(value.try_into()?);
+        }
+    };
+}
+
+impl<'v, 'de> de::Deserializer<'de> for OptionsDeserializer<'v> {
+    type Error = Error;
+
     fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
         match self.classify() {
             VariantClass::Boolean => self.deserialize_bool(visitor),
@@ -30,13 +255,15 @@ impl<'de> de::Deserializer<'de> for &Variant {
             VariantClass::Uint32 => self.deserialize_u32(visitor),
             VariantClass::Int64 => self.deserialize_i64(visitor),
             VariantClass::Uint64 => self.deserialize_u64(visitor),
-            VariantClass::Double => self.deserialize_bool(visitor),
+            VariantClass::Handle => self.deserialize_i32(visitor),
+            VariantClass::Double => self.deserialize_f64(visitor),
             VariantClass::String | VariantClass::ObjectPath | VariantClass::Signature => {
                 self.deserialize_str(visitor)
             }
             VariantClass::Variant => {
                 let variant = self.try_get::<glib::Variant>()?;
-                variant.as_serializable().deserialize_any(visitor)
+                self.boxed_child(variant.as_serializable())
+                    .deserialize_any(visitor)
             }
             VariantClass::Maybe => self.deserialize_option(visitor),
             VariantClass::Array => {
@@ -63,22 +290,34 @@ impl<'de> de::Deserializer<'de> for &Variant {
     }
 
     fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_bool(visitor));
         visitor.visit_bool(self.try_get()?)
     }
 
     fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_i8(visitor));
+        try_coerce!(self, visitor.visit_i8);
         visitor.visit_i8(self.try_get::<i16>()?.try_into()?)
     }
 
     fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_i16(visitor));
+        try_coerce!(self, visitor.visit_i16);
         visitor.visit_i16(self.try_get()?)
     }
 
     fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_i32(self.try_get()?)
+        try_unbox!(self.deserialize_i32(visitor));
+        try_coerce!(self, visitor.visit_i32);
+        match self.handle() {
+            Some(handle) => visitor.visit_i32(handle),
+            None => visitor.visit_i32(self.try_get()?),
+        }
     }
 
     fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_i64(visitor));
+        try_coerce!(self, visitor.visit_i64);
         visitor.visit_i64(self.try_get()?)
     }
 
@@ -96,18 +335,26 @@ impl<'de> de::Deserializer<'de> for &Variant {
     }
 
     fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_u8(visitor));
+        try_coerce!(self, visitor.visit_u8);
         visitor.visit_u8(self.try_get()?)
     }
 
     fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_u16(visitor));
+        try_coerce!(self, visitor.visit_u16);
         visitor.visit_u16(self.try_get()?)
     }
 
     fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_u32(visitor));
+        try_coerce!(self, visitor.visit_u32);
         visitor.visit_u32(self.try_get()?)
     }
 
     fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_u64(visitor));
+        try_coerce!(self, visitor.visit_u64);
         visitor.visit_u64(self.try_get()?)
     }
 
@@ -125,25 +372,29 @@ impl<'de> de::Deserializer<'de> for &Variant {
     }
 
     fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_f32(visitor));
         visitor.visit_f32(self.try_get::<f64>()? as f32)
     }
 
     fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_f64(visitor));
         visitor.visit_f64(self.try_get()?)
     }
 
     fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_char(visitor));
         let s = self
             .str()
             .ok_or_else(|| Error::StrMismatch(self.type_().to_owned()))?;
-        let c = s
-            .chars()
-            .next()
-            .ok_or_else(|| Error::ExpectedChar(s.to_owned()))?;
-        visitor.visit_char(c)
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(Error::ExpectedChar(s.to_owned())),
+        }
     }
 
     fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_str(visitor));
         let s = self
             .str()
             .ok_or_else(|| Error::StrMismatch(self.type_().to_owned()))?;
@@ -151,6 +402,7 @@ impl<'de> de::Deserializer<'de> for &Variant {
     }
 
     fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_string(visitor));
         let s = self
             .str()
             .ok_or_else(|| Error::StrMismatch(self.type_().to_owned()))?;
@@ -158,22 +410,26 @@ impl<'de> de::Deserializer<'de> for &Variant {
     }
 
     fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_bytes(visitor));
         visitor.visit_bytes(self.fixed_array()?)
     }
 
     fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_byte_buf(visitor));
         visitor.visit_byte_buf(self.fixed_array()?.to_owned())
     }
 
     fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        self.is_of_type(VariantTy::MAYBE)?;
+        try_unbox!(self.deserialize_option(visitor));
+        self.check_type(VariantClass::Maybe, VariantTy::MAYBE)?;
         match self.maybe().unwrap() {
-            Some(child) => visitor.visit_some(child.as_serializable()),
+            Some(child) => visitor.visit_some(self.child(child.as_serializable())),
             None => visitor.visit_none(),
         }
     }
 
     fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_unit(visitor));
         self.try_get::<()>()?;
         visitor.visit_unit()
     }
@@ -183,18 +439,35 @@ impl<'de> de::Deserializer<'de> for &Variant {
         _name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_unit(visitor));
         self.deserialize_unit(visitor)
     }
 
     fn deserialize_newtype_struct<V: Visitor<'de>>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        visitor.visit_newtype_struct(self)
+        if name == super::STRUCT_NAME {
+            self.is_of_type(VariantTy::VARIANT)?;
+            let inner = self.as_variant().unwrap();
+            visitor.visit_seq(VariantDeserializer::new(inner.as_serializable()))
+        } else if name == super::lazy::STRUCT_NAME {
+            super::handoff::offer(
+                super::lazy::STRUCT_NAME,
+                glib::Variant::clone(self.input),
+                || visitor.visit_unit(),
+            )
+        } else if name == super::fixed::STRUCT_NAME {
+            visitor.visit_seq(VariantDeserializer::new(self.input))
+        } else {
+            try_unbox!(self.deserialize_newtype_struct(name, visitor));
+            visitor.visit_newtype_struct(self)
+        }
     }
 
     fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_seq(visitor));
         let ty = self.type_();
         if ty.is_array() {
             match ty.element().as_str() {
@@ -239,7 +512,10 @@ impl<'de> de::Deserializer<'de> for &Variant {
         len: usize,
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        self.is_of_type(VariantTy::TUPLE)?;
+        try_unbox!(self.deserialize_tuple(len, visitor));
+        if !self.type_().is_dict_entry() {
+            self.check_type(VariantClass::Tuple, VariantTy::TUPLE)?;
+        }
         if self.n_children() != len {
             return Err(Error::LengthMismatch {
                 actual: self.n_children(),
@@ -261,12 +537,20 @@ impl<'de> de::Deserializer<'de> for &Variant {
             let inner = self.as_variant().unwrap();
             visitor.visit_seq(VariantDeserializer::new(inner.as_serializable()))
         } else {
+            try_unbox!(self.deserialize_tuple(len, visitor));
             self.deserialize_tuple(len, visitor)
         }
     }
 
     fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        self.is_of_type(VariantTy::DICTIONARY)?;
+        try_unbox!(self.deserialize_map(visitor));
+        let ty = self.type_();
+        let is_tuple_array =
+            ty.is_array() && ty.element().is_tuple() && ty.element().n_items() == 2;
+        let is_dictionary = self.typed && ty.is_array() && ty.element().is_dict_entry();
+        if !(is_dictionary || (is_tuple_array && self.options.tuple_arrays_as_dicts)) {
+            self.is_of_type(VariantTy::DICTIONARY)?;
+        }
         visitor.visit_map(ContainerDeserializer::new(self))
     }
 
@@ -281,10 +565,11 @@ impl<'de> de::Deserializer<'de> for &Variant {
 
     fn deserialize_enum<V: Visitor<'de>>(
         self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
+        name: &'static str,
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_enum(name, variants, visitor));
         if self.is_container() {
             visitor.visit_enum(EnumDeserializer::new(self))
         } else {
@@ -293,6 +578,7 @@ impl<'de> de::Deserializer<'de> for &Variant {
     }
 
     fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        try_unbox!(self.deserialize_identifier(visitor));
         match self.classify() {
             VariantClass::Byte => self.deserialize_u8(visitor),
             VariantClass::Int16 => self.deserialize_i16(visitor),
@@ -312,17 +598,16 @@ impl<'de> de::Deserializer<'de> for &Variant {
 
     #[inline]
     fn is_human_readable(&self) -> bool {
-        false
+        true
     }
 }
 
-#[repr(transparent)]
 struct EnumDeserializer<'v> {
-    input: &'v Variant,
+    input: OptionsDeserializer<'v>,
 }
 
 impl<'v> EnumDeserializer<'v> {
-    fn new(input: &'v Variant) -> Self {
+    fn new(input: OptionsDeserializer<'v>) -> Self {
         Self { input }
     }
     fn value(&self) -> Result<Variant, Error> {
@@ -346,7 +631,7 @@ impl<'v, 'de> de::EnumAccess<'de> for EnumDeserializer<'v> {
             .input
             .try_child_value(0)
             .ok_or_else(|| Error::UnsupportedType(self.input.type_().to_owned()))?;
-        let value = seed.deserialize(tag.as_serializable())?;
+        let value = seed.deserialize(self.input.child(tag.as_serializable()))?;
         Ok((value, self))
     }
 }
@@ -363,14 +648,16 @@ impl<'v, 'de> de::VariantAccess<'de> for EnumDeserializer<'v> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(&self.value()?)
+        seed.deserialize(self.input.boxed_child(&self.value()?))
     }
 
     fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.value()?.deserialize_seq(visitor)
+        self.input
+            .boxed_child(&self.value()?)
+            .deserialize_seq(visitor)
     }
 
     fn struct_variant<V>(
@@ -381,17 +668,18 @@ impl<'v, 'de> de::VariantAccess<'de> for EnumDeserializer<'v> {
     where
         V: Visitor<'de>,
     {
-        self.value()?.deserialize_seq(visitor)
+        self.input
+            .boxed_child(&self.value()?)
+            .deserialize_seq(visitor)
     }
 }
 
-#[repr(transparent)]
 struct UnitEnumDeserializer<'v> {
-    input: &'v Variant,
+    input: OptionsDeserializer<'v>,
 }
 
 impl<'v> UnitEnumDeserializer<'v> {
-    fn new(input: &'v Variant) -> Self {
+    fn new(input: OptionsDeserializer<'v>) -> Self {
         Self { input }
     }
 }
@@ -482,13 +770,13 @@ impl<'v, 'de, V: FixedSizeVariantType + IntoDeserializer<'de, Error>> de::SeqAcc
     }
 }
 
-struct VariantDeserializer<'v> {
+pub(super) struct VariantDeserializer<'v> {
     input: &'v Variant,
     index: usize,
 }
 
 impl<'v> VariantDeserializer<'v> {
-    fn new(input: &'v Variant) -> Self {
+    pub(super) fn new(input: &'v Variant) -> Self {
         Self { input, index: 0 }
     }
 }
@@ -508,7 +796,9 @@ impl<'v, 'de> de::SeqAccess<'de> for VariantDeserializer<'v> {
             }
             1 => {
                 self.index += 1;
-                seed.deserialize(self.input).map(Some)
+                let normal = self.input.normal_form();
+                let deserializer = de::value::BytesDeserializer::<Error>::new(normal.data());
+                seed.deserialize(deserializer).map(Some)
             }
             _ => Ok(None),
         }
@@ -520,12 +810,12 @@ impl<'v, 'de> de::SeqAccess<'de> for VariantDeserializer<'v> {
 }
 
 struct ContainerDeserializer<'v> {
-    input: &'v Variant,
+    input: OptionsDeserializer<'v>,
     index: usize,
 }
 
 impl<'v> ContainerDeserializer<'v> {
-    fn new(input: &'v Variant) -> Self {
+    fn new(input: OptionsDeserializer<'v>) -> Self {
         Self { input, index: 0 }
     }
 }
@@ -542,7 +832,8 @@ impl<'v, 'de> de::SeqAccess<'de> for ContainerDeserializer<'v> {
         }
         let child = self.input.child_value(self.index);
         self.index += 1;
-        seed.deserialize(child.as_serializable()).map(Some)
+        seed.deserialize(self.input.child(child.as_serializable()))
+            .map(Some)
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -562,7 +853,8 @@ impl<'v, 'de> de::MapAccess<'de> for ContainerDeserializer<'v> {
         }
         let entry = self.input.child_value(self.index);
         let key = entry.child_value(0);
-        seed.deserialize(key.as_serializable()).map(Some)
+        seed.deserialize(self.input.child(key.as_serializable()))
+            .map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
@@ -572,7 +864,7 @@ impl<'v, 'de> de::MapAccess<'de> for ContainerDeserializer<'v> {
         let entry = self.input.child_value(self.index);
         self.index += 1;
         let value = entry.child_value(1);
-        seed.deserialize(value.as_serializable())
+        seed.deserialize(self.input.child(value.as_serializable()))
     }
 
     fn next_entry_seed<K, V>(
@@ -591,8 +883,8 @@ impl<'v, 'de> de::MapAccess<'de> for ContainerDeserializer<'v> {
         self.index += 1;
         let key = entry.child_value(0);
         let value = entry.child_value(1);
-        let key = kseed.deserialize(key.as_serializable())?;
-        let value = vseed.deserialize(value.as_serializable())?;
+        let key = kseed.deserialize(self.input.child(key.as_serializable()))?;
+        let value = vseed.deserialize(self.input.child(value.as_serializable()))?;
         Ok(Some((key, value)))
     }
 
@@ -0,0 +1,849 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use super::{
+    serializer::{child_type_or_default, serialize_with_node, variant_tag, VariantTag},
+    GlibVariantExt,
+};
+use crate::{object_path, signature, Error, ObjectPath, Signature, VariantType, VariantTypeNode};
+use glib::{variant::VariantTypeMismatchError, VariantTy};
+use serde::{ser, Serialize};
+use std::{borrow::Cow, ops::Deref};
+
+/// Serializes `T` into GVariant data in normal form, without constructing any
+/// [`glib::Variant`](struct@glib::Variant)s.
+///
+/// The exceptions are newtype enum variants and [`LazyVariant`](crate::LazyVariant)s, which are
+/// serialized with [`to_variant`](crate::to_variant) and then copied into the output.
+pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + VariantType,
+{
+    let ty = T::variant_type();
+    to_bytes_with_type(value, &ty)
+}
+
+/// Serializes `value` into GVariant data in normal form for the definite type `node`.
+pub fn to_bytes_with_type<T>(value: &T, node: &VariantTypeNode) -> Result<Vec<u8>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    if !node.type_().is_definite() {
+        return Err(Error::UnsupportedType(node.type_().deref().to_owned()));
+    }
+    let mut out = Vec::new();
+    value.serialize(Encoder::new(&mut out, node))?;
+    Ok(out)
+}
+
+/// Serializes `T` with [`to_bytes`] and wraps the data in a
+/// [`glib::Variant`](struct@glib::Variant).
+pub fn to_variant_via_bytes<T>(value: &T) -> Result<glib::Variant, Error>
+where
+    T: Serialize + VariantType,
+{
+    let ty = T::variant_type();
+    let data = to_bytes_with_type(value, &ty)?;
+    Ok(glib::Variant::from_bytes_with_type(
+        &glib::Bytes::from_owned(data),
+        ty.type_(),
+    ))
+}
+
+/// Returns the alignment of values of type `ty`.
+pub(super) fn alignment(ty: &VariantTy) -> usize {
+    match ty.as_str().as_bytes()[0] {
+        b'n' | b'q' => 2,
+        b'i' | b'u' | b'h' => 4,
+        b'x' | b't' | b'd' | b'v' => 8,
+        b'm' | b'a' => alignment(ty.element()),
+        b'(' | b'{' => children(ty).map(alignment).max().unwrap_or(1),
+        _ => 1,
+    }
+}
+
+/// Returns the size of values of type `ty`, or `None` if values of `ty` vary in size.
+pub(super) fn fixed_size(ty: &VariantTy) -> Option<usize> {
+    match ty.as_str().as_bytes()[0] {
+        b'y' | b'b' => Some(1),
+        b'n' | b'q' => Some(2),
+        b'i' | b'u' | b'h' => Some(4),
+        b'x' | b't' | b'd' => Some(8),
+        b'(' | b'{' => {
+            let mut size = 0;
+            for child in children(ty) {
+                size = align_to(size, alignment(child)) + fixed_size(child)?;
+            }
+            // the unit type takes one byte
+            Some(align_to(size.max(1), alignment(ty)))
+        }
+        _ => None,
+    }
+}
+
+pub(super) fn children(ty: &VariantTy) -> impl Iterator<Item = &VariantTy> {
+    std::iter::successors(ty.first(), |child| child.next())
+}
+
+#[inline]
+pub(super) fn align_to(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+/// Returns the width of the framing offsets in a container with `body` bytes of children and
+/// `count` offsets.
+pub(super) fn offset_size(body: usize, count: usize) -> usize {
+    if count == 0 {
+        0
+    } else if body + count <= u8::MAX as usize {
+        1
+    } else if body + 2 * count <= u16::MAX as usize {
+        2
+    } else if body as u64 + 4 * count as u64 <= u32::MAX as u64 {
+        4
+    } else {
+        8
+    }
+}
+
+#[inline]
+fn pad(out: &mut Vec<u8>, alignment: usize) {
+    out.resize(align_to(out.len(), alignment), 0);
+}
+
+/// Appends `offsets` to the container starting at `start`.
+fn write_offsets(out: &mut Vec<u8>, start: usize, offsets: impl ExactSizeIterator<Item = usize>) {
+    let size = offset_size(out.len() - start, offsets.len());
+    for offset in offsets {
+        out.extend_from_slice(&(offset as u64).to_le_bytes()[..size]);
+    }
+}
+
+fn write_tag(out: &mut Vec<u8>, tag: &VariantTag) {
+    match tag {
+        VariantTag::Str(s) => {
+            out.extend_from_slice(s.as_bytes());
+            out.push(0);
+        }
+        VariantTag::I16(i) => out.extend_from_slice(&i.to_ne_bytes()),
+        VariantTag::I32(i) => out.extend_from_slice(&i.to_ne_bytes()),
+        VariantTag::I64(i) => out.extend_from_slice(&i.to_ne_bytes()),
+        VariantTag::U8(u) => out.push(*u),
+        VariantTag::U16(u) => out.extend_from_slice(&u.to_ne_bytes()),
+        VariantTag::U32(u) => out.extend_from_slice(&u.to_ne_bytes()),
+        VariantTag::U64(u) => out.extend_from_slice(&u.to_ne_bytes()),
+    }
+}
+
+/// Writes the `(tag, value)` tuple used for enum variants with data, where `value` is written by
+/// `func` and boxed in a variant. `func` returns the type of the value.
+fn write_tagged<F>(out: &mut Vec<u8>, tag: &VariantTag, func: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<glib::VariantType, Error>,
+{
+    let start = out.len();
+    write_tag(out, tag);
+    let tag_end = out.len() - start;
+    pad(out, 8);
+    let ty = func(out)?;
+    out.push(0);
+    out.extend_from_slice(ty.as_str().as_bytes());
+    if matches!(tag, VariantTag::Str(_)) {
+        write_offsets(out, start, std::iter::once(tag_end));
+    }
+    Ok(())
+}
+
+struct Encoder<'a, 't, 'n> {
+    out: &'a mut Vec<u8>,
+    node: &'t VariantTypeNode<'n>,
+    human_readable: bool,
+}
+
+impl<'a, 't, 'n> Encoder<'a, 't, 'n> {
+    fn new(out: &'a mut Vec<u8>, node: &'t VariantTypeNode<'n>) -> Self {
+        Self {
+            out,
+            node,
+            human_readable: true,
+        }
+    }
+    fn expect(&self, ty: &VariantTy) -> Result<(), Error> {
+        let expected: &VariantTy = self.node.type_();
+        if expected == ty {
+            Ok(())
+        } else {
+            Err(Error::Mismatch(VariantTypeMismatchError::new(
+                ty.to_owned(),
+                expected.to_owned(),
+            )))
+        }
+    }
+    fn write(self, ty: &VariantTy, data: &[u8]) -> Result<(), Error> {
+        self.expect(ty)?;
+        self.out.extend_from_slice(data);
+        Ok(())
+    }
+    fn unsupported(&self) -> Error {
+        Error::UnsupportedType(self.node.type_().deref().to_owned())
+    }
+}
+
+impl<'a, 't, 'n> ser::Serializer for Encoder<'a, 't, 'n> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = ArrayEncoder<'a, 't, 'n>;
+    type SerializeTuple = TupleEncoder<'a, 't, 'n>;
+    type SerializeTupleStruct = TupleEncoder<'a, 't, 'n>;
+    type SerializeTupleVariant = TupleVariantEncoder<'a, 't, 'n>;
+    type SerializeMap = MapEncoder<'a, 't, 'n>;
+    type SerializeStruct = TupleEncoder<'a, 't, 'n>;
+    type SerializeStructVariant = TupleVariantEncoder<'a, 't, 'n>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::BOOLEAN, &[v as u8])
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i16(v as i16)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::INT16, &v.to_ne_bytes())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        if self.node.type_().deref() == VariantTy::HANDLE {
+            self.write(VariantTy::HANDLE, &v.to_ne_bytes())
+        } else {
+            self.write(VariantTy::INT32, &v.to_ne_bytes())
+        }
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::INT64, &v.to_ne_bytes())
+    }
+
+    serde::serde_if_integer128! {
+        fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+            let v = v as u128;
+            let mut buf = [0u8; 16];
+            buf[..8].copy_from_slice(&((v >> 64) as i64).to_ne_bytes());
+            buf[8..].copy_from_slice(&(v as i64).to_ne_bytes());
+            self.write(VariantTy::new("ax").unwrap(), &buf)
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::BYTE, &[v])
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::UINT16, &v.to_ne_bytes())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::UINT32, &v.to_ne_bytes())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::UINT64, &v.to_ne_bytes())
+    }
+
+    serde::serde_if_integer128! {
+        fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+            let mut buf = [0u8; 16];
+            buf[..8].copy_from_slice(&((v >> 64) as u64).to_ne_bytes());
+            buf[8..].copy_from_slice(&(v as u64).to_ne_bytes());
+            self.write(VariantTy::new("at").unwrap(), &buf)
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::DOUBLE, &v.to_ne_bytes())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        let ty = self.node.type_();
+        match ty.as_str() {
+            "o" => {
+                ObjectPath::new(v).map_err(Error::Bool)?;
+            }
+            "g" => {
+                Signature::new(v).map_err(Error::Bool)?;
+            }
+            "s" => {
+                if v.contains('\0') {
+                    return Err(Error::Custom(String::from("String contains a nul byte")));
+                }
+            }
+            _ => return Err(Error::StrMismatch(ty.deref().to_owned())),
+        }
+        self.out.extend_from_slice(v.as_bytes());
+        self.out.push(0);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::BYTE_STRING, v)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        if !self.node.type_().is_maybe() {
+            return Err(self.unsupported());
+        }
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if !self.node.type_().is_maybe() {
+            return Err(self.unsupported());
+        }
+        let child = child_type_or_default(self.node, 0);
+        value.serialize(Encoder::new(self.out, &child))?;
+        if fixed_size(child.type_()).is_none() {
+            self.out.push(0);
+        }
+        Ok(())
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::UNIT, &[0])
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.write(VariantTy::UNIT, &[0])
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        let (tag, value_ty) = variant_tag(self.node, variant_index, variant)?;
+        if value_ty.is_some() {
+            write_tagged(self.out, &tag, |out| {
+                out.push(0);
+                Ok(VariantTy::UNIT.to_owned())
+            })
+        } else {
+            write_tag(self.out, &tag);
+            Ok(())
+        }
+    }
+
+    fn serialize_newtype_struct<T: ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize,
+    {
+        static UNIT_NODE: VariantTypeNode<'static> = VariantTypeNode::new_static(VariantTy::UNIT);
+        static BYTES_NODE: VariantTypeNode<'static> =
+            VariantTypeNode::new_static(VariantTy::BYTE_STRING);
+        match name {
+            object_path::STRUCT_NAME => {
+                self.expect(VariantTy::OBJECT_PATH)?;
+                value.serialize(self)
+            }
+            signature::STRUCT_NAME => {
+                self.expect(VariantTy::SIGNATURE)?;
+                value.serialize(self)
+            }
+            super::STRUCT_NAME => value.serialize(Encoder {
+                human_readable: false,
+                ..self
+            }),
+            super::lazy::STRUCT_NAME => {
+                let ty = self.node.type_();
+                let variant =
+                    super::lazy::capture(value, ty, || serialize_with_node(value, &UNIT_NODE))?;
+                variant.is_of_type(ty)?;
+                self.out.extend_from_slice(variant.normal_form().data());
+                Ok(())
+            }
+            super::fixed::STRUCT_NAME => super::fixed::raw(value, self.node.type_(), || {
+                value.serialize(Encoder::new(self.out, &BYTES_NODE))
+            }),
+            _ => value.serialize(self),
+        }
+    }
+
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize,
+    {
+        let (tag, _) = variant_tag(self.node, variant_index, variant)?;
+        let value = serialize_with_node(value, self.node)?;
+        write_tagged(self.out, &tag, |out| {
+            out.extend_from_slice(value.normal_form().data());
+            Ok(value.type_().to_owned())
+        })
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        if !self.node.type_().is_array() {
+            return Err(self.unsupported());
+        }
+        Ok(ArrayEncoder::new(self.out, self.node))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        TupleEncoder::new(self, "")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        TupleEncoder::new(self, name)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        TupleVariantEncoder::new(self, variant_index, variant)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        MapEncoder::new(self)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        TupleEncoder::new(self, name)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        TupleVariantEncoder::new(self, variant_index, variant)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
+    }
+}
+
+struct ArrayEncoder<'a, 't, 'n> {
+    out: &'a mut Vec<u8>,
+    element: Cow<'t, VariantTypeNode<'n>>,
+    alignment: usize,
+    fixed: bool,
+    start: usize,
+    offsets: Vec<usize>,
+}
+
+impl<'a, 't, 'n> ArrayEncoder<'a, 't, 'n> {
+    fn new(out: &'a mut Vec<u8>, node: &'t VariantTypeNode<'n>) -> Self {
+        let element = child_type_or_default(node, 0);
+        let ty = element.type_();
+        Self {
+            alignment: alignment(ty),
+            fixed: fixed_size(ty).is_some(),
+            start: out.len(),
+            out,
+            element,
+            offsets: Vec::new(),
+        }
+    }
+}
+
+impl<'a, 't, 'n> ser::SerializeSeq for ArrayEncoder<'a, 't, 'n> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize,
+    {
+        pad(self.out, self.alignment);
+        value.serialize(Encoder::new(self.out, &self.element))?;
+        if !self.fixed {
+            self.offsets.push(self.out.len() - self.start);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        write_offsets(self.out, self.start, self.offsets.into_iter());
+        Ok(())
+    }
+}
+
+/// Writes the children of a tuple or dictionary entry, and their framing offsets.
+struct TupleFrame<'t, 'n> {
+    node: Cow<'t, VariantTypeNode<'n>>,
+    start: usize,
+    index: usize,
+    offsets: Vec<usize>,
+}
+
+impl<'t, 'n> TupleFrame<'t, 'n> {
+    fn new(node: Cow<'t, VariantTypeNode<'n>>, start: usize) -> Result<Self, Error> {
+        let ty = node.type_();
+        if !ty.is_tuple() && !ty.is_dict_entry() {
+            return Err(Error::UnsupportedType(ty.deref().to_owned()));
+        }
+        Ok(Self {
+            node,
+            start,
+            index: 0,
+            offsets: Vec::new(),
+        })
+    }
+    fn element<T>(&mut self, out: &mut Vec<u8>, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let count = self.node.type_().n_items();
+        if self.index >= count {
+            return Err(Error::LengthMismatch {
+                actual: self.index + 1,
+                expected: count,
+            });
+        }
+        let child = child_type_or_default(&self.node, self.index);
+        pad(out, alignment(child.type_()));
+        value.serialize(Encoder::new(out, &child))?;
+        self.index += 1;
+        if self.index < count && fixed_size(child.type_()).is_none() {
+            self.offsets.push(out.len() - self.start);
+        }
+        Ok(())
+    }
+    fn end(self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let ty = self.node.type_();
+        if self.index != ty.n_items() {
+            return Err(Error::LengthMismatch {
+                actual: self.index,
+                expected: ty.n_items(),
+            });
+        }
+        match fixed_size(ty) {
+            Some(size) => out.resize(self.start + size, 0),
+            None => write_offsets(out, self.start, self.offsets.into_iter().rev()),
+        }
+        Ok(())
+    }
+}
+
+enum TupleEncoder<'a, 't, 'n> {
+    Tuple {
+        out: &'a mut Vec<u8>,
+        frame: TupleFrame<'t, 'n>,
+    },
+    /// Type string and data of a [`Variant`](super::Variant), written as the contents of a
+    /// variant.
+    Variant {
+        out: &'a mut Vec<u8>,
+        ty: Option<glib::VariantType>,
+        done: bool,
+    },
+}
+
+impl<'a, 't, 'n> TupleEncoder<'a, 't, 'n> {
+    fn new(encoder: Encoder<'a, 't, 'n>, name: &'static str) -> Result<Self, Error> {
+        if name == super::STRUCT_NAME {
+            encoder.expect(VariantTy::VARIANT)?;
+            Ok(Self::Variant {
+                out: encoder.out,
+                ty: None,
+                done: false,
+            })
+        } else {
+            let start = encoder.out.len();
+            Ok(Self::Tuple {
+                frame: TupleFrame::new(Cow::Borrowed(encoder.node), start)?,
+                out: encoder.out,
+            })
+        }
+    }
+}
+
+impl<'a, 't, 'n> ser::SerializeTuple for TupleEncoder<'a, 't, 'n> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize,
+    {
+        static ANY_NODE: VariantTypeNode<'static> = VariantTypeNode::new_static(VariantTy::ANY);
+        static BYTES_NODE: VariantTypeNode<'static> =
+            VariantTypeNode::new_static(VariantTy::BYTE_STRING);
+        match self {
+            Self::Tuple { out, frame } => frame.element(out, value),
+            Self::Variant {
+                out: _,
+                ty: ty @ None,
+                done: _,
+            } => {
+                let type_str = serialize_with_node(value, &ANY_NODE)?;
+                let type_str = type_str
+                    .str()
+                    .ok_or_else(|| Error::StrMismatch(type_str.type_().to_owned()))?;
+                let new_ty = glib::VariantType::new(type_str)?;
+                if !new_ty.is_definite() {
+                    return Err(Error::UnsupportedType(new_ty));
+                }
+                ty.replace(new_ty);
+                Ok(())
+            }
+            Self::Variant {
+                out,
+                ty: Some(ty),
+                done,
+            } => {
+                if *done {
+                    return Err(Error::LengthMismatch {
+                        actual: 3,
+                        expected: 2,
+                    });
+                }
+                value.serialize(Encoder::new(out, &BYTES_NODE))?;
+                out.push(0);
+                out.extend_from_slice(ty.as_str().as_bytes());
+                *done = true;
+                Ok(())
+            }
+        }
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self {
+            Self::Tuple { out, frame } => frame.end(out),
+            Self::Variant { done: true, .. } => Ok(()),
+            Self::Variant { ty, .. } => Err(Error::LengthMismatch {
+                actual: ty.is_some() as usize,
+                expected: 2,
+            }),
+        }
+    }
+}
+
+impl<'a, 't, 'n> ser::SerializeTupleStruct for TupleEncoder<'a, 't, 'n> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize,
+    {
+        ser::SerializeTuple::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeTuple::end(self)
+    }
+}
+
+impl<'a, 't, 'n> ser::SerializeStruct for TupleEncoder<'a, 't, 'n> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error>
+    where
+        T: Serialize,
+    {
+        ser::SerializeTuple::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeTuple::end(self)
+    }
+}
+
+struct TupleVariantEncoder<'a, 't, 'n> {
+    out: &'a mut Vec<u8>,
+    start: usize,
+    tag_end: Option<usize>,
+    frame: TupleFrame<'t, 'n>,
+}
+
+impl<'a, 't, 'n> TupleVariantEncoder<'a, 't, 'n> {
+    fn new(
+        encoder: Encoder<'a, 't, 'n>,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self, Error> {
+        let (tag, value_ty) = variant_tag(encoder.node, variant_index, variant)?;
+        let value_ty = value_ty.ok_or_else(|| encoder.unsupported())?;
+        let out = encoder.out;
+        let start = out.len();
+        write_tag(out, &tag);
+        let tag_end = out.len() - start;
+        let tag_end = matches!(tag, VariantTag::Str(_)).then_some(tag_end);
+        pad(out, 8);
+        let frame = TupleFrame::new(value_ty, out.len())?;
+        Ok(Self {
+            out,
+            start,
+            tag_end,
+            frame,
+        })
+    }
+    fn finish(self) -> Result<(), Error> {
+        let Self {
+            out,
+            start,
+            tag_end,
+            frame,
+        } = self;
+        let value_ty = frame.node.clone();
+        frame.end(out)?;
+        out.push(0);
+        out.extend_from_slice(value_ty.type_().as_str().as_bytes());
+        write_offsets(out, start, tag_end.into_iter());
+        Ok(())
+    }
+}
+
+impl<'a, 't, 'n> ser::SerializeTupleVariant for TupleVariantEncoder<'a, 't, 'n> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize,
+    {
+        self.frame.element(self.out, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'a, 't, 'n> ser::SerializeStructVariant for TupleVariantEncoder<'a, 't, 'n> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error>
+    where
+        T: Serialize,
+    {
+        self.frame.element(self.out, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+struct MapEncoder<'a, 't, 'n> {
+    out: &'a mut Vec<u8>,
+    key: Cow<'t, VariantTypeNode<'n>>,
+    value: Cow<'t, VariantTypeNode<'n>>,
+    alignment: usize,
+    entry_size: Option<usize>,
+    start: usize,
+    entry_start: usize,
+    key_end: Option<usize>,
+    offsets: Vec<usize>,
+}
+
+impl<'a, 't, 'n> MapEncoder<'a, 't, 'n> {
+    fn new(encoder: Encoder<'a, 't, 'n>) -> Result<Self, Error> {
+        let ty = encoder.node.type_();
+        if !ty.is_array() || !ty.element().is_dict_entry() {
+            return Err(encoder.unsupported());
+        }
+        let entry = ty.element();
+        let start = encoder.out.len();
+        Ok(Self {
+            out: encoder.out,
+            key: child_type_or_default(encoder.node, 0),
+            value: child_type_or_default(encoder.node, 1),
+            alignment: alignment(entry),
+            entry_size: fixed_size(entry),
+            start,
+            entry_start: start,
+            key_end: None,
+            offsets: Vec::new(),
+        })
+    }
+    fn finish_entry(&mut self) {
+        match self.entry_size {
+            Some(size) => self.out.resize(self.entry_start + size, 0),
+            None => {
+                write_offsets(self.out, self.entry_start, self.key_end.take().into_iter());
+                self.offsets.push(self.out.len() - self.start);
+            }
+        }
+    }
+}
+
+impl<'a, 't, 'n> ser::SerializeMap for MapEncoder<'a, 't, 'n> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize,
+    {
+        pad(self.out, self.alignment);
+        self.entry_start = self.out.len();
+        key.serialize(Encoder::new(self.out, &self.key))?;
+        if fixed_size(self.key.type_()).is_none() {
+            self.key_end = Some(self.out.len() - self.entry_start);
+        }
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize,
+    {
+        pad(self.out, alignment(self.value.type_()));
+        value.serialize(Encoder::new(self.out, &self.value))?;
+        self.finish_entry();
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        write_offsets(self.out, self.start, self.offsets.into_iter());
+        Ok(())
+    }
+}
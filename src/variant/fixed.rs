@@ -0,0 +1,253 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use super::encoder::{alignment, fixed_size};
+use crate::{Error, VariantType, VariantTypeNode};
+use glib::{StaticVariantType, VariantTy};
+use serde::{de, ser, Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+};
+
+pub(crate) const STRUCT_NAME: &str = "glib_serde::$FixedArray";
+
+/// Runs `func`, which serializes `value` as type `ty`, having the [`FixedArray`] it serializes
+/// write its data as bytes.
+pub(crate) fn raw<T: ?Sized, R>(
+    value: &T,
+    ty: &VariantTy,
+    func: impl FnOnce() -> Result<R, Error>,
+) -> Result<R, Error> {
+    super::handoff::request(STRUCT_NAME, value, ty, func).0
+}
+
+/// Marker for types that can be copied to and from GVariant array data as plain memory.
+///
+/// Implemented for the fixed-size numeric types. Structs can implement it with
+/// `#[derive(glib_serde::VariantType)]` and the `#[glib_serde_fixed_size]` attribute, which
+/// requires `#[repr(C)]`, checks that every field implements `FixedArrayElement` and that the
+/// struct has no padding:
+///
+/// ```
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// #[derive(glib_serde::VariantType, serde::Serialize, serde::Deserialize)]
+/// #[glib_serde_fixed_size]
+/// #[repr(C)]
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+///
+/// let points = glib_serde::FixedArray::from(vec![Point { x: 1.0, y: 2.0 }]);
+/// let variant = glib_serde::to_variant(&points).unwrap();
+/// assert_eq!(variant.type_(), "a(dd)");
+/// ```
+///
+/// # Safety
+///
+/// The memory layout of the type must match the GVariant serialization of its
+/// [`static_variant_type`](StaticVariantType::static_variant_type), it must not contain
+/// padding, and every bit pattern must be a valid value.
+pub unsafe trait FixedArrayElement: StaticVariantType + Copy + 'static {}
+
+unsafe impl FixedArrayElement for u8 {}
+unsafe impl FixedArrayElement for i16 {}
+unsafe impl FixedArrayElement for u16 {}
+unsafe impl FixedArrayElement for i32 {}
+unsafe impl FixedArrayElement for u32 {}
+unsafe impl FixedArrayElement for i64 {}
+unsafe impl FixedArrayElement for u64 {}
+unsafe impl FixedArrayElement for f64 {}
+
+/// Checks that `T` has the size and alignment of its variant type on this target.
+fn check_layout<T: FixedArrayElement>() -> Result<(), Error> {
+    let ty = T::static_variant_type();
+    if fixed_size(&ty) == Some(mem::size_of::<T>()) && alignment(&ty) == mem::align_of::<T>() {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedType(ty.into_owned()))
+    }
+}
+
+fn as_bytes<T: FixedArrayElement>(values: &[T]) -> &[u8] {
+    // SAFETY: `T` has no padding, so every byte is initialized
+    unsafe { std::slice::from_raw_parts(values.as_ptr() as *const u8, mem::size_of_val(values)) }
+}
+
+fn from_bytes<T: FixedArrayElement>(data: &[u8]) -> Result<Vec<T>, Error> {
+    check_layout::<T>()?;
+    let size = mem::size_of::<T>();
+    if data.len() % size != 0 {
+        return Err(Error::Custom(format!(
+            "Array data of {} bytes does not hold elements of {} bytes",
+            data.len(),
+            size
+        )));
+    }
+    let len = data.len() / size;
+    let mut values = Vec::<T>::with_capacity(len);
+    // SAFETY: the vector has room for `len` elements and `T` accepts any bit pattern. Copying
+    // bytes means `data` does not need to be aligned for `T`.
+    unsafe {
+        std::ptr::copy_nonoverlapping(data.as_ptr(), values.as_mut_ptr() as *mut u8, data.len());
+        values.set_len(len);
+    }
+    Ok(values)
+}
+
+/// Array of fixed-size values that is serialized as one block of memory.
+///
+/// With [`to_variant`](crate::to_variant), [`from_variant`](crate::from_variant),
+/// [`to_bytes`](crate::to_bytes) and [`from_bytes`](crate::from_bytes), the array data is
+/// copied in one go instead of one element at a time. Other formats serialize a sequence of
+/// `T`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct FixedArray<T>(Vec<T>);
+
+impl<T> FixedArray<T> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for FixedArray<T> {
+    type Target = Vec<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for FixedArray<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for FixedArray<T> {
+    fn from(values: Vec<T>) -> Self {
+        Self(values)
+    }
+}
+
+impl<T> From<FixedArray<T>> for Vec<T> {
+    fn from(array: FixedArray<T>) -> Self {
+        array.0
+    }
+}
+
+impl<T: StaticVariantType> StaticVariantType for FixedArray<T> {
+    fn static_variant_type() -> Cow<'static, VariantTy> {
+        Vec::<T>::static_variant_type()
+    }
+}
+
+impl<T: VariantType> VariantType for FixedArray<T> {
+    const TYPE_STR: Option<&'static str> = Vec::<T>::TYPE_STR;
+    fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
+        Vec::<T>::variant_type()
+    }
+}
+
+impl<T: FixedArrayElement + Serialize> Serialize for FixedArray<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(STRUCT_NAME, &FixedArrayData(&self.0))
+    }
+}
+
+struct FixedArrayData<'a, T>(&'a [T]);
+
+impl<'a, T: FixedArrayElement + Serialize> Serialize for FixedArrayData<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match super::handoff::take_request(STRUCT_NAME, self) {
+            Some(ty) => {
+                let element = T::static_variant_type();
+                let actual = glib::VariantType::new_array(&element);
+                if ty.as_str() != actual.as_str() {
+                    return Err(ser::Error::custom(Error::Mismatch(
+                        glib::variant::VariantTypeMismatchError::new(actual, ty),
+                    )));
+                }
+                check_layout::<T>().map_err(ser::Error::custom)?;
+                serializer.serialize_bytes(as_bytes(self.0))
+            }
+            None => serializer.collect_seq(self.0),
+        }
+    }
+}
+
+impl<'de, T: FixedArrayElement + Deserialize<'de>> Deserialize<'de> for FixedArray<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(STRUCT_NAME, FixedArrayVisitor(PhantomData))
+    }
+}
+
+struct FixedArrayVisitor<T>(PhantomData<T>);
+
+impl<'de, T: FixedArrayElement + Deserialize<'de>> de::Visitor<'de> for FixedArrayVisitor<T> {
+    type Value = FixedArray<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of fixed-size values")
+    }
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        // the type string, then the array data
+        let ty = seq
+            .next_element::<String>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let element = T::static_variant_type();
+        let expected = glib::VariantType::new_array(&element);
+        if ty != expected.as_str() {
+            return Err(de::Error::invalid_value(de::Unexpected::Str(&ty), &self));
+        }
+        let values = seq
+            .next_element_seed(FixedArraySeed(PhantomData))?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(FixedArray(values))
+    }
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        Vec::<T>::deserialize(deserializer).map(FixedArray)
+    }
+}
+
+struct FixedArraySeed<T>(PhantomData<T>);
+
+impl<'de, T: FixedArrayElement> de::DeserializeSeed<'de> for FixedArraySeed<T> {
+    type Value = Vec<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+}
+
+impl<'de, T: FixedArrayElement> de::Visitor<'de> for FixedArraySeed<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("serialized array data")
+    }
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        from_bytes(v).map_err(de::Error::custom)
+    }
+}
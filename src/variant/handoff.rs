@@ -0,0 +1,106 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+//! Passes values between the serializers and deserializers of this crate and the wrappers that
+//! take a faster path through them, [`LazyVariant`](super::LazyVariant) and
+//! [`FixedArray`](super::FixedArray).
+//!
+//! A handoff is keyed by the struct name the wrapper serializes as, and a serializer request also
+//! by the address of the value passed to `serialize_newtype_struct`. A wrapper reached any other
+//! way, like one nested in user code that serializes into another format, never sees it.
+
+use glib::VariantTy;
+use std::cell::RefCell;
+
+enum Handoff {
+    /// Asks the value at `key` to serialize in the form that only this crate reads, as type `ty`.
+    Request {
+        name: &'static str,
+        key: *const (),
+        ty: glib::VariantType,
+    },
+    /// Variant handed back by the value that took a request, or offered by a deserializer.
+    Variant {
+        name: &'static str,
+        variant: glib::Variant,
+    },
+}
+
+thread_local! {
+    static HANDOFF: RefCell<Option<Handoff>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous handoff, also when `func` panics.
+struct HandoffGuard(Option<Handoff>);
+
+impl Drop for HandoffGuard {
+    fn drop(&mut self) {
+        HANDOFF.with(|h| h.replace(self.0.take()));
+    }
+}
+
+fn key<T: ?Sized>(value: &T) -> *const () {
+    value as *const T as *const ()
+}
+
+/// Runs `func`, which serializes `value` as the newtype struct `name` with type `ty`. Returns
+/// its result and the variant that `value` handed back with [`hand_back`], if any.
+pub(crate) fn request<T: ?Sized, R>(
+    name: &'static str,
+    value: &T,
+    ty: &VariantTy,
+    func: impl FnOnce() -> R,
+) -> (R, Option<glib::Variant>) {
+    let request = Handoff::Request {
+        name,
+        key: key(value),
+        ty: ty.to_owned(),
+    };
+    let _guard = HandoffGuard(HANDOFF.with(|h| h.replace(Some(request))));
+    let ret = func();
+    (ret, take_variant(name))
+}
+
+/// Takes the request made for `value`, returning the type it is serialized as.
+pub(crate) fn take_request<T: ?Sized>(name: &'static str, value: &T) -> Option<glib::VariantType> {
+    HANDOFF.with(|h| {
+        let mut h = h.borrow_mut();
+        match h.take() {
+            Some(Handoff::Request {
+                name: n,
+                key: k,
+                ty,
+            }) if n == name && k == key(value) => Some(ty),
+            other => {
+                *h = other;
+                None
+            }
+        }
+    })
+}
+
+/// Hands `variant` back to the serializer that made the request for `name`.
+pub(crate) fn hand_back(name: &'static str, variant: glib::Variant) {
+    HANDOFF.with(|h| h.replace(Some(Handoff::Variant { name, variant })));
+}
+
+/// Runs `func`, which deserializes the newtype struct `name`, offering it `variant`.
+pub(crate) fn offer<R>(name: &'static str, variant: glib::Variant, func: impl FnOnce() -> R) -> R {
+    let offer = Handoff::Variant { name, variant };
+    let _guard = HandoffGuard(HANDOFF.with(|h| h.replace(Some(offer))));
+    func()
+}
+
+/// Takes the variant handed over for `name`.
+pub(crate) fn take_variant(name: &'static str) -> Option<glib::Variant> {
+    HANDOFF.with(|h| {
+        let mut h = h.borrow_mut();
+        match h.take() {
+            Some(Handoff::Variant { name: n, variant }) if n == name => Some(variant),
+            other => {
+                *h = other;
+                None
+            }
+        }
+    })
+}
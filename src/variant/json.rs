@@ -0,0 +1,509 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+//! JSON mapping compatible with `json_gvariant_serialize` and `json_gvariant_deserialize` from
+//! json-glib.
+//!
+//! Dictionaries become objects with stringified keys, maybes become `null` or their inner value,
+//! variants are unboxed, and tuples and other arrays become arrays. Use this module with
+//! `#[serde(with = "glib_serde::json")]`, or use the [`JsonVariant`] wrapper.
+//!
+//! Without a type, values are inferred the same way json-glib does: integers become `x`,
+//! numbers with a fraction become `d`, arrays become `av`, objects become `a{sv}` and `null`
+//! becomes `mv`. Use [`deserialize_typed`] or [`JsonVariantSeed`] to deserialize against a
+//! known type instead.
+
+use super::GlibVariantExt;
+use crate::{Error, ObjectPath, Signature, VariantBuilder, VariantBuilderExt};
+use glib::{StaticVariantType, ToVariant, VariantClass, VariantTy};
+use serde::{
+    de::{self, DeserializeSeed, Visitor},
+    ser::{self, SerializeMap, SerializeSeq, SerializeTuple},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::convert::TryFrom;
+
+/// Serializes a variant using the json-glib mapping.
+pub fn serialize<S>(variant: &glib::Variant, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    JsonSerializeInput(variant).serialize(serializer)
+}
+
+/// Deserializes a variant using the json-glib mapping, inferring the type from the input.
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: From<glib::Variant>,
+{
+    deserializer.deserialize_any(InferVisitor).map(Into::into)
+}
+
+/// Deserializes a variant of type `ty` using the json-glib mapping.
+pub fn deserialize_typed<'de, D>(deserializer: D, ty: &VariantTy) -> Result<glib::Variant, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    JsonVariantSeed(ty).deserialize(deserializer)
+}
+
+/// Wrapper type for [`glib::Variant`](struct@glib::Variant) using the json-glib mapping.
+///
+/// Deserializing infers the type from the input.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct JsonVariant(glib::Variant);
+
+unsafe impl Send for JsonVariant {}
+unsafe impl Sync for JsonVariant {}
+
+impl JsonVariant {
+    pub fn into_inner(self) -> glib::Variant {
+        self.0
+    }
+}
+
+impl std::ops::Deref for JsonVariant {
+    type Target = glib::Variant;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<glib::Variant> for JsonVariant {
+    fn from(variant: glib::Variant) -> Self {
+        Self(variant)
+    }
+}
+
+impl From<JsonVariant> for glib::Variant {
+    fn from(variant: JsonVariant) -> Self {
+        variant.0
+    }
+}
+
+impl Serialize for JsonVariant {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonVariant {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(deserializer)
+    }
+}
+
+/// Seed for deserializing a variant of a known type using the json-glib mapping.
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct JsonVariantSeed<'t>(pub &'t VariantTy);
+
+impl<'t, 'de> DeserializeSeed<'de> for JsonVariantSeed<'t> {
+    type Value = glib::Variant;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let ty = self.0;
+        let visitor = TypedVisitor(ty);
+        if !ty.is_definite() {
+            return Err(de::Error::custom(Error::UnsupportedType(ty.to_owned())));
+        }
+        match ty.as_str() {
+            "b" => deserializer.deserialize_bool(visitor),
+            "y" | "n" | "q" | "i" | "u" | "x" | "t" | "d" => deserializer.deserialize_any(visitor),
+            "h" => Err(de::Error::custom("HANDLE values not supported")),
+            "s" | "o" | "g" => deserializer.deserialize_str(visitor),
+            "v" => deserializer
+                .deserialize_any(InferVisitor)
+                .map(|v| glib::Variant::from_variant(&v)),
+            _ if ty.is_maybe() => deserializer.deserialize_option(visitor),
+            _ if ty.is_array() && ty.element().is_dict_entry() => {
+                deserializer.deserialize_map(visitor)
+            }
+            _ if ty.is_dict_entry() => deserializer.deserialize_map(visitor),
+            _ => deserializer.deserialize_seq(visitor),
+        }
+    }
+}
+
+#[repr(transparent)]
+struct JsonSerializeInput<'t>(&'t glib::Variant);
+
+impl<'t> Serialize for JsonSerializeInput<'t> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[inline]
+        fn try_serialize<T, S>(v: &glib::Variant, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            T: glib::FromVariant + Serialize,
+            S: Serializer,
+        {
+            v.try_get::<T>()
+                .map_err(ser::Error::custom)?
+                .serialize(serializer)
+        }
+
+        let v = self.0;
+        match v.classify() {
+            VariantClass::Boolean => try_serialize::<bool, _>(v, serializer),
+            VariantClass::Byte => try_serialize::<u8, _>(v, serializer),
+            VariantClass::Int16 => try_serialize::<i16, _>(v, serializer),
+            VariantClass::Uint16 => try_serialize::<u16, _>(v, serializer),
+            VariantClass::Int32 => try_serialize::<i32, _>(v, serializer),
+            VariantClass::Uint32 => try_serialize::<u32, _>(v, serializer),
+            VariantClass::Int64 => try_serialize::<i64, _>(v, serializer),
+            VariantClass::Uint64 => try_serialize::<u64, _>(v, serializer),
+            VariantClass::Handle => Err(ser::Error::custom("HANDLE values not supported")),
+            VariantClass::Double => try_serialize::<f64, _>(v, serializer),
+            VariantClass::String | VariantClass::ObjectPath | VariantClass::Signature => {
+                serializer.serialize_str(v.str().unwrap())
+            }
+            VariantClass::Variant => {
+                JsonSerializeInput(&v.as_variant().unwrap()).serialize(serializer)
+            }
+            VariantClass::Maybe => match v.maybe().unwrap() {
+                Some(inner) => serializer.serialize_some(&JsonSerializeInput(&inner)),
+                None => serializer.serialize_none(),
+            },
+            VariantClass::Array if v.type_().element().is_dict_entry() => {
+                let count = v.n_children();
+                let mut map = serializer.serialize_map(Some(count))?;
+                for i in 0..count {
+                    let entry = v.child_value(i);
+                    let key = key_to_string(&entry.child_value(0)).map_err(ser::Error::custom)?;
+                    map.serialize_entry(&key, &JsonSerializeInput(&entry.child_value(1)))?;
+                }
+                map.end()
+            }
+            VariantClass::Array => {
+                let count = v.n_children();
+                let mut seq = serializer.serialize_seq(Some(count))?;
+                for i in 0..count {
+                    seq.serialize_element(&JsonSerializeInput(&v.child_value(i)))?;
+                }
+                seq.end()
+            }
+            VariantClass::Tuple => {
+                let count = v.n_children();
+                let mut seq = serializer.serialize_tuple(count)?;
+                for i in 0..count {
+                    seq.serialize_element(&JsonSerializeInput(&v.child_value(i)))?;
+                }
+                seq.end()
+            }
+            VariantClass::DictEntry => {
+                let key = key_to_string(&v.child_value(0)).map_err(ser::Error::custom)?;
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&key, &JsonSerializeInput(&v.child_value(1)))?;
+                map.end()
+            }
+            _ => Err(ser::Error::custom(Error::UnsupportedType(
+                v.type_().to_owned(),
+            ))),
+        }
+    }
+}
+
+fn key_to_string(key: &glib::Variant) -> Result<String, Error> {
+    Ok(match key.classify() {
+        VariantClass::String | VariantClass::ObjectPath | VariantClass::Signature => {
+            key.str().unwrap().to_owned()
+        }
+        VariantClass::Boolean => key.get::<bool>().unwrap().to_string(),
+        VariantClass::Byte => key.get::<u8>().unwrap().to_string(),
+        VariantClass::Int16 => key.get::<i16>().unwrap().to_string(),
+        VariantClass::Uint16 => key.get::<u16>().unwrap().to_string(),
+        VariantClass::Int32 => key.get::<i32>().unwrap().to_string(),
+        VariantClass::Uint32 => key.get::<u32>().unwrap().to_string(),
+        VariantClass::Int64 => key.get::<i64>().unwrap().to_string(),
+        VariantClass::Uint64 => key.get::<u64>().unwrap().to_string(),
+        VariantClass::Double => key.get::<f64>().unwrap().to_string(),
+        _ => return Err(Error::UnsupportedType(key.type_().to_owned())),
+    })
+}
+
+fn key_from_str<E: de::Error>(ty: &VariantTy, s: &str) -> Result<glib::Variant, E> {
+    fn parse<T: std::str::FromStr + ToVariant, E: de::Error>(
+        s: &str,
+        ty: &VariantTy,
+    ) -> Result<glib::Variant, E> {
+        s.parse::<T>().map(|v| v.to_variant()).map_err(|_| {
+            de::Error::invalid_value(
+                de::Unexpected::Str(s),
+                &format!("dictionary key of type `{}`", ty).as_str(),
+            )
+        })
+    }
+
+    match ty.as_str() {
+        "s" => Ok(s.to_variant()),
+        "o" => ObjectPath::new(s)
+            .map(|o| o.to_variant())
+            .map_err(de::Error::custom),
+        "g" => Signature::new(s)
+            .map(|g| g.to_variant())
+            .map_err(de::Error::custom),
+        "b" => parse::<bool, E>(s, ty),
+        "y" => parse::<u8, E>(s, ty),
+        "n" => parse::<i16, E>(s, ty),
+        "q" => parse::<u16, E>(s, ty),
+        "i" => parse::<i32, E>(s, ty),
+        "u" => parse::<u32, E>(s, ty),
+        "x" => parse::<i64, E>(s, ty),
+        "t" => parse::<u64, E>(s, ty),
+        "d" => parse::<f64, E>(s, ty),
+        _ => Err(de::Error::custom(Error::UnsupportedType(ty.to_owned()))),
+    }
+}
+
+#[repr(transparent)]
+struct TypedVisitor<'t>(&'t VariantTy);
+
+impl<'t> TypedVisitor<'t> {
+    fn integer<E: de::Error>(
+        self,
+        v: i128,
+        unexpected: de::Unexpected,
+    ) -> Result<glib::Variant, E> {
+        fn convert<T, E>(v: i128, unexpected: de::Unexpected, ty: &VariantTy) -> Result<T, E>
+        where
+            T: TryFrom<i128>,
+            E: de::Error,
+        {
+            T::try_from(v).map_err(|_| {
+                de::Error::invalid_value(unexpected, &format!("integer of type `{}`", ty).as_str())
+            })
+        }
+
+        let ty = self.0;
+        Ok(match ty.as_str() {
+            "y" => convert::<u8, E>(v, unexpected, ty)?.to_variant(),
+            "n" => convert::<i16, E>(v, unexpected, ty)?.to_variant(),
+            "q" => convert::<u16, E>(v, unexpected, ty)?.to_variant(),
+            "i" => convert::<i32, E>(v, unexpected, ty)?.to_variant(),
+            "u" => convert::<u32, E>(v, unexpected, ty)?.to_variant(),
+            "x" => convert::<i64, E>(v, unexpected, ty)?.to_variant(),
+            "t" => convert::<u64, E>(v, unexpected, ty)?.to_variant(),
+            "d" => (v as f64).to_variant(),
+            _ => return Err(de::Error::invalid_type(unexpected, &self)),
+        })
+    }
+}
+
+impl<'t, 'de> Visitor<'de> for TypedVisitor<'t> {
+    type Value = glib::Variant;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "JSON value for GVariant type `{}`", self.0)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        match self.0.as_str() {
+            "b" => Ok(v.to_variant()),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Bool(v), &self)),
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        self.integer(v.into(), de::Unexpected::Signed(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.integer(v.into(), de::Unexpected::Unsigned(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        match self.0.as_str() {
+            "d" => Ok(v.to_variant()),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Float(v), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        match self.0.as_str() {
+            "s" | "o" | "g" => key_from_str(self.0, v),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Str(v), &self)),
+        }
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        if self.0.is_maybe() {
+            Ok(glib::Variant::from_none(self.0.element()))
+        } else {
+            Err(de::Error::invalid_type(de::Unexpected::Option, &self))
+        }
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.visit_none()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !self.0.is_maybe() {
+            return Err(de::Error::invalid_type(de::Unexpected::Option, &self));
+        }
+        let value = JsonVariantSeed(self.0.element()).deserialize(deserializer)?;
+        Ok(glib::Variant::from_some(&value))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let ty = self.0;
+        if ty.is_array() {
+            let mut builder = VariantBuilder::new(ty);
+            while let Some(value) = seq.next_element_seed(JsonVariantSeed(ty.element()))? {
+                builder.add_value(&value).map_err(de::Error::custom)?;
+            }
+            builder.end().map_err(de::Error::custom)
+        } else if ty.is_tuple() {
+            let mut builder = VariantBuilder::new(ty);
+            let len = ty.n_items();
+            let expected = format!("array of length {}", len);
+            let mut iter = ty.first();
+            for i in 0..len {
+                let elem = iter.unwrap();
+                let value = seq
+                    .next_element_seed(JsonVariantSeed(elem))?
+                    .ok_or_else(|| de::Error::invalid_length(i, &expected.as_str()))?;
+                builder.add_value(&value).map_err(de::Error::custom)?;
+                iter = elem.next();
+            }
+            if seq.next_element::<de::IgnoredAny>()?.is_some() {
+                return Err(de::Error::invalid_length(len + 1, &expected.as_str()));
+            }
+            builder.end().map_err(de::Error::custom)
+        } else {
+            Err(de::Error::invalid_type(de::Unexpected::Seq, &self))
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let ty = self.0;
+        if ty.is_dict_entry() {
+            let key = map
+                .next_key::<String>()?
+                .ok_or_else(|| de::Error::invalid_length(0, &"object with one member"))?;
+            let key = key_from_str(ty.key(), &key)?;
+            let value = map.next_value_seed(JsonVariantSeed(ty.value()))?;
+            if map.next_key::<de::IgnoredAny>()?.is_some() {
+                return Err(de::Error::invalid_length(2, &"object with one member"));
+            }
+            return Ok(glib::Variant::from_dict_entry(&key, &value));
+        }
+        if !ty.is_array() || !ty.element().is_dict_entry() {
+            return Err(de::Error::invalid_type(de::Unexpected::Map, &self));
+        }
+        let mut builder = VariantBuilder::new(ty);
+        let elem = ty.element();
+        while let Some(key) = map.next_key::<String>()? {
+            let key = key_from_str(elem.key(), &key)?;
+            let value = map.next_value_seed(JsonVariantSeed(elem.value()))?;
+            let mut dict_entry = builder.open(elem).map_err(de::Error::custom)?;
+            dict_entry.add_value(&key).map_err(de::Error::custom)?;
+            dict_entry.add_value(&value).map_err(de::Error::custom)?;
+        }
+        builder.end().map_err(de::Error::custom)
+    }
+}
+
+struct InferVisitor;
+
+impl<'de> Visitor<'de> for InferVisitor {
+    type Value = glib::Variant;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(v.to_variant())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(v.to_variant())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(match i64::try_from(v) {
+            Ok(v) => v.to_variant(),
+            Err(_) => v.to_variant(),
+        })
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(v.to_variant())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.to_variant())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(glib::Variant::from_none(VariantTy::VARIANT))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.visit_none()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = deserializer.deserialize_any(InferVisitor)?;
+        Ok(glib::Variant::from_some(&glib::Variant::from_variant(
+            &value,
+        )))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut builder = VariantBuilder::new(&Vec::<glib::Variant>::static_variant_type());
+        while let Some(value) = seq.next_element::<JsonVariant>()? {
+            builder
+                .add_value(&glib::Variant::from_variant(&value))
+                .map_err(de::Error::custom)?;
+        }
+        builder.end().map_err(de::Error::custom)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let ty = glib::VariantDict::static_variant_type();
+        let mut builder = VariantBuilder::new(&ty);
+        while let Some((key, value)) = map.next_entry::<String, JsonVariant>()? {
+            let mut dict_entry = builder.open(ty.element()).map_err(de::Error::custom)?;
+            dict_entry
+                .add_value(&key.to_variant())
+                .map_err(de::Error::custom)?;
+            dict_entry
+                .add_value(&glib::Variant::from_variant(&value))
+                .map_err(de::Error::custom)?;
+        }
+        builder.end().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,177 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, VariantType, VariantTypeNode};
+use glib::once_cell::unsync::OnceCell;
+use serde::{de, ser, Deserialize, Serialize};
+use std::{borrow::Cow, marker::PhantomData};
+
+pub(crate) const STRUCT_NAME: &str = "glib_serde::$LazyVariant";
+
+/// Runs `func`, which serializes `value` with type `ty`, returning the variant written by the
+/// [`LazyVariant`] it serializes.
+pub(crate) fn capture<T: ?Sized, R>(
+    value: &T,
+    ty: &glib::VariantTy,
+    func: impl FnOnce() -> Result<R, Error>,
+) -> Result<glib::Variant, Error> {
+    let (ret, variant) = super::handoff::request(STRUCT_NAME, value, ty, func);
+    ret?;
+    variant.ok_or_else(|| Error::Custom(String::from("LazyVariant did not write a variant")))
+}
+
+/// Field type that defers deserializing `T` until it is first accessed.
+///
+/// When deserialized with [`from_variant`](crate::from_variant), holds onto the child
+/// [`glib::Variant`](struct@glib::Variant) and decodes it on the first call to
+/// [`get`](Self::get), caching the result. Serializing with [`to_variant`](crate::to_variant)
+/// writes back the original variant if the value was never mutably borrowed. Other formats
+/// serialize and deserialize `T` directly.
+#[derive(Clone, Debug)]
+pub struct LazyVariant<T> {
+    variant: Option<glib::Variant>,
+    value: OnceCell<T>,
+}
+
+impl<T> LazyVariant<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            variant: None,
+            value: OnceCell::from(value),
+        }
+    }
+    pub fn from_variant(variant: glib::Variant) -> Self {
+        Self {
+            variant: Some(variant),
+            value: OnceCell::new(),
+        }
+    }
+    /// Returns the original variant, or `None` if the value was created or modified in Rust.
+    pub fn variant(&self) -> Option<&glib::Variant> {
+        self.variant.as_ref()
+    }
+    /// Returns `true` if the value has been decoded.
+    pub fn is_decoded(&self) -> bool {
+        self.value.get().is_some()
+    }
+    /// Replaces the value, discarding the original variant.
+    pub fn set(&mut self, value: T) {
+        self.variant = None;
+        self.value = OnceCell::from(value);
+    }
+}
+
+impl<T: de::DeserializeOwned> LazyVariant<T> {
+    /// Decodes the value on first access and returns a reference to it.
+    pub fn get(&self) -> Result<&T, Error> {
+        self.value
+            .get_or_try_init(|| crate::from_variant(self.variant.as_ref().unwrap()))
+    }
+    /// Decodes the value on first access and returns a mutable reference to it. The original
+    /// variant is discarded, so the value is re-encoded when serialized.
+    pub fn get_mut(&mut self) -> Result<&mut T, Error> {
+        self.get()?;
+        self.variant = None;
+        Ok(self.value.get_mut().unwrap())
+    }
+    pub fn into_inner(self) -> Result<T, Error> {
+        self.get()?;
+        Ok(self.value.into_inner().unwrap())
+    }
+}
+
+impl<T> From<glib::Variant> for LazyVariant<T> {
+    fn from(variant: glib::Variant) -> Self {
+        Self::from_variant(variant)
+    }
+}
+
+impl<T: glib::StaticVariantType> glib::StaticVariantType for LazyVariant<T> {
+    fn static_variant_type() -> Cow<'static, glib::VariantTy> {
+        T::static_variant_type()
+    }
+}
+
+impl<T: VariantType> VariantType for LazyVariant<T> {
+    const TYPE_STR: Option<&'static str> = T::TYPE_STR;
+    const VALIDATES_VALUES: bool = T::VALIDATES_VALUES;
+    fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
+        T::variant_type()
+    }
+    fn validate_value(variant: &glib::Variant) -> Result<(), Error> {
+        T::validate_value(variant)
+    }
+}
+
+impl<T> Serialize for LazyVariant<T>
+where
+    T: Serialize + de::DeserializeOwned + VariantType,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(STRUCT_NAME, &LazyValue(self))
+    }
+}
+
+struct LazyValue<'a, T>(&'a LazyVariant<T>);
+
+impl<'a, T> Serialize for LazyValue<'a, T>
+where
+    T: Serialize + de::DeserializeOwned + VariantType,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        if super::handoff::take_request(STRUCT_NAME, self).is_some() {
+            let variant = match &self.0.variant {
+                Some(variant) => variant.clone(),
+                None => {
+                    crate::to_variant(self.0.value.get().unwrap()).map_err(ser::Error::custom)?
+                }
+            };
+            super::handoff::hand_back(STRUCT_NAME, variant);
+            serializer.serialize_unit()
+        } else {
+            self.0
+                .get()
+                .map_err(ser::Error::custom)?
+                .serialize(serializer)
+        }
+    }
+}
+
+impl<'de, T: de::DeserializeOwned> Deserialize<'de> for LazyVariant<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct LazyVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: de::DeserializeOwned> de::Visitor<'de> for LazyVisitor<T> {
+            type Value = LazyVariant<T>;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a lazily decoded value")
+            }
+            fn visit_unit<E>(self) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                super::handoff::take_variant(STRUCT_NAME)
+                    .map(LazyVariant::from_variant)
+                    .ok_or_else(|| de::Error::invalid_type(de::Unexpected::Unit, &self))
+            }
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                T::deserialize(deserializer).map(LazyVariant::new)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(STRUCT_NAME, LazyVisitor(PhantomData))
+    }
+}
@@ -1,20 +1,40 @@
 // SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
 // SPDX-License-Identifier: MIT
 
-use crate::{VariantBuilder, VariantBuilderExt, VariantType};
+use crate::{Error, VariantBuilder, VariantBuilderExt, VariantType};
 use glib::{translate::*, variant::VariantTypeMismatchError, VariantTy};
 use std::{borrow::Cow, ops::Deref};
 
+pub(crate) mod cast;
+pub use cast::*;
+pub(crate) mod decoder;
+pub use decoder::*;
 pub(crate) mod deserialize;
 pub(crate) mod deserializer;
 pub use deserializer::*;
+pub(crate) mod encoder;
+pub use encoder::*;
+pub(crate) mod fixed;
+pub use fixed::*;
+pub(crate) mod handoff;
+pub mod json;
+pub(crate) mod lazy;
+pub use lazy::*;
+pub(crate) mod seq;
 pub(crate) mod serialize;
+pub use seq::*;
 pub(crate) mod serializer;
 pub use serializer::*;
+pub(crate) mod update;
+pub use update::*;
 
 const STRUCT_NAME: &str = "glib_serde::$Variant";
 
 /// Wrapper type for [`glib::Variant`](struct@glib::Variant).
+///
+/// In human-readable formats, serializes as a string in GVariant text format with type
+/// annotations, e.g. `"<(1, 'x')>"`. Other formats receive a `(type, bytes)` tuple containing
+/// the type string and the serialized data in normal form.
 #[derive(Clone, Debug, Hash, Eq, PartialEq, PartialOrd)]
 #[repr(transparent)]
 pub struct Variant(glib::Variant);
@@ -67,7 +87,9 @@ impl Deref for Variant {
     }
 }
 
-impl VariantType for Variant {}
+impl VariantType for Variant {
+    const TYPE_STR: Option<&'static str> = Some("v");
+}
 
 impl From<glib::Variant> for Variant {
     fn from(other: glib::Variant) -> Self {
@@ -81,6 +103,76 @@ impl From<Variant> for glib::Variant {
     }
 }
 
+/// Constructs a variant of type `ty` from untrusted serialized data. Fails if the type is not
+/// definite or the data is not in normal form.
+pub(crate) fn variant_from_data(ty: &VariantTy, data: glib::Bytes) -> Result<glib::Variant, Error> {
+    if !ty.is_definite() {
+        return Err(Error::UnsupportedType(ty.to_owned()));
+    }
+    let variant = glib::Variant::from_bytes_with_type(&data, ty);
+    if !variant.is_normal_form() {
+        return Err(Error::NonNormalData(ty.to_owned()));
+    }
+    Ok(variant)
+}
+
+/// Checks the type of `variant` as a whole, returning an error that locates the first child type
+/// that differs from `expected`.
+pub(crate) fn check_type(variant: &glib::Variant, expected: &VariantTy) -> Result<(), Error> {
+    if variant.is_of_type(expected).is_ok() {
+        return Ok(());
+    }
+    let actual = variant.type_();
+    let mut path = Vec::new();
+    type_divergence(actual, expected, &mut path);
+    Err(Error::TypeMismatch {
+        actual: actual.to_owned(),
+        expected: expected.to_owned(),
+        path,
+    })
+}
+
+pub(crate) fn is_subtype_of(ty: &VariantTy, supertype: &VariantTy) -> bool {
+    unsafe {
+        from_glib(glib::ffi::g_variant_type_is_subtype_of(
+            ty.to_glib_none().0,
+            supertype.to_glib_none().0,
+        ))
+    }
+}
+
+/// Pushes the child indices leading to the first mismatch onto `path`. Returns `false` if `actual`
+/// is a subtype of `expected`.
+fn type_divergence(actual: &VariantTy, expected: &VariantTy, path: &mut Vec<usize>) -> bool {
+    if is_subtype_of(actual, expected) {
+        return false;
+    }
+    if (actual.is_array() && expected.is_array()) || (actual.is_maybe() && expected.is_maybe()) {
+        path.push(0);
+        if type_divergence(actual.element(), expected.element(), path) {
+            return true;
+        }
+        path.pop();
+    } else if (actual.is_tuple() && expected.is_tuple() && expected != VariantTy::TUPLE)
+        || (actual.is_dict_entry() && expected.is_dict_entry())
+    {
+        let (mut a, mut e) = (actual.first(), expected.first());
+        let mut index = 0;
+        while let (Some(at), Some(et)) = (a, e) {
+            path.push(index);
+            if type_divergence(at, et, path) {
+                return true;
+            }
+            path.pop();
+            a = at.next();
+            e = et.next();
+            index += 1;
+        }
+        path.push(index);
+    }
+    true
+}
+
 /// Extra methods for [`glib::Variant`](struct@glib::Variant).
 pub trait GlibVariantExt {
     fn parse(type_: Option<&VariantTy>, s: &str) -> Result<Option<glib::Variant>, glib::Error>;
@@ -93,6 +185,9 @@ pub trait GlibVariantExt {
     ) -> glib::Variant;
     fn is_of_type(&self, ty: &VariantTy) -> Result<(), VariantTypeMismatchError>;
     fn maybe(&self) -> Option<Option<glib::Variant>>;
+    fn from_handle(handle: i32) -> glib::Variant;
+    fn handle(&self) -> Option<i32>;
+    fn lookup_value(&self, key: &str, type_: Option<&VariantTy>) -> Option<glib::Variant>;
     fn as_serializable(&self) -> &Variant;
 }
 
@@ -147,19 +242,13 @@ impl GlibVariantExt for glib::Variant {
         children: impl IntoIterator<Item = glib::Variant>,
     ) -> glib::Variant {
         assert!(ty.is_array());
-        let builder = VariantBuilder::new(ty);
+        let mut builder = VariantBuilder::new(ty);
         for value in children {
-            assert!(
-                value.is_of_type(ty.element()).is_ok(),
-                "Type mismatch: Expected `{}` got `{}`",
-                ty.element(),
-                value.type_(),
-            );
-            unsafe {
-                builder.add_value(&value);
+            if let Err(e) = builder.add_value(&value) {
+                panic!("{}", e);
             }
         }
-        builder.end()
+        builder.end().unwrap_or_else(|e| panic!("{}", e))
     }
     fn is_of_type(&self, ty: &VariantTy) -> Result<(), VariantTypeMismatchError> {
         let is_type: bool = unsafe {
@@ -188,6 +277,22 @@ impl GlibVariantExt for glib::Variant {
             }
         })
     }
+    fn from_handle(handle: i32) -> glib::Variant {
+        unsafe { from_glib_none(glib::ffi::g_variant_new_handle(handle)) }
+    }
+    fn handle(&self) -> Option<i32> {
+        self.is_of_type(VariantTy::HANDLE).ok()?;
+        Some(unsafe { glib::ffi::g_variant_get_handle(self.to_glib_none().0) })
+    }
+    fn lookup_value(&self, key: &str, type_: Option<&VariantTy>) -> Option<glib::Variant> {
+        unsafe {
+            from_glib_full(glib::ffi::g_variant_lookup_value(
+                self.to_glib_none().0,
+                key.to_glib_none().0,
+                type_.to_glib_none().0,
+            ))
+        }
+    }
     fn as_serializable(&self) -> &Variant {
         unsafe { &*(self as *const glib::Variant as *const Variant) }
     }
@@ -0,0 +1,198 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use super::GlibVariantExt;
+use crate::{Error, VariantType};
+use glib::{ToVariant, VariantTy};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+/// Returns a [`VariantSeq`] over the children of the array `variant`.
+pub fn iter_variant<T>(variant: &glib::Variant) -> Result<VariantSeq<T>, Error>
+where
+    T: DeserializeOwned + VariantType,
+{
+    VariantSeq::new(variant.clone())
+}
+
+/// Typed view of an array variant that decodes each child on demand.
+///
+/// Iterating yields one `Result<T, Error>` per child, fetched with
+/// [`child_value`](glib::Variant::child_value) so only the current child is decoded.
+/// [`get`](Self::get) and [`len`](Self::len) always refer to the whole array; the number of
+/// children left to iterate is the [`size_hint`](Iterator::size_hint).
+#[derive(Clone, Debug)]
+pub struct VariantSeq<T> {
+    variant: glib::Variant,
+    start: usize,
+    end: usize,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> VariantSeq<T>
+where
+    T: DeserializeOwned + VariantType,
+{
+    /// Fails if `variant` is not an array of `T`.
+    pub fn new(variant: glib::Variant) -> Result<Self, Error> {
+        super::check_type(&variant, <[T]>::variant_type().type_())?;
+        let end = variant.n_children();
+        Ok(Self {
+            variant,
+            start: 0,
+            end,
+            phantom: PhantomData,
+        })
+    }
+    pub fn variant(&self) -> &glib::Variant {
+        &self.variant
+    }
+    /// Returns the number of children in the array, however many have been iterated.
+    pub fn len(&self) -> usize {
+        self.variant.n_children()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Decodes the child at `index` in the array, whether or not it has been iterated.
+    pub fn get(&self, index: usize) -> Option<Result<T, Error>> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(crate::from_variant(&self.variant.child_value(index)))
+    }
+}
+
+impl<T> Iterator for VariantSeq<T>
+where
+    T: DeserializeOwned + VariantType,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        let item = crate::from_variant(&self.variant.child_value(self.start));
+        self.start += 1;
+        Some(item)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.start = self.start.saturating_add(n).min(self.end);
+        self.next()
+    }
+}
+
+impl<T> DoubleEndedIterator for VariantSeq<T>
+where
+    T: DeserializeOwned + VariantType,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(crate::from_variant(&self.variant.child_value(self.end)))
+    }
+}
+
+impl<T> ExactSizeIterator for VariantSeq<T> where T: DeserializeOwned + VariantType {}
+
+/// Typed view of an `a{s*}` or `a{o*}` dictionary that decodes each value on demand.
+///
+/// Lookups go through [`lookup_value`](glib::Variant::lookup_value). Values boxed in `v` are
+/// unboxed unless `V` is itself a variant type.
+/// [`len`](Self::len) counts the whole dictionary; the number of entries left to iterate is the
+/// [`size_hint`](Iterator::size_hint).
+#[derive(Clone, Debug)]
+pub struct VariantMap<V> {
+    variant: glib::Variant,
+    start: usize,
+    end: usize,
+    phantom: PhantomData<fn() -> V>,
+}
+
+impl<V> VariantMap<V>
+where
+    V: DeserializeOwned + VariantType,
+{
+    /// Fails if `variant` is not a dictionary with string or object path keys and values of
+    /// type `V` or `v`.
+    pub fn new(variant: glib::Variant) -> Result<Self, Error> {
+        let value_node = V::variant_type();
+        let ty = variant.type_();
+        let valid = ty.is_array()
+            && ty.element().is_dict_entry()
+            && matches!(ty.element().key().as_str(), "s" | "o")
+            && (ty.element().value() == VariantTy::VARIANT
+                || super::is_subtype_of(ty.element().value(), value_node.type_()));
+        if !valid {
+            let expected = format!("a{{s{}}}", value_node.type_().as_str());
+            super::check_type(&variant, &glib::VariantType::new(&expected)?)?;
+        }
+        let end = variant.n_children();
+        Ok(Self {
+            variant,
+            start: 0,
+            end,
+            phantom: PhantomData,
+        })
+    }
+    pub fn variant(&self) -> &glib::Variant {
+        &self.variant
+    }
+    fn decode(&self, value: glib::Variant) -> Result<V, Error> {
+        let boxed = self.variant.type_().element().value() == VariantTy::VARIANT;
+        if boxed && V::variant_type().type_().as_str() == "v" {
+            crate::from_variant(&value.to_variant())
+        } else {
+            crate::from_variant(&value)
+        }
+    }
+    /// Looks up `key` and decodes its value.
+    pub fn get(&self, key: &str) -> Option<Result<V, Error>> {
+        let value = self.variant.lookup_value(key, None)?;
+        Some(self.decode(value))
+    }
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.variant.lookup_value(key, None).is_some()
+    }
+    /// Returns the number of entries in the dictionary, however many have been iterated.
+    pub fn len(&self) -> usize {
+        self.variant.n_children()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<V> Iterator for VariantMap<V>
+where
+    V: DeserializeOwned + VariantType,
+{
+    type Item = Result<(String, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        let entry = self.variant.child_value(self.start);
+        self.start += 1;
+        let key = entry.child_value(0).str().unwrap().to_owned();
+        let mut value = entry.child_value(1);
+        if let Some(inner) = value.as_variant() {
+            value = inner;
+        }
+        Some(self.decode(value).map(|value| (key, value)))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl<V> ExactSizeIterator for VariantMap<V> where V: DeserializeOwned + VariantType {}
@@ -2,7 +2,12 @@
 // SPDX-License-Identifier: MIT
 
 use super::GlibVariantExt;
-use crate::{object_path, signature, Error, ObjectPath, Signature, VariantType, VariantTypeNode};
+use crate::{
+    object_path, signature,
+    variant_builder::{descend, private::Sealed},
+    Error, ObjectPath, Signature, VariantBuilder, VariantBuilderContainer, VariantBuilderExt,
+    VariantType, VariantTypeNode,
+};
 use glib::{ToVariant, VariantTy};
 use serde::{
     ser::{self, SerializeTuple},
@@ -16,16 +21,44 @@ where
     T: Serialize + VariantType,
 {
     let ty = T::variant_type();
-    let serializer = Serializer::new(&ty);
-    value.serialize(serializer)
+    serialize_with_node(value, &ty)
+}
+
+pub(super) fn serialize_with_node<T>(
+    value: &T,
+    node: &VariantTypeNode,
+) -> Result<glib::Variant, Error>
+where
+    T: ?Sized + Serialize,
+{
+    Ok(value
+        .serialize(Serializer::new(node))?
+        .expect("Serializer without a builder did not return a variant"))
+}
+
+/// Builder that a [`Serializer`] writes its value into.
+type Sink<'b> = &'b mut (dyn Sealed + 'b);
+
+/// Adds `variant` to `sink`, or returns it if there is no sink.
+fn emit(sink: Option<Sink>, variant: glib::Variant) -> Result<Option<glib::Variant>, Error> {
+    match sink {
+        Some(sink) => {
+            sink.add_value(&variant)?;
+            Ok(None)
+        }
+        None => Ok(Some(variant)),
+    }
 }
 
-#[repr(transparent)]
-struct Serializer<'t, 'n> {
+/// Serializes into the variant returned from `to_variant`, or into the parent builder when
+/// nested inside a container, so that children are never collected separately.
+struct Serializer<'t, 'n, 'b> {
     node: &'t VariantTypeNode<'n>,
+    human_readable: bool,
+    sink: Option<Sink<'b>>,
 }
 
-fn child_type_or_default<'t, 'n>(
+pub(super) fn child_type_or_default<'t, 'n>(
     node: &'t VariantTypeNode<'n>,
     index: usize,
 ) -> Cow<'t, VariantTypeNode<'n>> {
@@ -49,6 +82,14 @@ fn child_type_or_default<'t, 'n>(
                 } else {
                     None
                 }
+            } else if ty.is_dict_entry() {
+                if index == 0 {
+                    Some(ty.key())
+                } else if index == 1 {
+                    Some(ty.value())
+                } else {
+                    None
+                }
             } else if ty.is_tuple() {
                 let mut i = 0;
                 let mut iter = ty.first();
@@ -68,53 +109,71 @@ fn child_type_or_default<'t, 'n>(
         .unwrap_or_else(|| Cow::Owned(VariantTypeNode::new(Cow::Borrowed(VariantTy::ANY), [])))
 }
 
-impl<'t, 'n> Serializer<'t, 'n> {
+impl<'t, 'n, 'b> Serializer<'t, 'n, 'b> {
     fn new(node: &'t VariantTypeNode<'n>) -> Self {
-        Self { node }
+        Self {
+            node,
+            human_readable: true,
+            sink: None,
+        }
     }
-    fn variant_tag(
-        &self,
-        variant_index: u32,
-        variant: &'static str,
-    ) -> Result<(VariantTag, Option<Cow<'t, VariantTypeNode<'n>>>), Error> {
-        let ty = self.node.type_();
-        let (tag_ty, value_ty) = if ty.is_tuple() {
-            let tag_ty = ty
-                .first()
-                .ok_or_else(|| Error::UnsupportedType(ty.deref().to_owned()))?;
-            let value_node = child_type_or_default(self.node, variant_index as usize);
-            (tag_ty, Some(value_node))
-        } else {
-            (ty.deref(), None)
-        };
-        let tag = match tag_ty.as_str() {
-            "s" => VariantTag::Str(variant.to_owned()),
-            "n" => VariantTag::I16(variant_index.try_into()?),
-            "i" => VariantTag::I32(variant_index.try_into()?),
-            "x" => VariantTag::I64(variant_index as i64),
-            "y" => VariantTag::U8(variant_index.try_into()?),
-            "q" => VariantTag::U16(variant_index.try_into()?),
-            "u" => VariantTag::U32(variant_index),
-            "t" => VariantTag::U64(variant_index as u64),
-            _ => return Err(Error::InvalidTag(tag_ty.to_owned())),
-        };
-        Ok((tag, value_ty))
+    fn with_sink(node: &'t VariantTypeNode<'n>, sink: Sink<'b>) -> Self {
+        Self {
+            node,
+            human_readable: true,
+            sink: Some(sink),
+        }
     }
+    #[inline]
+    fn emit(self, variant: glib::Variant) -> Result<Option<glib::Variant>, Error> {
+        emit(self.sink, variant)
+    }
+}
+
+/// Returns the tag for an enum variant, and the type of its value if `node` is a `(tag, value)`
+/// tuple.
+pub(super) fn variant_tag<'t, 'n>(
+    node: &'t VariantTypeNode<'n>,
+    variant_index: u32,
+    variant: &'static str,
+) -> Result<(VariantTag, Option<Cow<'t, VariantTypeNode<'n>>>), Error> {
+    let ty = node.type_();
+    let (tag_ty, value_ty) = if ty.is_tuple() {
+        let tag_ty = ty
+            .first()
+            .ok_or_else(|| Error::UnsupportedType(ty.deref().to_owned()))?;
+        let value_node = child_type_or_default(node, variant_index as usize);
+        (tag_ty, Some(value_node))
+    } else {
+        (ty.deref(), None)
+    };
+    let tag = match tag_ty.as_str() {
+        "s" => VariantTag::Str(variant.to_owned()),
+        "n" => VariantTag::I16(variant_index.try_into()?),
+        "i" => VariantTag::I32(variant_index.try_into()?),
+        "x" => VariantTag::I64(variant_index as i64),
+        "y" => VariantTag::U8(variant_index.try_into()?),
+        "q" => VariantTag::U16(variant_index.try_into()?),
+        "u" => VariantTag::U32(variant_index),
+        "t" => VariantTag::U64(variant_index as u64),
+        _ => return Err(Error::InvalidTag(tag_ty.to_owned())),
+    };
+    Ok((tag, value_ty))
 }
 
-impl<'t, 'n> ser::Serializer for Serializer<'t, 'n> {
-    type Ok = glib::Variant;
+impl<'t, 'n, 'b> ser::Serializer for Serializer<'t, 'n, 'b> {
+    type Ok = Option<glib::Variant>;
     type Error = Error;
-    type SerializeSeq = SeqSerializer<'t, 'n>;
-    type SerializeTuple = TupleSerializer<'t, 'n>;
-    type SerializeTupleStruct = TupleSerializer<'t, 'n>;
-    type SerializeTupleVariant = TupleVariantSerializer<'t, 'n>;
-    type SerializeMap = MapSerializer<'t, 'n>;
-    type SerializeStruct = TupleSerializer<'t, 'n>;
-    type SerializeStructVariant = TupleVariantSerializer<'t, 'n>;
+    type SerializeSeq = SeqSerializer<'t, 'n, 'b>;
+    type SerializeTuple = TupleSerializer<'t, 'n, 'b>;
+    type SerializeTupleStruct = TupleSerializer<'t, 'n, 'b>;
+    type SerializeTupleVariant = TupleVariantSerializer<'t, 'n, 'b>;
+    type SerializeMap = MapSerializer<'t, 'n, 'b>;
+    type SerializeStruct = TupleSerializer<'t, 'n, 'b>;
+    type SerializeStructVariant = TupleVariantSerializer<'t, 'n, 'b>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        self.emit(v.to_variant())
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
@@ -122,45 +181,49 @@ impl<'t, 'n> ser::Serializer for Serializer<'t, 'n> {
     }
 
     fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        self.emit(v.to_variant())
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        if self.node.type_().deref() == VariantTy::HANDLE {
+            self.emit(glib::Variant::from_handle(v))
+        } else {
+            self.emit(v.to_variant())
+        }
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        self.emit(v.to_variant())
     }
 
     serde::serde_if_integer128! {
         fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
             let v = v as u128;
             let buf = [(v >> 64) as i64, v as i64];
-            Ok(glib::Variant::array_from_fixed_array(&buf))
+            self.emit(glib::Variant::array_from_fixed_array(&buf))
         }
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        self.emit(v.to_variant())
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        self.emit(v.to_variant())
     }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        self.emit(v.to_variant())
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        self.emit(v.to_variant())
     }
 
     serde::serde_if_integer128! {
         fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
             let buf = [(v >> 64) as u64, v as u64];
-            Ok(glib::Variant::array_from_fixed_array(&buf))
+            self.emit(glib::Variant::array_from_fixed_array(&buf))
         }
     }
 
@@ -169,7 +232,7 @@ impl<'t, 'n> ser::Serializer for Serializer<'t, 'n> {
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        Ok(v.to_variant())
+        self.emit(v.to_variant())
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
@@ -178,25 +241,26 @@ impl<'t, 'n> ser::Serializer for Serializer<'t, 'n> {
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
         let ty = self.node.type_();
-        match ty.as_str() {
+        let variant = match ty.as_str() {
             "o" => ObjectPath::new(v)
                 .map(|o| o.to_variant())
-                .map_err(Error::Bool),
+                .map_err(Error::Bool)?,
             "g" => Signature::new(v)
                 .map(|g| g.to_variant())
-                .map_err(Error::Bool),
-            "s" | "*" => Ok(v.to_variant()),
-            _ => Err(Error::StrMismatch(ty.deref().to_owned())),
-        }
+                .map_err(Error::Bool)?,
+            "s" | "*" => v.to_variant(),
+            _ => return Err(Error::StrMismatch(ty.deref().to_owned())),
+        };
+        self.emit(variant)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        Ok(glib::Variant::array_from_fixed_array(v))
+        self.emit(glib::Variant::array_from_fixed_array(v))
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
         let ty = child_type_or_default(self.node, 0);
-        Ok(glib::Variant::from_none(ty.type_()))
+        self.emit(glib::Variant::from_none(ty.type_()))
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
@@ -204,17 +268,23 @@ impl<'t, 'n> ser::Serializer for Serializer<'t, 'n> {
         T: ?Sized + Serialize,
     {
         let ty = child_type_or_default(self.node, 0);
-        let value = value.serialize(Serializer::new(&ty))?;
-        value.is_of_type(ty.type_())?;
-        Ok(glib::Variant::from_some(&value))
+        let maybe_ty = if self.node.type_().is_maybe() {
+            Cow::Borrowed(self.node.type_().deref())
+        } else {
+            let maybe_ty = format!("m{}", ty.type_().as_str());
+            Cow::Owned(glib::VariantType::new(&maybe_ty)?)
+        };
+        let mut builder = Builder::open(self.sink, &maybe_ty)?;
+        value.serialize(Serializer::with_sink(&ty, builder.sink()))?;
+        builder.end()
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
-        Ok(().to_variant())
+        self.emit(().to_variant())
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
-        Ok(().to_variant())
+        self.emit(().to_variant())
     }
 
     fn serialize_unit_variant(
@@ -223,11 +293,11 @@ impl<'t, 'n> ser::Serializer for Serializer<'t, 'n> {
         variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        let (tag, value_ty) = self.variant_tag(variant_index, variant)?;
+        let (tag, value_ty) = variant_tag(self.node, variant_index, variant)?;
         if value_ty.is_some() {
-            Ok((tag, ().to_variant()).to_variant())
+            self.emit((tag, ().to_variant()).to_variant())
         } else {
-            Ok(tag.to_variant())
+            self.emit(tag.to_variant())
         }
     }
 
@@ -243,9 +313,43 @@ impl<'t, 'n> ser::Serializer for Serializer<'t, 'n> {
             VariantTypeNode::new_static(VariantTy::OBJECT_PATH);
         static SIGNATURE_NODE: VariantTypeNode<'static> =
             VariantTypeNode::new_static(VariantTy::SIGNATURE);
+        static UNIT_NODE: VariantTypeNode<'static> = VariantTypeNode::new_static(VariantTy::UNIT);
+        static BYTES_NODE: VariantTypeNode<'static> =
+            VariantTypeNode::new_static(VariantTy::BYTE_STRING);
         match name {
-            object_path::STRUCT_NAME => value.serialize(Serializer::new(&OBJECT_PATH_NODE)),
-            signature::STRUCT_NAME => value.serialize(Serializer::new(&SIGNATURE_NODE)),
+            object_path::STRUCT_NAME => value.serialize(Serializer {
+                node: &OBJECT_PATH_NODE,
+                human_readable: true,
+                sink: self.sink,
+            }),
+            signature::STRUCT_NAME => value.serialize(Serializer {
+                node: &SIGNATURE_NODE,
+                human_readable: true,
+                sink: self.sink,
+            }),
+            // write the type string and data fields, which are combined into one boxed variant
+            super::STRUCT_NAME => value.serialize(Serializer {
+                node: self.node,
+                human_readable: false,
+                sink: self.sink,
+            }),
+            super::lazy::STRUCT_NAME => {
+                let ty = self.node.type_();
+                let variant = super::lazy::capture(value, ty, || {
+                    value.serialize(Serializer::new(&UNIT_NODE))
+                })?;
+                variant.is_of_type(ty)?;
+                self.emit(variant)
+            }
+            super::fixed::STRUCT_NAME => {
+                let ty = self.node.type_();
+                let data =
+                    super::fixed::raw(value, ty, || serialize_with_node(value, &BYTES_NODE))?;
+                self.emit(glib::Variant::from_bytes_with_type(
+                    &data.data_as_bytes(),
+                    ty,
+                ))
+            }
             _ => value.serialize(self),
         }
     }
@@ -260,184 +364,189 @@ impl<'t, 'n> ser::Serializer for Serializer<'t, 'n> {
     where
         T: Serialize,
     {
-        let (tag, _) = self.variant_tag(variant_index, variant)?;
-        Ok((tag, value.serialize(self)?).to_variant())
+        let (tag, value_ty) = variant_tag(self.node, variant_index, variant)?;
+        let value_ty =
+            value_ty.ok_or_else(|| Error::UnsupportedType(self.node.type_().deref().to_owned()))?;
+        let mut builder = Builder::open(self.sink, self.node.type_())?;
+        builder.sink().add_value(&tag.to_variant())?;
+        descend(builder.sink(), VariantTy::VARIANT)?;
+        value.serialize(Serializer::with_sink(&value_ty, builder.sink()))?;
+        builder.end()
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        let len = len.unwrap_or_default();
         let ty = self.node.type_();
-        if ty.is_array() {
-            match ty.element().as_str() {
-                "y" => Ok(SeqSerializer::new_u8(len)),
-                "q" => Ok(SeqSerializer::new_u16(len)),
-                "u" => Ok(SeqSerializer::new_u32(len)),
-                "t" => Ok(SeqSerializer::new_u64(len)),
-                "n" => Ok(SeqSerializer::new_i16(len)),
-                "i" => Ok(SeqSerializer::new_i32(len)),
-                "x" => Ok(SeqSerializer::new_i64(len)),
-                "d" => Ok(SeqSerializer::new_f64(len)),
-                "b" => Ok(SeqSerializer::new_bool(len)),
-                _ => Ok(SeqSerializer::new(self.node, len)),
-            }
-        } else {
-            Ok(SeqSerializer::new(self.node, len))
+        if !ty.is_array() {
+            return Err(Error::UnsupportedType(ty.deref().to_owned()));
+        }
+        match FixedArray::new(ty.element().as_str(), len.unwrap_or_default()) {
+            Some(values) => Ok(SeqSerializer::Fixed {
+                values,
+                sink: self.sink,
+            }),
+            None => Ok(SeqSerializer::Variant {
+                child_node: child_type_or_default(self.node, 0),
+                builder: Builder::open(self.sink, ty)?,
+            }),
         }
     }
 
-    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        Ok(TupleSerializer::new(Cow::Borrowed(self.node), "", len))
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        TupleSerializer::new(Cow::Borrowed(self.node), "", self.sink)
     }
 
     fn serialize_tuple_struct(
         self,
         name: &'static str,
-        len: usize,
+        _len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        Ok(TupleSerializer::new(Cow::Borrowed(self.node), name, len))
+        TupleSerializer::new(Cow::Borrowed(self.node), name, self.sink)
     }
 
     fn serialize_tuple_variant(
         self,
-        name: &'static str,
+        _name: &'static str,
         variant_index: u32,
         variant: &'static str,
-        len: usize,
+        _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        let (tag, value_ty) = self.variant_tag(variant_index, variant)?;
+        let (tag, value_ty) = variant_tag(self.node, variant_index, variant)?;
         let value_ty =
             value_ty.ok_or_else(|| Error::UnsupportedType(self.node.type_().deref().to_owned()))?;
-        Ok(TupleVariantSerializer::new(tag, value_ty, name, len))
+        TupleVariantSerializer::new(tag, self.node.type_(), value_ty, self.sink)
     }
 
-    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        Ok(MapSerializer::new(self.node, len.unwrap_or_default()))
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        MapSerializer::new(self.node, self.sink)
     }
 
     fn serialize_struct(
         self,
         name: &'static str,
-        len: usize,
+        _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        Ok(TupleSerializer::new(Cow::Borrowed(self.node), name, len))
+        TupleSerializer::new(Cow::Borrowed(self.node), name, self.sink)
     }
 
     fn serialize_struct_variant(
         self,
-        name: &'static str,
+        _name: &'static str,
         variant_index: u32,
         variant: &'static str,
-        len: usize,
+        _len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        let (tag, value_ty) = self.variant_tag(variant_index, variant)?;
+        let (tag, value_ty) = variant_tag(self.node, variant_index, variant)?;
         let value_ty =
             value_ty.ok_or_else(|| Error::UnsupportedType(self.node.type_().deref().to_owned()))?;
-        Ok(TupleVariantSerializer::new(tag, value_ty, name, len))
+        TupleVariantSerializer::new(tag, self.node.type_(), value_ty, self.sink)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
     }
 }
 
-enum SeqSerializer<'t, 'n> {
-    Variant {
-        node: &'t VariantTypeNode<'n>,
-        child_node: Cow<'t, VariantTypeNode<'n>>,
-        variants: Vec<glib::Variant>,
-    },
-    U8 {
-        values: Vec<u8>,
-    },
-    U16 {
-        values: Vec<u16>,
-    },
-    U32 {
-        values: Vec<u32>,
-    },
-    U64 {
-        values: Vec<u64>,
-    },
-    I16 {
-        values: Vec<i16>,
-    },
-    I32 {
-        values: Vec<i32>,
-    },
-    I64 {
-        values: Vec<i64>,
-    },
-    F64 {
-        values: Vec<f64>,
-    },
-    Bool {
-        values: Vec<bool>,
-    },
+/// Container being written by a serializer. At the top level this owns a new builder, otherwise
+/// it is a child container opened in the parent's builder.
+enum Builder<'b> {
+    Root(VariantBuilder),
+    Child(VariantBuilderContainer<'b>),
 }
 
-impl<'t, 'n> SeqSerializer<'t, 'n> {
-    fn new(node: &'t VariantTypeNode<'n>, size: usize) -> Self {
-        let child_node = child_type_or_default(node, 0);
-        Self::Variant {
-            node,
-            child_node,
-            variants: Vec::with_capacity(size),
-        }
-    }
-    #[inline]
-    fn new_u8(size: usize) -> Self {
-        Self::U8 {
-            values: Vec::with_capacity(size),
-        }
-    }
-    #[inline]
-    fn new_u16(size: usize) -> Self {
-        Self::U16 {
-            values: Vec::with_capacity(size),
-        }
-    }
-    #[inline]
-    fn new_u32(size: usize) -> Self {
-        Self::U32 {
-            values: Vec::with_capacity(size),
-        }
-    }
-    #[inline]
-    fn new_u64(size: usize) -> Self {
-        Self::U64 {
-            values: Vec::with_capacity(size),
+impl<'b> Builder<'b> {
+    fn open(sink: Option<Sink<'b>>, ty: &VariantTy) -> Result<Self, Error> {
+        match sink {
+            Some(sink) => Ok(Self::Child(sink.open(ty)?)),
+            None => Ok(Self::Root(VariantBuilder::new(ty))),
         }
     }
-    #[inline]
-    fn new_i16(size: usize) -> Self {
-        Self::I16 {
-            values: Vec::with_capacity(size),
+    fn sink(&mut self) -> &mut dyn Sealed {
+        match self {
+            Self::Root(builder) => builder,
+            Self::Child(container) => container,
         }
     }
-    #[inline]
-    fn new_i32(size: usize) -> Self {
-        Self::I32 {
-            values: Vec::with_capacity(size),
+    fn end(self) -> Result<Option<glib::Variant>, Error> {
+        match self {
+            Self::Root(builder) => builder.end().map(Some),
+            Self::Child(container) => container.close().map(|_| None),
         }
     }
-    #[inline]
-    fn new_i64(size: usize) -> Self {
-        Self::I64 {
-            values: Vec::with_capacity(size),
-        }
+}
+
+/// Arrays of basic fixed-size types, which are collected and copied in with
+/// [`array_from_fixed_array`](glib::Variant::array_from_fixed_array).
+enum FixedArray {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    F64(Vec<f64>),
+    Bool(Vec<bool>),
+}
+
+impl FixedArray {
+    fn new(element: &str, size: usize) -> Option<Self> {
+        Some(match element {
+            "y" => Self::U8(Vec::with_capacity(size)),
+            "q" => Self::U16(Vec::with_capacity(size)),
+            "u" => Self::U32(Vec::with_capacity(size)),
+            "t" => Self::U64(Vec::with_capacity(size)),
+            "n" => Self::I16(Vec::with_capacity(size)),
+            "i" => Self::I32(Vec::with_capacity(size)),
+            "x" => Self::I64(Vec::with_capacity(size)),
+            "d" => Self::F64(Vec::with_capacity(size)),
+            "b" => Self::Bool(Vec::with_capacity(size)),
+            _ => return None,
+        })
     }
-    #[inline]
-    fn new_f64(size: usize) -> Self {
-        Self::F64 {
-            values: Vec::with_capacity(size),
+    fn push<S: ?Sized + Serialize>(&mut self, value: &S) -> Result<(), Error> {
+        let value = value.serialize(U64Serializer)?;
+        match self {
+            Self::U8(values) => values.push(value as u8),
+            Self::U16(values) => values.push(value as u16),
+            Self::U32(values) => values.push(value as u32),
+            Self::U64(values) => values.push(value),
+            Self::I16(values) => values.push(value as i16),
+            Self::I32(values) => values.push(value as i32),
+            Self::I64(values) => values.push(value as i64),
+            Self::F64(values) => values.push(f64::from_bits(value)),
+            Self::Bool(values) => values.push(value != 0),
         }
+        Ok(())
     }
-    #[inline]
-    fn new_bool(size: usize) -> Self {
-        Self::Bool {
-            values: Vec::with_capacity(size),
+    fn into_variant(self) -> glib::Variant {
+        match self {
+            Self::U8(values) => glib::Variant::array_from_fixed_array(&values),
+            Self::U16(values) => glib::Variant::array_from_fixed_array(&values),
+            Self::U32(values) => glib::Variant::array_from_fixed_array(&values),
+            Self::U64(values) => glib::Variant::array_from_fixed_array(&values),
+            Self::I16(values) => glib::Variant::array_from_fixed_array(&values),
+            Self::I32(values) => glib::Variant::array_from_fixed_array(&values),
+            Self::I64(values) => glib::Variant::array_from_fixed_array(&values),
+            Self::F64(values) => glib::Variant::array_from_fixed_array(&values),
+            Self::Bool(values) => glib::Variant::array_from_fixed_array(&values),
         }
     }
 }
 
-impl<'t, 'n> ser::SerializeSeq for SeqSerializer<'t, 'n> {
-    type Ok = glib::Variant;
+enum SeqSerializer<'t, 'n, 'b> {
+    Variant {
+        child_node: Cow<'t, VariantTypeNode<'n>>,
+        builder: Builder<'b>,
+    },
+    Fixed {
+        values: FixedArray,
+        sink: Option<Sink<'b>>,
+    },
+}
+
+impl<'t, 'n, 'b> ser::SerializeSeq for SeqSerializer<'t, 'n, 'b> {
+    type Ok = Option<glib::Variant>;
     type Error = Error;
 
     fn serialize_element<S: ?Sized>(&mut self, value: &S) -> Result<(), Self::Error>
@@ -446,40 +555,12 @@ impl<'t, 'n> ser::SerializeSeq for SeqSerializer<'t, 'n> {
     {
         match self {
             Self::Variant {
-                node: _,
                 child_node,
-                variants,
+                builder,
             } => {
-                let child_node = child_node.clone();
-                variants.push(value.serialize(Serializer::new(&child_node))?);
-            }
-            Self::U8 { values } => {
-                values.push(value.serialize(U64Serializer)? as u8);
-            }
-            Self::U16 { values } => {
-                values.push(value.serialize(U64Serializer)? as u16);
-            }
-            Self::U32 { values } => {
-                values.push(value.serialize(U64Serializer)? as u32);
-            }
-            Self::U64 { values } => {
-                values.push(value.serialize(U64Serializer)?);
-            }
-            Self::I16 { values } => {
-                values.push(value.serialize(U64Serializer)? as i16);
-            }
-            Self::I32 { values } => {
-                values.push(value.serialize(U64Serializer)? as i32);
-            }
-            Self::I64 { values } => {
-                values.push(value.serialize(U64Serializer)? as i64);
-            }
-            Self::F64 { values } => {
-                values.push(f64::from_bits(value.serialize(U64Serializer)?));
-            }
-            Self::Bool { values } => {
-                values.push(value.serialize(U64Serializer)? != 0);
+                value.serialize(Serializer::with_sink(child_node, builder.sink()))?;
             }
+            Self::Fixed { values, sink: _ } => values.push(value)?,
         }
         Ok(())
     }
@@ -487,88 +568,114 @@ impl<'t, 'n> ser::SerializeSeq for SeqSerializer<'t, 'n> {
     fn end(self) -> Result<Self::Ok, Self::Error> {
         match self {
             Self::Variant {
-                node,
                 child_node: _,
-                variants,
-            } => Ok(glib::Variant::array_from_variant_iter(
-                node.type_(),
-                variants,
-            )),
-            Self::U8 { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
-            Self::U16 { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
-            Self::U32 { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
-            Self::U64 { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
-            Self::I16 { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
-            Self::I32 { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
-            Self::I64 { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
-            Self::F64 { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
-            Self::Bool { values } => Ok(glib::Variant::array_from_fixed_array(&values)),
+                builder,
+            } => builder.end(),
+            Self::Fixed { values, sink } => emit(sink, values.into_variant()),
         }
     }
 }
 
-struct TupleSerializer<'t, 'n> {
+enum TupleTarget<'b> {
+    Builder(Builder<'b>),
+    /// Type string and data of a [`Variant`](super::Variant), combined into one boxed variant.
+    Variant {
+        sink: Option<Sink<'b>>,
+        ty: Option<glib::VariantType>,
+        value: Option<glib::Variant>,
+    },
+}
+
+struct TupleSerializer<'t, 'n, 'b> {
     node: Cow<'t, VariantTypeNode<'n>>,
-    name: &'static str,
     index: usize,
-    variants: Vec<glib::Variant>,
+    target: TupleTarget<'b>,
 }
 
-impl<'t, 'n> TupleSerializer<'t, 'n> {
-    fn new(node: Cow<'t, VariantTypeNode<'n>>, name: &'static str, size: usize) -> Self {
-        Self {
+impl<'t, 'n, 'b> TupleSerializer<'t, 'n, 'b> {
+    fn new(
+        node: Cow<'t, VariantTypeNode<'n>>,
+        name: &'static str,
+        sink: Option<Sink<'b>>,
+    ) -> Result<Self, Error> {
+        let target = if name == super::STRUCT_NAME {
+            TupleTarget::Variant {
+                sink,
+                ty: None,
+                value: None,
+            }
+        } else {
+            let ty = node.type_();
+            let ty = if ty.is_tuple() || ty.is_dict_entry() {
+                ty.deref()
+            } else {
+                VariantTy::TUPLE
+            };
+            TupleTarget::Builder(Builder::open(sink, ty)?)
+        };
+        Ok(Self {
             node,
-            name,
             index: 0,
-            variants: Vec::with_capacity(size),
-        }
+            target,
+        })
     }
 }
 
-impl<'t, 'n> ser::SerializeTuple for TupleSerializer<'t, 'n> {
-    type Ok = glib::Variant;
+impl<'t, 'n, 'b> ser::SerializeTuple for TupleSerializer<'t, 'n, 'b> {
+    type Ok = Option<glib::Variant>;
     type Error = Error;
 
     fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        let variant = if self.name == super::STRUCT_NAME && self.index == 1 {
-            let ty = glib::VariantType::new(
-                self.variants[0]
+        let node = child_type_or_default(&self.node, self.index);
+        match &mut self.target {
+            TupleTarget::Builder(builder) => {
+                value.serialize(Serializer::with_sink(&node, builder.sink()))?;
+            }
+            TupleTarget::Variant {
+                sink: _,
+                ty: ty @ None,
+                value: _,
+            } => {
+                let type_str = serialize_with_node(value, &node)?;
+                let type_str = type_str
                     .str()
-                    .ok_or_else(|| Error::StrMismatch(self.variants[0].type_().to_owned()))?,
-            )?;
-            let node = VariantTypeNode::new(ty.into(), []);
-            value.serialize(Serializer::new(&node))?
-        } else {
-            let node = child_type_or_default(&self.node, self.index);
-            value.serialize(Serializer::new(&node))?
-        };
-        self.variants.push(variant);
+                    .ok_or_else(|| Error::StrMismatch(type_str.type_().to_owned()))?;
+                ty.replace(glib::VariantType::new(type_str)?);
+            }
+            TupleTarget::Variant {
+                sink: _,
+                ty: Some(ty),
+                value: data,
+            } => {
+                let node = VariantTypeNode::new(Cow::Borrowed(&**ty), []);
+                let bytes = serialize_with_node(value, &node)?;
+                bytes.is_of_type(VariantTy::BYTE_STRING)?;
+                data.replace(super::variant_from_data(ty, bytes.data_as_bytes())?);
+            }
+        }
         self.index += 1;
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        if self.name == super::STRUCT_NAME {
-            assert_eq!(self.variants.len(), 2);
-            assert_eq!(
-                self.variants[0]
-                    .str()
-                    .ok_or_else(|| Error::StrMismatch(self.variants[0].type_().to_owned()))?,
-                self.variants[1].type_().as_str()
-            );
-            let mut variants = self.variants;
-            Ok(variants.remove(1).to_variant())
-        } else {
-            Ok(glib::Variant::tuple_from_iter(self.variants.into_iter()))
+        match self.target {
+            TupleTarget::Builder(builder) => builder.end(),
+            TupleTarget::Variant { sink, ty: _, value } => {
+                let value = value.ok_or(Error::LengthMismatch {
+                    actual: self.index,
+                    expected: 2,
+                })?;
+                emit(sink, value.to_variant())
+            }
         }
     }
 }
 
-impl<'t, 'n> ser::SerializeTupleStruct for TupleSerializer<'t, 'n> {
-    type Ok = glib::Variant;
+impl<'t, 'n, 'b> ser::SerializeTupleStruct for TupleSerializer<'t, 'n, 'b> {
+    type Ok = Option<glib::Variant>;
     type Error = Error;
 
     fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
@@ -583,7 +690,7 @@ impl<'t, 'n> ser::SerializeTupleStruct for TupleSerializer<'t, 'n> {
     }
 }
 
-enum VariantTag {
+pub(super) enum VariantTag {
     Str(String),
     I16(i16),
     I32(i32),
@@ -609,59 +716,93 @@ impl ToVariant for VariantTag {
     }
 }
 
-struct TupleVariantSerializer<'t, 'n> {
-    tag: VariantTag,
-    inner: TupleSerializer<'t, 'n>,
+/// Writes a `(tag, <value>)` enum variant, with the fields of the value written straight into
+/// the boxed child.
+struct TupleVariantSerializer<'t, 'n, 'b> {
+    node: Cow<'t, VariantTypeNode<'n>>,
+    index: usize,
+    builder: Builder<'b>,
 }
 
-impl<'t, 'n> TupleVariantSerializer<'t, 'n> {
+impl<'t, 'n, 'b> TupleVariantSerializer<'t, 'n, 'b> {
     fn new(
         tag: VariantTag,
+        enum_ty: &VariantTy,
         node: Cow<'t, VariantTypeNode<'n>>,
-        name: &'static str,
-        size: usize,
-    ) -> Self {
-        Self {
-            tag,
-            inner: TupleSerializer::new(node, name, size),
-        }
+        sink: Option<Sink<'b>>,
+    ) -> Result<Self, Error> {
+        let mut builder = Builder::open(sink, enum_ty)?;
+        builder.sink().add_value(&tag.to_variant())?;
+        descend(builder.sink(), VariantTy::VARIANT)?;
+        let ty = node.type_();
+        let ty = if ty.is_tuple() || ty.is_dict_entry() {
+            ty.deref()
+        } else {
+            VariantTy::TUPLE
+        };
+        descend(builder.sink(), ty)?;
+        Ok(Self {
+            node,
+            index: 0,
+            builder,
+        })
+    }
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let node = child_type_or_default(&self.node, self.index);
+        value.serialize(Serializer::with_sink(&node, self.builder.sink()))?;
+        self.index += 1;
+        Ok(())
     }
 }
 
-impl<'t, 'n> ser::SerializeTupleVariant for TupleVariantSerializer<'t, 'n> {
-    type Ok = glib::Variant;
+impl<'t, 'n, 'b> ser::SerializeTupleVariant for TupleVariantSerializer<'t, 'n, 'b> {
+    type Ok = Option<glib::Variant>;
     type Error = Error;
 
     fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        self.inner.serialize_element(value)
+        TupleVariantSerializer::serialize_field(self, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok((self.tag, SerializeTuple::end(self.inner)?).to_variant())
+        self.builder.end()
     }
 }
 
-struct MapSerializer<'t, 'n> {
+/// Returns the type of the entries in the dictionary `node`.
+fn entry_type<'t>(node: &'t VariantTypeNode) -> &'t VariantTy {
+    let ty = node.type_().element();
+    if ty.is_dict_entry() {
+        ty
+    } else {
+        VariantTy::DICT_ENTRY
+    }
+}
+
+struct MapSerializer<'t, 'n, 'b> {
     node: &'t VariantTypeNode<'n>,
+    builder: Builder<'b>,
     key: Option<glib::Variant>,
-    variants: Vec<glib::Variant>,
 }
 
-impl<'t, 'n> MapSerializer<'t, 'n> {
-    fn new(node: &'t VariantTypeNode<'n>, size: usize) -> Self {
-        Self {
+impl<'t, 'n, 'b> MapSerializer<'t, 'n, 'b> {
+    fn new(node: &'t VariantTypeNode<'n>, sink: Option<Sink<'b>>) -> Result<Self, Error> {
+        let ty = node.type_();
+        if !ty.is_array() {
+            return Err(Error::UnsupportedType(ty.deref().to_owned()));
+        }
+        Ok(Self {
             node,
+            builder: Builder::open(sink, ty)?,
             key: None,
-            variants: Vec::with_capacity(size),
-        }
+        })
     }
 }
 
-impl<'t, 'n> ser::SerializeMap for MapSerializer<'t, 'n> {
-    type Ok = glib::Variant;
+impl<'t, 'n, 'b> ser::SerializeMap for MapSerializer<'t, 'n, 'b> {
+    type Ok = Option<glib::Variant>;
     type Error = Error;
 
     fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
@@ -670,7 +811,7 @@ impl<'t, 'n> ser::SerializeMap for MapSerializer<'t, 'n> {
     {
         assert!(self.key.is_none());
         let key_node = child_type_or_default(self.node, 0);
-        self.key.replace(key.serialize(Serializer::new(&key_node))?);
+        self.key.replace(serialize_with_node(key, &key_node)?);
         Ok(())
     }
 
@@ -678,11 +819,12 @@ impl<'t, 'n> ser::SerializeMap for MapSerializer<'t, 'n> {
     where
         T: Serialize,
     {
+        let key = self.key.take().unwrap();
         let value_node = child_type_or_default(self.node, 1);
-        let value = value.serialize(Serializer::new(&value_node))?;
-        let variant = glib::Variant::from_dict_entry(&self.key.take().unwrap(), &value);
-        self.variants.push(variant);
-        Ok(())
+        let mut entry = self.builder.sink().open(entry_type(self.node))?;
+        entry.add_value(&key)?;
+        value.serialize(Serializer::with_sink(&value_node, &mut entry))?;
+        entry.close()
     }
 
     fn serialize_entry<K: ?Sized, V: ?Sized>(
@@ -697,21 +839,19 @@ impl<'t, 'n> ser::SerializeMap for MapSerializer<'t, 'n> {
         assert!(self.key.is_none());
         let key_node = child_type_or_default(self.node, 0);
         let value_node = child_type_or_default(self.node, 1);
-        let key = key.serialize(Serializer::new(&key_node))?;
-        let value = value.serialize(Serializer::new(&value_node))?;
-        let variant = glib::Variant::from_dict_entry(&key, &value);
-        self.variants.push(variant);
-        Ok(())
+        let mut entry = self.builder.sink().open(entry_type(self.node))?;
+        key.serialize(Serializer::with_sink(&key_node, &mut entry))?;
+        value.serialize(Serializer::with_sink(&value_node, &mut entry))?;
+        entry.close()
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        let ty = self.node.type_();
-        Ok(glib::Variant::array_from_variant_iter(ty, self.variants))
+        self.builder.end()
     }
 }
 
-impl<'t, 'n> ser::SerializeStruct for TupleSerializer<'t, 'n> {
-    type Ok = glib::Variant;
+impl<'t, 'n, 'b> ser::SerializeStruct for TupleSerializer<'t, 'n, 'b> {
+    type Ok = Option<glib::Variant>;
     type Error = Error;
 
     fn serialize_field<T: ?Sized>(
@@ -730,8 +870,8 @@ impl<'t, 'n> ser::SerializeStruct for TupleSerializer<'t, 'n> {
     }
 }
 
-impl<'t, 'n> ser::SerializeStructVariant for TupleVariantSerializer<'t, 'n> {
-    type Ok = glib::Variant;
+impl<'t, 'n, 'b> ser::SerializeStructVariant for TupleVariantSerializer<'t, 'n, 'b> {
+    type Ok = Option<glib::Variant>;
     type Error = Error;
 
     fn serialize_field<T: ?Sized>(
@@ -742,11 +882,11 @@ impl<'t, 'n> ser::SerializeStructVariant for TupleVariantSerializer<'t, 'n> {
     where
         T: Serialize,
     {
-        self.inner.serialize_element(value)
+        TupleVariantSerializer::serialize_field(self, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok((self.tag, SerializeTuple::end(self.inner)?).to_variant())
+        self.builder.end()
     }
 }
 
@@ -0,0 +1,152 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use super::GlibVariantExt;
+use crate::{Error, VariantType};
+use glib::VariantTy;
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer, Visitor},
+    Serialize,
+};
+
+/// Overwrites the fields of `value` that are present in the `a{sv}` dictionary `dict`, leaving
+/// the other fields unchanged. Returns the names of the fields whose values changed.
+///
+/// `T` must be a struct with named fields. Each dictionary value must have the type of its field;
+/// nested structs are updated through their own `deserialize_in_place`. Keys that do not name a
+/// field are an error.
+///
+/// The update goes through [`Deserialize::deserialize_in_place`](serde::Deserialize), which
+/// serde only derives with its `deserialize_in_place` feature; this crate enables it. Fields
+/// absent from `dict` are fed their current values, and fields that serde does not deserialize,
+/// like those marked `#[serde(skip)]`, are left as they are. Types with a hand-written
+/// `Deserialize` are replaced as a whole unless they implement `deserialize_in_place`.
+///
+/// Keys and value types are checked before anything is written, so `value` is only left partly
+/// updated if a field fails to deserialize, such as an enum with an unknown nick.
+pub fn update_from_variant<T>(
+    value: &mut T,
+    dict: &glib::Variant,
+) -> Result<Vec<&'static str>, Error>
+where
+    T: Serialize + DeserializeOwned + VariantType,
+{
+    super::check_type(dict, VariantTy::VARDICT)?;
+    let current = super::to_variant(&*value)?;
+    let mut changed = Vec::new();
+    let deserializer = UpdateDeserializer {
+        current: &current,
+        dict,
+        changed: &mut changed,
+    };
+    T::deserialize_in_place(deserializer, value)?;
+    Ok(changed)
+}
+
+struct UpdateDeserializer<'a> {
+    current: &'a glib::Variant,
+    dict: &'a glib::Variant,
+    changed: &'a mut Vec<&'static str>,
+}
+
+impl<'a> UpdateDeserializer<'a> {
+    /// Returns the current value of each field, in order. Structs with one field may be encoded
+    /// as that field alone rather than as a one-element tuple.
+    fn current_fields(&self, fields: &[&str]) -> Result<Vec<glib::Variant>, Error> {
+        let current = self.current;
+        if current.type_().is_tuple() && current.n_children() == fields.len() {
+            Ok(current.iter().collect())
+        } else if fields.len() == 1 {
+            Ok(vec![current.clone()])
+        } else {
+            Err(Error::UnsupportedType(current.type_().to_owned()))
+        }
+    }
+}
+
+impl<'a, 'de> Deserializer<'de> for UpdateDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(Error::UnsupportedType(self.current.type_().to_owned()))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let current = self.current_fields(fields)?;
+        for i in 0..self.dict.n_children() {
+            let key = self.dict.child_value(i).child_value(0);
+            let key = key.str().unwrap();
+            if !fields.contains(&key) {
+                return Err(de::Error::unknown_field(key, fields));
+            }
+        }
+        let dict = glib::VariantDict::new(Some(self.dict));
+        let mut entries = Vec::with_capacity(fields.len());
+        for (field, old) in fields.iter().zip(current) {
+            match dict.lookup_value(field, None) {
+                Some(new) => {
+                    super::check_type(&new, old.type_())?;
+                    if new != old {
+                        self.changed.push(*field);
+                    }
+                    entries.push((*field, new));
+                }
+                None => entries.push((*field, old)),
+            }
+        }
+        visitor.visit_map(UpdateMap {
+            entries: entries.into_iter(),
+            value: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Hands every field to the struct visitor, with either its new or its current value.
+struct UpdateMap {
+    entries: std::vec::IntoIter<(&'static str, glib::Variant)>,
+    value: Option<glib::Variant>,
+}
+
+impl<'de> de::MapAccess<'de> for UpdateMap {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((field, value)) => {
+                self.value = Some(value);
+                seed.deserialize(IntoDeserializer::<'de, Error>::into_deserializer(field))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::Custom("value is missing".into()))?;
+        seed.deserialize(value.as_serializable())
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
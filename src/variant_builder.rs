@@ -1,78 +1,302 @@
 // SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
 // SPDX-License-Identifier: MIT
 
-use glib::{ffi, translate::*, ToVariant, Variant, VariantTy};
-use std::{marker::PhantomData, mem::MaybeUninit, ptr::NonNull};
+use crate::{Error, VariantType};
+use glib::{ffi, translate::*, variant::VariantTypeMismatchError, ToVariant, Variant, VariantTy};
+use serde::Serialize;
+use std::{mem::MaybeUninit, ptr::NonNull};
 
-pub struct VariantBuilder(ffi::GVariantBuilder);
-
-impl VariantBuilder {
-    pub fn new(ty: &VariantTy) -> Self {
-        let mut builder: MaybeUninit<ffi::GVariantBuilder> = MaybeUninit::uninit();
-        Self(unsafe {
-            ffi::g_variant_builder_init(builder.as_mut_ptr(), ty.to_glib_none().0);
-            builder.assume_init()
-        })
+pub(crate) mod private {
+    /// Tracks the children added to one level of a builder.
+    pub struct Frame {
+        pub(super) ty: glib::VariantType,
+        pub(super) elem: Option<glib::VariantType>,
+        /// Types of the children of an indefinite maybe, tuple or dict entry.
+        pub(super) children: Vec<glib::VariantType>,
+        /// Frames of the enclosing containers opened with `descend`, innermost last.
+        pub(super) outer: Vec<Frame>,
+        pub(super) count: usize,
+        pub(super) poisoned: bool,
     }
-    pub fn end(self) -> Variant {
-        let v = unsafe { self.end_unsafe() };
-        std::mem::forget(self);
-        v
+
+    pub trait Sealed {
+        fn parts(&mut self) -> (*mut glib::ffi::GVariantBuilder, &mut Frame);
     }
 }
 
-impl Drop for VariantBuilder {
-    fn drop(&mut self) {
-        unsafe { ffi::g_variant_builder_clear(self.as_ptr()) };
+use private::{Frame, Sealed};
+
+impl Frame {
+    fn new(ty: &VariantTy) -> Self {
+        Self {
+            ty: ty.to_owned(),
+            elem: None,
+            children: Vec::new(),
+            outer: Vec::new(),
+            count: 0,
+            poisoned: false,
+        }
+    }
+    fn check_poisoned(&self) -> Result<(), Error> {
+        if self.poisoned {
+            Err(Error::Custom(String::from(
+                "Child container was dropped before it was complete",
+            )))
+        } else {
+            Ok(())
+        }
+    }
+    fn next_type(&self) -> Result<&VariantTy, Error> {
+        self.check_poisoned()?;
+        let ty: &VariantTy = &self.ty;
+        let too_many = |expected| Error::LengthMismatch {
+            actual: self.count + 1,
+            expected,
+        };
+        if ty.is_array() {
+            Ok(self.elem.as_deref().unwrap_or_else(|| ty.element()))
+        } else if ty.is_maybe() {
+            if self.count > 0 {
+                return Err(too_many(1));
+            }
+            Ok(ty.element())
+        } else if ty == VariantTy::VARIANT {
+            if self.count > 0 {
+                return Err(too_many(1));
+            }
+            Ok(VariantTy::ANY)
+        } else if ty == VariantTy::TUPLE {
+            Ok(VariantTy::ANY)
+        } else {
+            let mut child = ty.first();
+            for _ in 0..self.count {
+                child = child.and_then(|c| c.next());
+            }
+            child.ok_or_else(|| too_many(ty.n_items()))
+        }
+    }
+    fn push(&mut self, child: &VariantTy) -> Result<(), Error> {
+        let expected = self.next_type()?;
+        if !crate::variant::is_subtype_of(child, expected) {
+            return Err(Error::Mismatch(VariantTypeMismatchError::new(
+                child.to_owned(),
+                expected.to_owned(),
+            )));
+        }
+        if self.ty.is_array() && self.elem.is_none() && !self.ty.element().is_definite() {
+            self.elem = Some(child.to_owned());
+        } else if !self.ty.is_array() && !self.ty.is_definite() {
+            self.children.push(child.to_owned());
+        }
+        self.count += 1;
+        Ok(())
+    }
+    /// Replaces the type recorded for the last child with `child`, the type of a child container
+    /// that was opened with an indefinite type and has now been built.
+    fn resolve_last(&mut self, child: glib::VariantType) {
+        if self.ty.is_array() && self.count == 1 && !self.ty.element().is_definite() {
+            self.elem = Some(child);
+        } else if let Some(last) = self.children.last_mut() {
+            *last = child;
+        }
+    }
+    /// Makes the child container `ty`, just opened in GLib, the current frame.
+    fn descend(&mut self, ty: &VariantTy) {
+        let mut outer = std::mem::take(&mut self.outer);
+        outer.push(std::mem::replace(self, Frame::new(ty)));
+        self.outer = outer;
+    }
+    /// Closes the containers opened with `descend`, failing if one is missing children.
+    fn ascend_all(&mut self, ptr: *mut ffi::GVariantBuilder) -> Result<(), Error> {
+        while !self.outer.is_empty() {
+            self.finish()?;
+            unsafe { ffi::g_variant_builder_close(ptr) };
+            let mut outer = std::mem::take(&mut self.outer);
+            let child = std::mem::replace(self, outer.pop().unwrap());
+            self.outer = outer;
+            if !child.ty.is_definite() {
+                self.resolve_last(child.built_type());
+            }
+        }
+        Ok(())
+    }
+    /// Returns the type of the finished container, which is definite even if `ty` is not.
+    fn built_type(&self) -> glib::VariantType {
+        let ty: &VariantTy = &self.ty;
+        if ty.is_definite() {
+            return ty.to_owned();
+        }
+        let children = self.children.iter().map(|c| c.as_str());
+        let s = if ty.is_array() {
+            format!("a{}", self.elem.as_ref().unwrap().as_str())
+        } else if ty.is_maybe() {
+            match self.children.first() {
+                Some(child) => format!("m{}", child.as_str()),
+                None => ty.as_str().to_owned(),
+            }
+        } else if ty.is_dict_entry() {
+            format!("{{{}}}", children.collect::<String>())
+        } else {
+            format!("({})", children.collect::<String>())
+        };
+        glib::VariantType::new(&s).unwrap()
+    }
+    fn finish(&self) -> Result<(), Error> {
+        self.check_poisoned()?;
+        let ty: &VariantTy = &self.ty;
+        let expected = if (ty.is_tuple() && ty != VariantTy::TUPLE) || ty.is_dict_entry() {
+            Some(ty.n_items())
+        } else if ty == VariantTy::VARIANT {
+            Some(1)
+        } else {
+            None
+        };
+        if let Some(expected) = expected {
+            if self.count != expected {
+                return Err(Error::LengthMismatch {
+                    actual: self.count,
+                    expected,
+                });
+            }
+        }
+        if (ty.is_array() || ty.is_maybe()) && self.count == 0 && !ty.element().is_definite() {
+            return Err(Error::UnsupportedType(ty.to_owned()));
+        }
+        Ok(())
     }
 }
 
-pub trait VariantBuilderExt {
-    fn as_ptr(&self) -> *mut ffi::GVariantBuilder;
-    unsafe fn add<T: ToVariant>(&self, value: &T) {
-        self.add(&value.to_variant());
+/// Safe interface for building container variants.
+///
+/// Each added value is checked against the type expected by the container, returning an
+/// [`Error`] instead of aborting inside GLib.
+pub trait VariantBuilderExt: Sealed {
+    fn add_value(&mut self, value: &Variant) -> Result<(), Error> {
+        let (ptr, frame) = self.parts();
+        frame.push(value.type_())?;
+        unsafe { ffi::g_variant_builder_add_value(ptr, value.to_glib_none().0) };
+        Ok(())
+    }
+    fn add<T: ToVariant>(&mut self, value: &T) -> Result<(), Error> {
+        self.add_value(&value.to_variant())
     }
-    unsafe fn add_value(&self, value: &Variant) {
-        ffi::g_variant_builder_add_value(self.as_ptr(), value.to_glib_none().0);
+    /// Serializes `value` with [`to_variant`](crate::to_variant) and adds it.
+    fn serialize<T: Serialize + VariantType>(&mut self, value: &T) -> Result<(), Error> {
+        self.add_value(&crate::to_variant(value)?)
     }
-    fn open(&self, ty: &VariantTy) -> VariantBuilderContainer<'_> {
-        unsafe { ffi::g_variant_builder_open(self.as_ptr(), ty.to_glib_none().0) };
-        VariantBuilderContainer {
-            inner: NonNull::new(self.as_ptr()).unwrap(),
-            phantom: PhantomData,
+    /// Opens a child container of type `ty`. Values are added to the child until the returned
+    /// guard is closed or dropped.
+    fn open(&mut self, ty: &VariantTy) -> Result<VariantBuilderContainer<'_>, Error> {
+        if !ty.is_container() {
+            return Err(Error::UnsupportedType(ty.to_owned()));
         }
+        let (ptr, parent) = self.parts();
+        parent.push(ty)?;
+        unsafe { ffi::g_variant_builder_open(ptr, ty.to_glib_none().0) };
+        Ok(VariantBuilderContainer {
+            inner: NonNull::new(ptr).unwrap(),
+            frame: Frame::new(ty),
+            parent,
+        })
     }
-    unsafe fn end_unsafe(&self) -> Variant {
-        from_glib_none(ffi::g_variant_builder_end(self.as_ptr()))
+}
+
+impl<T: Sealed + ?Sized> VariantBuilderExt for T {}
+
+/// Opens a child container of type `ty` without a guard, for serializers that cannot hold one.
+/// Values are added to the child until the builder or guard that owns it is ended or closed,
+/// which closes the child first.
+pub(crate) fn descend<B: Sealed + ?Sized>(builder: &mut B, ty: &VariantTy) -> Result<(), Error> {
+    if !ty.is_container() {
+        return Err(Error::UnsupportedType(ty.to_owned()));
     }
+    let (ptr, frame) = builder.parts();
+    frame.push(ty)?;
+    unsafe { ffi::g_variant_builder_open(ptr, ty.to_glib_none().0) };
+    frame.descend(ty);
+    Ok(())
 }
 
-impl VariantBuilderExt for VariantBuilder {
-    fn as_ptr(&self) -> *mut ffi::GVariantBuilder {
-        &self.0 as *const _ as *mut _
+/// Wrapper for a stack-allocated [`GVariantBuilder`](ffi::GVariantBuilder).
+pub struct VariantBuilder {
+    inner: ffi::GVariantBuilder,
+    frame: Frame,
+}
+
+impl VariantBuilder {
+    /// Panics if `ty` is not a container type.
+    pub fn new(ty: &VariantTy) -> Self {
+        assert!(ty.is_container(), "Type `{}` is not a container", ty);
+        let mut builder: MaybeUninit<ffi::GVariantBuilder> = MaybeUninit::uninit();
+        Self {
+            inner: unsafe {
+                ffi::g_variant_builder_init(builder.as_mut_ptr(), ty.to_glib_none().0);
+                builder.assume_init()
+            },
+            frame: Frame::new(ty),
+        }
+    }
+    /// Builds the variant, failing if the container is missing children.
+    pub fn end(mut self) -> Result<Variant, Error> {
+        self.frame.ascend_all(&mut self.inner)?;
+        self.frame.finish()?;
+        Ok(unsafe { from_glib_none(ffi::g_variant_builder_end(&mut self.inner)) })
     }
 }
 
-#[repr(transparent)]
+impl Drop for VariantBuilder {
+    fn drop(&mut self) {
+        unsafe { ffi::g_variant_builder_clear(&mut self.inner) };
+    }
+}
+
+impl Sealed for VariantBuilder {
+    fn parts(&mut self) -> (*mut ffi::GVariantBuilder, &mut Frame) {
+        (&mut self.inner, &mut self.frame)
+    }
+}
+
+/// Guard for a child container opened with [`VariantBuilderExt::open`].
+///
+/// Dropping the guard closes the container. If the container is missing children, the parent is
+/// marked as failed instead, and returns an error from any further calls.
 pub struct VariantBuilderContainer<'t> {
     inner: NonNull<ffi::GVariantBuilder>,
-    phantom: PhantomData<&'t ()>,
+    frame: Frame,
+    parent: &'t mut Frame,
+}
+
+impl<'t> VariantBuilderContainer<'t> {
+    /// Closes the container, failing if it is missing children.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.frame.ascend_all(self.inner.as_ptr())?;
+        self.frame.finish()
+    }
 }
 
 impl<'t> Drop for VariantBuilderContainer<'t> {
     fn drop(&mut self) {
-        unsafe { ffi::g_variant_builder_close(self.inner.as_ptr()) };
+        let ptr = self.inner.as_ptr();
+        let finished = self.frame.ascend_all(ptr).and_then(|_| self.frame.finish());
+        if finished.is_ok() {
+            unsafe { ffi::g_variant_builder_close(ptr) };
+            if !self.frame.ty.is_definite() {
+                self.parent.resolve_last(self.frame.built_type());
+            }
+        } else {
+            self.parent.poisoned = true;
+        }
     }
 }
 
-impl<'t> VariantBuilderExt for VariantBuilderContainer<'t> {
-    fn as_ptr(&self) -> *mut ffi::GVariantBuilder {
-        self.inner.as_ptr()
+impl<'t> Sealed for VariantBuilderContainer<'t> {
+    fn parts(&mut self) -> (*mut ffi::GVariantBuilder, &mut Frame) {
+        (self.inner.as_ptr(), &mut self.frame)
     }
 }
 
 glib::wrapper! {
-    pub struct SharedVariantBuilder(Shared<ffi::GVariantBuilder>);
+    struct SharedBuilder(Shared<ffi::GVariantBuilder>);
 
     match fn {
         ref => |ptr| ffi::g_variant_builder_ref(ptr),
@@ -81,21 +305,42 @@ glib::wrapper! {
     }
 }
 
+/// Wrapper for a reference-counted [`GVariantBuilder`](ffi::GVariantBuilder) that can be passed
+/// to C functions. Values added through the pointer from C are not checked.
+pub struct SharedVariantBuilder {
+    inner: SharedBuilder,
+    frame: Frame,
+}
+
 impl SharedVariantBuilder {
+    /// Panics if `ty` is not a container type.
     pub fn new(ty: &VariantTy) -> Self {
-        unsafe { from_glib_full(ffi::g_variant_builder_new(ty.to_glib_none().0)) }
+        assert!(ty.is_container(), "Type `{}` is not a container", ty);
+        Self {
+            inner: unsafe { from_glib_full(ffi::g_variant_builder_new(ty.to_glib_none().0)) },
+            frame: Frame::new(ty),
+        }
     }
-    pub fn end(&self) -> Variant {
+    /// Builds the variant and resets the builder, failing if the container is missing children.
+    pub fn end(&mut self) -> Result<Variant, Error> {
+        let ptr = self.as_ptr();
+        self.frame.ascend_all(ptr)?;
+        self.frame.finish()?;
+        let ty = self.frame.ty.clone();
+        self.frame = Frame::new(&ty);
         unsafe {
-            let v = self.end_unsafe();
-            ffi::g_variant_builder_init(self.as_ptr(), v.type_().to_glib_none().0);
-            v
+            let v = from_glib_none(ffi::g_variant_builder_end(ptr));
+            ffi::g_variant_builder_init(ptr, ty.to_glib_none().0);
+            Ok(v)
         }
     }
+    pub fn as_ptr(&self) -> *mut ffi::GVariantBuilder {
+        self.inner.to_glib_none().0
+    }
 }
 
-impl VariantBuilderExt for SharedVariantBuilder {
-    fn as_ptr(&self) -> *mut ffi::GVariantBuilder {
-        self.0.to_glib_none().0
+impl Sealed for SharedVariantBuilder {
+    fn parts(&mut self) -> (*mut ffi::GVariantBuilder, &mut Frame) {
+        (self.as_ptr(), &mut self.frame)
     }
 }
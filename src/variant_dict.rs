@@ -36,11 +36,9 @@ impl glib::StaticVariantType for VariantDict {
 }
 
 impl super::VariantType for VariantDict {
+    const TYPE_STR: Option<&'static str> = Some("a{sv}");
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
-        Cow::Owned(VariantTypeNode::new(
-            <Self as glib::StaticVariantType>::static_variant_type(),
-            [String::variant_type(), Variant::variant_type()],
-        ))
+        <std::collections::HashMap<String, Variant> as super::VariantType>::variant_type()
     }
 }
 
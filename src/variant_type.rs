@@ -1,10 +1,15 @@
 // SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
 // SPDX-License-Identifier: MIT
 
-use glib::variant::DictEntry;
+use __private::{JoinedTypeStr, TypeStrParts};
+use glib::{once_cell::sync::Lazy, variant::DictEntry};
 use std::{
-    borrow::Cow,
+    borrow::{Borrow, Cow},
+    cell::RefCell,
     collections::{BTreeMap, HashMap},
+    hash::Hash,
+    sync::RwLock,
+    thread::LocalKey,
 };
 
 /// A tree node that stores [`glib::VariantTy`]s for enum variants.
@@ -38,122 +43,301 @@ impl<'t> VariantTypeNode<'t> {
     }
 }
 
+type NodeMap<K> = HashMap<K, &'static VariantTypeNode<'static>>;
+
+/// Nodes without children, keyed by type string.
+static LEAF_NODES: Lazy<RwLock<NodeMap<String>>> = Lazy::new(Default::default);
+/// Container nodes, keyed by a tag for the kind of container followed by the addresses of the
+/// child nodes.
+static CONTAINER_NODES: Lazy<RwLock<NodeMap<Vec<usize>>>> = Lazy::new(Default::default);
+
+thread_local! {
+    /// Copies of the global caches, so that lookups of known types take no lock.
+    static LOCAL_LEAF_NODES: RefCell<NodeMap<String>> = Default::default();
+    static LOCAL_CONTAINER_NODES: RefCell<NodeMap<Vec<usize>>> = Default::default();
+}
+
+/// Looks up `key` in the thread's cache, then in the global cache, building the node with `build`
+/// if neither has it. Nodes are never freed, and there is one per key across all threads.
+fn cached_node<Q, K>(
+    local: &'static LocalKey<RefCell<NodeMap<K>>>,
+    global: &RwLock<NodeMap<K>>,
+    key: &Q,
+    build: impl FnOnce() -> VariantTypeNode<'static>,
+) -> Cow<'static, VariantTypeNode<'static>>
+where
+    Q: Eq + Hash + ToOwned<Owned = K> + ?Sized,
+    K: Eq + Hash + Borrow<Q>,
+{
+    if let Some(node) = local.with(|local| local.borrow().get(key).copied()) {
+        return Cow::Borrowed(node);
+    }
+    let cached = global.read().unwrap().get(key).copied();
+    let node = match cached {
+        Some(node) => node,
+        None => *global
+            .write()
+            .unwrap()
+            .entry(key.to_owned())
+            .or_insert_with(|| Box::leak(Box::new(build()))),
+    };
+    local.with(|local| local.borrow_mut().insert(key.to_owned(), node));
+    Cow::Borrowed(node)
+}
+
+/// Returns the node for a type without children, building it on the first call.
+fn leaf_node(ty: Cow<'static, glib::VariantTy>) -> Cow<'static, VariantTypeNode<'static>> {
+    cached_node(&LOCAL_LEAF_NODES, &LEAF_NODES, ty.as_str(), || {
+        VariantTypeNode::new(ty.clone(), [])
+    })
+}
+
+/// Returns the node for a container of `children`, calling `build` only the first time it is
+/// seen. The cache only applies if every child node is static, which holds for the types in
+/// this crate and derived types.
+fn container_node<const N: usize>(
+    tag: u8,
+    children: [Cow<'static, VariantTypeNode<'static>>; N],
+    build: impl FnOnce([Cow<'static, VariantTypeNode<'static>>; N]) -> VariantTypeNode<'static>,
+) -> Cow<'static, VariantTypeNode<'static>> {
+    if children.iter().any(|child| matches!(child, Cow::Owned(_))) {
+        return Cow::Owned(build(children));
+    }
+    let mut key = [0usize; 17];
+    key[0] = tag as usize;
+    for (addr, child) in key[1..].iter_mut().zip(&children) {
+        if let Cow::Borrowed(node) = child {
+            *addr = *node as *const VariantTypeNode as usize;
+        }
+    }
+    cached_node(&LOCAL_CONTAINER_NODES, &CONTAINER_NODES, &key[..=N], || {
+        build(children)
+    })
+}
+
 /// An extension of [`StaticVariantType`](glib::StaticVariantType) that can retreive types for enum
 /// variants.
 pub trait VariantType: glib::StaticVariantType {
+    /// The type string, for types where it is known at compile time.
+    ///
+    /// `#[derive(VariantType)]` fills this in for structs whose field types all provide one,
+    /// and also adds an inherent `VARIANT_TYPE_STR` constant for use in const contexts. The
+    /// standard containers provide one when their children do.
+    ///
+    /// This must be a single complete type that matches
+    /// [`static_variant_type`](glib::StaticVariantType::static_variant_type). Derived types
+    /// fail to compile if the type strings of their fields don't join into a valid one.
+    const TYPE_STR: Option<&'static str> = None;
+    /// Whether [`validate_value`](Self::validate_value) checks anything. Containers skip their
+    /// children when it doesn't.
+    const VALIDATES_VALUES: bool = false;
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
-        Cow::Owned(VariantTypeNode::new(Self::static_variant_type(), []))
+        leaf_node(Self::static_variant_type())
+    }
+    /// Checks the parts of `variant` that its type doesn't constrain, such as enum nicks, without
+    /// deserializing it. `variant` must already have the type from
+    /// [`variant_type`](Self::variant_type), as checked by [`validate`](crate::validate).
+    ///
+    /// The default accepts any value.
+    fn validate_value(_variant: &glib::Variant) -> Result<(), crate::Error> {
+        Ok(())
     }
 }
 
-impl VariantType for glib::Variant {}
-impl VariantType for glib::VariantDict {}
-impl VariantType for () {}
-impl VariantType for u8 {}
-impl VariantType for i16 {}
-impl VariantType for u16 {}
-impl VariantType for i32 {}
-impl VariantType for u32 {}
-impl VariantType for i64 {}
-impl VariantType for u64 {}
-impl VariantType for f64 {}
-impl VariantType for bool {}
-impl VariantType for String {}
-impl VariantType for str {}
+/// Validates each child of a container variant as `T`.
+fn validate_children<T: VariantType + ?Sized>(variant: &glib::Variant) -> Result<(), crate::Error> {
+    if T::VALIDATES_VALUES {
+        for i in 0..variant.n_children() {
+            T::validate_value(&variant.child_value(i))?;
+        }
+    }
+    Ok(())
+}
+
+macro_rules! basic_impls {
+    ($($ty:ty => $type_str:literal),+ $(,)?) => {
+        $(
+            impl VariantType for $ty {
+                const TYPE_STR: Option<&'static str> = Some($type_str);
+            }
+        )+
+    }
+}
+
+basic_impls! {
+    glib::Variant => "v",
+    glib::VariantDict => "a{sv}",
+    () => "()",
+    u8 => "y",
+    i16 => "n",
+    u16 => "q",
+    i32 => "i",
+    u32 => "u",
+    i64 => "x",
+    u64 => "t",
+    f64 => "d",
+    bool => "b",
+    String => "s",
+    str => "s",
+}
 
 impl<'a, T: ?Sized + VariantType> VariantType for &'a T {
+    const TYPE_STR: Option<&'static str> = T::TYPE_STR;
+    const VALIDATES_VALUES: bool = T::VALIDATES_VALUES;
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
         T::variant_type()
     }
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        T::validate_value(variant)
+    }
+}
+
+impl<T: VariantType> TypeStrParts for Option<T> {
+    const TYPE_STR_PARTS: &'static [Option<&'static str>] = &[Some("m"), T::TYPE_STR];
 }
 
 impl<T: VariantType> VariantType for Option<T> {
+    const TYPE_STR: Option<&'static str> = JoinedTypeStr::<Self>::TYPE_STR;
+    const VALIDATES_VALUES: bool = T::VALIDATES_VALUES;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        validate_children::<T>(variant)
+    }
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
-        let child_node = T::variant_type();
-        let mut builder = glib::GStringBuilder::new("m");
-        builder.append(child_node.type_().as_str());
-        let ty = glib::VariantType::from_string(builder.into_string()).unwrap();
-        Cow::Owned(VariantTypeNode::new(
-            Cow::Owned(ty),
-            [child_node.to_owned()],
-        ))
+        container_node(b'm', [T::variant_type()], |[child_node]| {
+            let mut builder = glib::GStringBuilder::new("m");
+            builder.append(child_node.type_().as_str());
+            let ty = glib::VariantType::from_string(builder.into_string()).unwrap();
+            VariantTypeNode::new(Cow::Owned(ty), [child_node])
+        })
     }
 }
 
+impl<T: VariantType> TypeStrParts for [T] {
+    const TYPE_STR_PARTS: &'static [Option<&'static str>] = &[Some("a"), T::TYPE_STR];
+}
+
 impl<T: VariantType> VariantType for [T] {
+    const TYPE_STR: Option<&'static str> = JoinedTypeStr::<Self>::TYPE_STR;
+    const VALIDATES_VALUES: bool = T::VALIDATES_VALUES;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        validate_children::<T>(variant)
+    }
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
-        let child_node = T::variant_type();
-        let mut builder = glib::GStringBuilder::new("a");
-        builder.append(child_node.type_().as_str());
-        let ty = glib::VariantType::from_string(builder.into_string()).unwrap();
-        Cow::Owned(VariantTypeNode::new(
-            Cow::Owned(ty),
-            [child_node.to_owned()],
-        ))
+        container_node(b'a', [T::variant_type()], |[child_node]| {
+            let mut builder = glib::GStringBuilder::new("a");
+            builder.append(child_node.type_().as_str());
+            let ty = glib::VariantType::from_string(builder.into_string()).unwrap();
+            VariantTypeNode::new(Cow::Owned(ty), [child_node])
+        })
     }
 }
 
 impl<T: VariantType> VariantType for Vec<T> {
+    const TYPE_STR: Option<&'static str> = <[T]>::TYPE_STR;
+    const VALIDATES_VALUES: bool = T::VALIDATES_VALUES;
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
         <[T]>::variant_type()
     }
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        validate_children::<T>(variant)
+    }
 }
 
 impl<A: AsRef<[T]>, T: glib::FixedSizeVariantType + VariantType> VariantType
     for glib::FixedSizeVariantArray<A, T>
 {
+    const TYPE_STR: Option<&'static str> = <[T]>::TYPE_STR;
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
         <[T]>::variant_type()
     }
 }
 
+impl<K: VariantType, V: VariantType> TypeStrParts for DictEntry<K, V> {
+    const TYPE_STR_PARTS: &'static [Option<&'static str>] =
+        &[Some("{"), K::TYPE_STR, V::TYPE_STR, Some("}")];
+}
+
 impl<K: VariantType, V: VariantType> VariantType for DictEntry<K, V> {
+    const TYPE_STR: Option<&'static str> = JoinedTypeStr::<Self>::TYPE_STR;
+    const VALIDATES_VALUES: bool = K::VALIDATES_VALUES || V::VALIDATES_VALUES;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        K::validate_value(&variant.child_value(0))?;
+        V::validate_value(&variant.child_value(1))
+    }
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
-        let key_node = K::variant_type();
-        let value_node = V::variant_type();
-        let mut builder = glib::GStringBuilder::new("{");
-        builder.append(key_node.type_().as_str());
-        builder.append(value_node.type_().as_str());
-        builder.append_c('}');
-        let ty = glib::VariantType::from_string(builder.into_string()).unwrap();
-        Cow::Owned(VariantTypeNode::new(
-            Cow::Owned(ty),
-            [key_node.to_owned(), value_node.to_owned()],
-        ))
+        let children = [K::variant_type(), V::variant_type()];
+        container_node(b'{', children, |[key_node, value_node]| {
+            let mut builder = glib::GStringBuilder::new("{");
+            builder.append(key_node.type_().as_str());
+            builder.append(value_node.type_().as_str());
+            builder.append_c('}');
+            let ty = glib::VariantType::from_string(builder.into_string()).unwrap();
+            VariantTypeNode::new(Cow::Owned(ty), [key_node, value_node])
+        })
     }
 }
 
+impl<K: VariantType, V: VariantType> TypeStrParts for HashMap<K, V> {
+    const TYPE_STR_PARTS: &'static [Option<&'static str>] =
+        &[Some("a"), <DictEntry<K, V>>::TYPE_STR];
+}
+
 impl<K: VariantType, V: VariantType> VariantType for HashMap<K, V> {
+    const TYPE_STR: Option<&'static str> = JoinedTypeStr::<Self>::TYPE_STR;
+    const VALIDATES_VALUES: bool = <DictEntry<K, V>>::VALIDATES_VALUES;
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        validate_children::<DictEntry<K, V>>(variant)
+    }
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
-        let child_node = <DictEntry<K, V>>::variant_type();
-        let mut builder = glib::GStringBuilder::new("a");
-        builder.append(child_node.type_().as_str());
-        let ty = glib::VariantType::from_string(builder.into_string()).unwrap();
-        Cow::Owned(VariantTypeNode::new(
-            Cow::Owned(ty),
-            child_node.child_types().to_owned(),
-        ))
+        // tagged apart from `[DictEntry<K, V>]`, whose node keeps the entry node as its child
+        container_node(b'h', [<DictEntry<K, V>>::variant_type()], |[child_node]| {
+            let mut builder = glib::GStringBuilder::new("a");
+            builder.append(child_node.type_().as_str());
+            let ty = glib::VariantType::from_string(builder.into_string()).unwrap();
+            VariantTypeNode::new(Cow::Owned(ty), child_node.child_types().to_owned())
+        })
     }
 }
 
 impl<K: VariantType, V: VariantType> VariantType for BTreeMap<K, V> {
+    const TYPE_STR: Option<&'static str> = <HashMap<K, V>>::TYPE_STR;
+    const VALIDATES_VALUES: bool = <HashMap<K, V>>::VALIDATES_VALUES;
     fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
         <HashMap<K, V>>::variant_type()
     }
+    fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+        <HashMap<K, V>>::validate_value(variant)
+    }
 }
 
 macro_rules! tuple_impls {
     ($($len:expr => ($($n:tt $name:ident)+))+) => {
         $(
+            impl<$($name),+> TypeStrParts for ($($name,)+)
+            where
+                $($name: VariantType,)+
+            {
+                const TYPE_STR_PARTS: &'static [Option<&'static str>] =
+                    &[Some("("), $($name::TYPE_STR,)+ Some(")")];
+            }
+
             impl<$($name),+> VariantType for ($($name,)+)
             where
                 $($name: VariantType,)+
             {
+                const TYPE_STR: Option<&'static str> = JoinedTypeStr::<Self>::TYPE_STR;
+                const VALIDATES_VALUES: bool = false $(|| $name::VALIDATES_VALUES)+;
+                fn validate_value(variant: &glib::Variant) -> Result<(), crate::Error> {
+                    $($name::validate_value(&variant.child_value($n))?;)+
+                    Ok(())
+                }
                 fn variant_type() -> Cow<'static, VariantTypeNode<'static>> {
-                    Cow::Owned(VariantTypeNode::new(
-                        <Self as glib::StaticVariantType>::static_variant_type(),
-                        [$($name::variant_type()),+],
-                    ))
+                    container_node(b'(', [$($name::variant_type()),+], |children| {
+                        VariantTypeNode::new(
+                            <Self as glib::StaticVariantType>::static_variant_type(),
+                            children,
+                        )
+                    })
                 }
             }
         )+
@@ -178,3 +362,360 @@ tuple_impls! {
     15 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11 12 T12 13 T13 14 T14)
     16 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11 12 T12 13 T13 14 T14 15 T15)
 }
+
+#[doc(hidden)]
+pub mod __private {
+    //! Used by `#[derive(VariantType)]` to join type strings at compile time and to validate
+    //! values, and by the literal macros to build their values without `unsafe` at the call site.
+
+    use crate::prelude::GlibVariantExt;
+    use serde::de::{
+        self,
+        value::{StrDeserializer, U64Deserializer},
+    };
+    use std::{
+        cell::Cell,
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+        marker::PhantomData,
+    };
+
+    /// Lets `#[derive(VariantType)]` check enums with their `Deserialize` impl when there is one,
+    /// as only that knows which names it accepts, such as GLib nicks or renamed variants.
+    /// Building a unit-only enum costs nothing, and enums with data only have their variant name
+    /// read by [`variant_index`](ValidateWithDeserialize::variant_index).
+    ///
+    /// Calling `validate` on `&DeserializeProbe<T>` picks [`ValidateWithDeserialize`] when `T`
+    /// implements `Deserialize`, and falls back to [`ValidateWithoutDeserialize`] otherwise.
+    pub struct DeserializeProbe<T>(PhantomData<T>);
+
+    impl<T> DeserializeProbe<T> {
+        pub fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    impl<T> Default for DeserializeProbe<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    pub trait ValidateWithDeserialize {
+        fn validate(&self, variant: &glib::Variant) -> Result<(), crate::Error>;
+        /// Finds which of the first `len` variants the variant name `tag` deserializes into.
+        /// Fails if `Deserialize` rejects the name, and returns `None` if it doesn't read enums
+        /// through `deserialize_enum`, in which case only [`validate`](Self::validate) can tell.
+        fn variant_index(&self, tag: &str, len: usize) -> Result<Option<usize>, crate::Error>;
+    }
+
+    impl<T: serde::de::DeserializeOwned> ValidateWithDeserialize for DeserializeProbe<T> {
+        fn validate(&self, variant: &glib::Variant) -> Result<(), crate::Error> {
+            T::deserialize(variant.as_serializable()).map(drop)
+        }
+        fn variant_index(&self, tag: &str, len: usize) -> Result<Option<usize>, crate::Error> {
+            let field = match TagProbe::field::<T>(Tag::Name(tag))? {
+                Some(field) => field,
+                None => return Ok(None),
+            };
+            // an index that `Deserialize` rejects just isn't the one
+            Ok((0..len).find(|&index| {
+                let other = TagProbe::field::<T>(Tag::Index(index as u64));
+                matches!(other, Ok(Some(other)) if other == field)
+            }))
+        }
+    }
+
+    pub trait ValidateWithoutDeserialize {
+        fn validate(&self, variant: &glib::Variant) -> Result<(), crate::Error>;
+        fn variant_index(&self, tag: &str, len: usize) -> Result<Option<usize>, crate::Error>;
+    }
+
+    impl<T> ValidateWithoutDeserialize for &DeserializeProbe<T> {
+        fn validate(&self, _variant: &glib::Variant) -> Result<(), crate::Error> {
+            Ok(())
+        }
+        fn variant_index(&self, _tag: &str, _len: usize) -> Result<Option<usize>, crate::Error> {
+            Ok(None)
+        }
+    }
+
+    /// A variant identifier, as a name or as an index.
+    enum Tag<'a> {
+        Name(&'a str),
+        Index(u64),
+    }
+
+    /// How far a [`TagProbe`] got.
+    #[derive(Clone, Copy)]
+    enum TagState {
+        /// `Deserialize` never asked for an enum.
+        Unread,
+        /// `Deserialize` rejected the identifier.
+        Rejected,
+        /// The identifier deserialized into the field with this discriminant hash.
+        Field(u64),
+    }
+
+    /// Deserializes an enum with only its variant identifier, to see which variant a name maps to
+    /// without reading the payload.
+    ///
+    /// The derived `Deserialize` reads identifiers into a private field enum. Its values can't be
+    /// named, so they are compared by the hash of their discriminant.
+    struct TagProbe<'a> {
+        tag: Tag<'a>,
+        state: &'a Cell<TagState>,
+    }
+
+    impl TagProbe<'_> {
+        fn field<T: serde::de::DeserializeOwned>(tag: Tag) -> Result<Option<u64>, crate::Error> {
+            let state = Cell::new(TagState::Unread);
+            let res = T::deserialize(TagProbe { tag, state: &state });
+            match (state.get(), res) {
+                (TagState::Field(field), _) => Ok(Some(field)),
+                (TagState::Rejected, Err(e)) => Err(e),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    impl<'de> de::Deserializer<'de> for TagProbe<'_> {
+        type Error = crate::Error;
+
+        fn deserialize_any<V: de::Visitor<'de>>(
+            self,
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom(
+                "only enums can be probed for their variant index",
+            ))
+        }
+
+        fn deserialize_enum<V: de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            visitor.visit_enum(self)
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct identifier ignored_any
+        }
+    }
+
+    impl<'de> de::EnumAccess<'de> for TagProbe<'_> {
+        type Error = crate::Error;
+        type Variant = Self;
+
+        fn variant_seed<V: de::DeserializeSeed<'de>>(
+            self,
+            seed: V,
+        ) -> Result<(V::Value, Self::Variant), Self::Error> {
+            self.state.set(TagState::Rejected);
+            let field = match self.tag {
+                Tag::Name(name) => seed.deserialize(StrDeserializer::<crate::Error>::new(name))?,
+                Tag::Index(index) => {
+                    seed.deserialize(U64Deserializer::<crate::Error>::new(index))?
+                }
+            };
+            let mut hasher = DefaultHasher::new();
+            std::mem::discriminant(&field).hash(&mut hasher);
+            self.state.set(TagState::Field(hasher.finish()));
+            Ok((field, self))
+        }
+    }
+
+    impl<'de> de::VariantAccess<'de> for TagProbe<'_> {
+        type Error = crate::Error;
+
+        fn unit_variant(self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+            self,
+            _seed: T,
+        ) -> Result<T::Value, Self::Error> {
+            Err(de::Error::custom("the payload is not probed"))
+        }
+
+        fn tuple_variant<V: de::Visitor<'de>>(
+            self,
+            _len: usize,
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("the payload is not probed"))
+        }
+
+        fn struct_variant<V: de::Visitor<'de>>(
+            self,
+            _fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("the payload is not probed"))
+        }
+    }
+
+    /// Used by `variant_type!`, which checks `s` at compile time.
+    pub const fn literal_variant_ty(s: &'static str) -> &'static glib::VariantTy {
+        unsafe { glib::VariantTy::from_str_unchecked(s) }
+    }
+
+    /// Used by `signature!`, which checks `s` at compile time.
+    pub const fn literal_signature(s: &'static str) -> crate::SignatureRef<'static> {
+        unsafe { crate::SignatureRef::from_str_unchecked(s) }
+    }
+
+    /// Used by `object_path!`, which checks `s` at compile time.
+    pub const fn literal_object_path(s: &'static str) -> crate::ObjectPathRef<'static> {
+        unsafe { crate::ObjectPathRef::from_str_unchecked(s) }
+    }
+
+    /// Checks that `variant` is a tuple with `len` children, as the payload of an enum variant
+    /// with `len` fields.
+    pub fn check_tuple(variant: &glib::Variant, len: usize) -> Result<(), crate::Error> {
+        if !variant.type_().is_tuple() {
+            return Err(crate::Error::UnsupportedType(variant.type_().to_owned()));
+        }
+        if variant.n_children() != len {
+            return Err(crate::Error::LengthMismatch {
+                actual: variant.n_children(),
+                expected: len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Type strings joined at compile time are limited to this length, the same limit D-Bus
+    /// puts on signatures. Longer ones are left to `static_variant_type`.
+    pub const MAX_TYPE_STR_LEN: usize = 255;
+
+    /// Containers may be nested this deep, as in GLib.
+    const MAX_DEPTH: usize = 128;
+
+    /// The parts of a type string that [`JoinedTypeStr`] joins at compile time, such as `"m"`
+    /// followed by the type string of the child for `Option<T>`.
+    pub trait TypeStrParts {
+        const TYPE_STR_PARTS: &'static [Option<&'static str>];
+    }
+
+    /// Holds the type string joined from the [`TypeStrParts`] of `T`.
+    pub struct JoinedTypeStr<T: ?Sized>(PhantomData<T>);
+
+    impl<T: TypeStrParts + ?Sized> JoinedTypeStr<T> {
+        const BUF: &'static TypeStrBuf = &TypeStrBuf::join(T::TYPE_STR_PARTS);
+
+        /// The joined type string, or `None` if a part is unknown or the result is too long.
+        ///
+        /// Fails to compile if the parts don't join into a single complete type, so the result
+        /// can be passed to `VariantTy::from_str_unchecked`.
+        pub const TYPE_STR: Option<&'static str> = Self::BUF.as_str();
+    }
+
+    struct TypeStrBuf {
+        bytes: [u8; MAX_TYPE_STR_LEN],
+        len: Option<usize>,
+    }
+
+    impl TypeStrBuf {
+        const fn join(parts: &[Option<&str>]) -> Self {
+            let mut buf = Self {
+                bytes: [0; MAX_TYPE_STR_LEN],
+                len: None,
+            };
+            let mut len = 0;
+            let mut i = 0;
+            while i < parts.len() {
+                let part = match parts[i] {
+                    Some(part) => part.as_bytes(),
+                    None => return buf,
+                };
+                if part.len() > MAX_TYPE_STR_LEN - len {
+                    return buf;
+                }
+                let mut j = 0;
+                while j < part.len() {
+                    buf.bytes[len] = part[j];
+                    len += 1;
+                    j += 1;
+                }
+                i += 1;
+            }
+            match scan_type(&buf.bytes, len, 0, 0) {
+                Some(end) if end == len => {}
+                _ => panic!("VariantType::TYPE_STR parts don't join into a valid type string"),
+            }
+            buf.len = Some(len);
+            buf
+        }
+
+        const fn as_str(&self) -> Option<&str> {
+            let len = match self.len {
+                Some(len) => len,
+                None => return None,
+            };
+            match std::str::from_utf8(self.bytes.split_at(len).0) {
+                Ok(s) => Some(s),
+                Err(_) => None,
+            }
+        }
+    }
+
+    /// Returns the end of the complete type starting at `pos` in the first `len` bytes of `s`,
+    /// following the rules of `g_variant_type_string_scan`.
+    const fn scan_type(s: &[u8], len: usize, pos: usize, depth: usize) -> Option<usize> {
+        if pos >= len {
+            return None;
+        }
+        let container = matches!(s[pos], b'a' | b'm' | b'(' | b'{');
+        if container && depth >= MAX_DEPTH {
+            return None;
+        }
+        match s[pos] {
+            b'a' | b'm' => scan_type(s, len, pos + 1, depth + 1),
+            b'(' => {
+                let mut end = pos + 1;
+                loop {
+                    if end >= len {
+                        return None;
+                    }
+                    if s[end] == b')' {
+                        return Some(end + 1);
+                    }
+                    end = match scan_type(s, len, end, depth + 1) {
+                        Some(end) => end,
+                        None => return None,
+                    };
+                }
+            }
+            b'{' => {
+                if pos + 1 >= len || !is_basic(s[pos + 1]) {
+                    return None;
+                }
+                match scan_type(s, len, pos + 2, depth + 1) {
+                    Some(end) if end < len && s[end] == b'}' => Some(end + 1),
+                    _ => None,
+                }
+            }
+            b'v' | b'r' | b'*' => Some(pos + 1),
+            b if is_basic(b) => Some(pos + 1),
+            _ => None,
+        }
+    }
+
+    const fn is_basic(b: u8) -> bool {
+        let basic = b"bynqiuxthdsog?";
+        let mut i = 0;
+        while i < basic.len() {
+            if basic[i] == b {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+}
@@ -0,0 +1,537 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+use glib::{ToVariant, VariantTy};
+use glib_serde::{
+    from_bytes, from_bytes_with_type, prelude::*, to_bytes, to_bytes_with_type, to_variant,
+    to_variant_via_bytes, Variant, VariantBuilder, VariantTypeNode,
+};
+use serde::{
+    de::{self, Deserialize, Deserializer, Visitor},
+    ser::{Serialize, SerializeTuple, Serializer},
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
+
+/// Xorshift generator, so failures can be reproduced from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+    fn string(&mut self, alphabet: &[u8]) -> String {
+        let len = if self.below(8) == 0 {
+            self.below(400)
+        } else {
+            self.below(8)
+        };
+        (0..len)
+            .map(|_| alphabet[self.below(alphabet.len() as u64) as usize] as char)
+            .collect()
+    }
+}
+
+/// Value of a type that is only known at runtime.
+#[derive(Debug)]
+enum Value {
+    Bool(bool),
+    Byte(u8),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Variant(Variant),
+    Maybe(Option<Box<Value>>),
+    Array(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    Tuple(Vec<Value>),
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Bool(v) => serializer.serialize_bool(*v),
+            Self::Byte(v) => serializer.serialize_u8(*v),
+            Self::Int16(v) => serializer.serialize_i16(*v),
+            Self::UInt16(v) => serializer.serialize_u16(*v),
+            Self::Int32(v) => serializer.serialize_i32(*v),
+            Self::UInt32(v) => serializer.serialize_u32(*v),
+            Self::Int64(v) => serializer.serialize_i64(*v),
+            Self::UInt64(v) => serializer.serialize_u64(*v),
+            Self::Double(v) => serializer.serialize_f64(*v),
+            Self::Str(v) => serializer.serialize_str(v),
+            Self::Bytes(v) => serializer.serialize_bytes(v),
+            Self::Variant(v) => v.serialize(serializer),
+            Self::Maybe(None) => serializer.serialize_none(),
+            Self::Maybe(Some(v)) => serializer.serialize_some(v),
+            Self::Array(v) => serializer.collect_seq(v),
+            Self::Dict(v) => serializer.collect_map(v.iter().map(|(k, v)| (k, v))),
+            Self::Tuple(v) => {
+                let mut tuple = serializer.serialize_tuple(v.len())?;
+                for child in v {
+                    tuple.serialize_element(child)?;
+                }
+                tuple.end()
+            }
+        }
+    }
+}
+
+/// Reads any value, unboxing variants and reading tuples as arrays.
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("any value")
+    }
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+    fn visit_u8<E>(self, v: u8) -> Result<Value, E> {
+        Ok(Value::Byte(v))
+    }
+    fn visit_i16<E>(self, v: i16) -> Result<Value, E> {
+        Ok(Value::Int16(v))
+    }
+    fn visit_u16<E>(self, v: u16) -> Result<Value, E> {
+        Ok(Value::UInt16(v))
+    }
+    fn visit_i32<E>(self, v: i32) -> Result<Value, E> {
+        Ok(Value::Int32(v))
+    }
+    fn visit_u32<E>(self, v: u32) -> Result<Value, E> {
+        Ok(Value::UInt32(v))
+    }
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int64(v))
+    }
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::UInt64(v))
+    }
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Double(v))
+    }
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::Str(v.to_owned()))
+    }
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_owned()))
+    }
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Maybe(None))
+    }
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Ok(Value::Maybe(Some(Box::new(value))))
+    }
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Tuple(Vec::new()))
+    }
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Dict(entries))
+    }
+}
+
+/// Checks that `value` holds the same data as `expected`.
+fn check_value(value: &Value, expected: &glib::Variant) {
+    if let Some(inner) = expected.as_variant() {
+        return check_value(value, &inner);
+    }
+    let ty = expected.type_();
+    match value {
+        Value::Bool(v) => assert_eq!(Some(*v), expected.get(), "{}", ty),
+        Value::Byte(v) => assert_eq!(Some(*v), expected.get(), "{}", ty),
+        Value::Int16(v) => assert_eq!(Some(*v), expected.get(), "{}", ty),
+        Value::UInt16(v) => assert_eq!(Some(*v), expected.get(), "{}", ty),
+        Value::Int32(v) => assert_eq!(Some(*v), expected.get(), "{}", ty),
+        Value::UInt32(v) => assert_eq!(Some(*v), expected.get(), "{}", ty),
+        Value::Int64(v) => assert_eq!(Some(*v), expected.get(), "{}", ty),
+        Value::UInt64(v) => assert_eq!(Some(*v), expected.get(), "{}", ty),
+        Value::Double(v) => assert_eq!(
+            Some(v.to_bits()),
+            expected.get::<f64>().map(f64::to_bits),
+            "{}",
+            ty
+        ),
+        Value::Str(v) => assert_eq!(Some(v.as_str()), expected.str(), "{}", ty),
+        Value::Bytes(v) => assert_eq!(&v[..], expected.fixed_array::<u8>().unwrap(), "{}", ty),
+        Value::Variant(_) => unreachable!(),
+        Value::Maybe(v) => match (v, expected.maybe().unwrap()) {
+            (None, None) => {}
+            (Some(v), Some(child)) => check_value(v, &child),
+            (v, child) => panic!("{:?} differs from {:?}", v, child),
+        },
+        Value::Array(v) | Value::Tuple(v) => {
+            assert_eq!(v.len(), expected.n_children(), "{}", ty);
+            for (i, child) in v.iter().enumerate() {
+                check_value(child, &expected.child_value(i));
+            }
+        }
+        Value::Dict(v) => {
+            assert_eq!(v.len(), expected.n_children(), "{}", ty);
+            for (i, (key, value)) in v.iter().enumerate() {
+                let entry = expected.child_value(i);
+                check_value(key, &entry.child_value(0));
+                check_value(value, &entry.child_value(1));
+            }
+        }
+    }
+}
+
+fn random_type(rng: &mut Rng, depth: u32) -> String {
+    const BASIC: &[u8] = b"ybnqiuxtdsog";
+    let basic = |rng: &mut Rng| -> String {
+        (BASIC[rng.below(BASIC.len() as u64) as usize] as char).into()
+    };
+    if depth == 0 {
+        return basic(rng);
+    }
+    match rng.below(7) {
+        0 => format!("m{}", random_type(rng, depth - 1)),
+        1 => format!("a{}", random_type(rng, depth - 1)),
+        2 => {
+            let children: String = (0..rng.below(4))
+                .map(|_| random_type(rng, depth - 1))
+                .collect();
+            format!("({})", children)
+        }
+        3 => format!("a{{{}{}}}", basic(rng), random_type(rng, depth - 1)),
+        4 => String::from("v"),
+        _ => basic(rng),
+    }
+}
+
+/// Returns a random value of type `ty`, and the same value built with GLib.
+fn random_value(rng: &mut Rng, ty: &VariantTy, depth: u32) -> (Value, glib::Variant) {
+    match ty.as_str().as_bytes()[0] {
+        b'b' => {
+            let v = rng.below(2) == 1;
+            (Value::Bool(v), v.to_variant())
+        }
+        b'y' => {
+            let v = rng.next() as u8;
+            (Value::Byte(v), v.to_variant())
+        }
+        b'n' => {
+            let v = rng.next() as i16;
+            (Value::Int16(v), v.to_variant())
+        }
+        b'q' => {
+            let v = rng.next() as u16;
+            (Value::UInt16(v), v.to_variant())
+        }
+        b'i' => {
+            let v = rng.next() as i32;
+            (Value::Int32(v), v.to_variant())
+        }
+        b'u' => {
+            let v = rng.next() as u32;
+            (Value::UInt32(v), v.to_variant())
+        }
+        b'x' => {
+            let v = rng.next() as i64;
+            (Value::Int64(v), v.to_variant())
+        }
+        b't' => {
+            let v = rng.next();
+            (Value::UInt64(v), v.to_variant())
+        }
+        b'd' => {
+            let v = rng.next() as i32 as f64 / 8.0;
+            (Value::Double(v), v.to_variant())
+        }
+        b's' => {
+            let v = rng.string(b"abc xyz_");
+            let variant = v.to_variant();
+            (Value::Str(v), variant)
+        }
+        b'o' => {
+            let v = (0..rng.below(4))
+                .map(|i| format!("/p{}", i))
+                .collect::<String>();
+            let v = if v.is_empty() { String::from("/") } else { v };
+            let variant = glib_serde::ObjectPath::new(v.as_str())
+                .unwrap()
+                .to_variant();
+            (Value::Str(v), variant)
+        }
+        b'g' => {
+            let v: String = (0..rng.below(4))
+                .map(|_| b"ybnqiuxtdsog"[rng.below(12) as usize] as char)
+                .collect();
+            let variant = glib_serde::Signature::new(v.as_str()).unwrap().to_variant();
+            (Value::Str(v), variant)
+        }
+        b'v' => {
+            let inner_ty = glib::VariantType::new(&random_type(rng, depth)).unwrap();
+            let (_, inner) = random_value(rng, &inner_ty, depth.saturating_sub(1));
+            let variant = glib::Variant::from_variant(&inner);
+            (Value::Variant(Variant::from(inner)), variant)
+        }
+        b'm' => {
+            if rng.below(2) == 0 {
+                (Value::Maybe(None), glib::Variant::from_none(ty.element()))
+            } else {
+                let (value, variant) = random_value(rng, ty.element(), depth);
+                (
+                    Value::Maybe(Some(Box::new(value))),
+                    glib::Variant::from_some(&variant),
+                )
+            }
+        }
+        b'a' => {
+            let len = if ty.element().is_basic() && rng.below(8) == 0 {
+                rng.below(200)
+            } else {
+                rng.below(4)
+            };
+            let mut builder = VariantBuilder::new(ty);
+            let mut values = Vec::new();
+            for _ in 0..len {
+                let (value, variant) = random_value(rng, ty.element(), depth);
+                builder.add_value(&variant).unwrap();
+                values.push(value);
+            }
+            let value = if ty.element().is_dict_entry() {
+                Value::Dict(
+                    values
+                        .into_iter()
+                        .map(|entry| match entry {
+                            Value::Tuple(mut kv) => {
+                                let value = kv.pop().unwrap();
+                                (kv.pop().unwrap(), value)
+                            }
+                            _ => unreachable!(),
+                        })
+                        .collect(),
+                )
+            } else {
+                Value::Array(values)
+            };
+            (value, builder.end().unwrap())
+        }
+        b'(' | b'{' => {
+            let mut builder = VariantBuilder::new(ty);
+            let mut values = Vec::new();
+            let mut child = ty.first();
+            while let Some(child_ty) = child {
+                let (value, variant) = random_value(rng, child_ty, depth);
+                builder.add_value(&variant).unwrap();
+                values.push(value);
+                child = child_ty.next();
+            }
+            (Value::Tuple(values), builder.end().unwrap())
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn encode_random_values() {
+    let mut rng = Rng(0x2545f4914f6cdd1d);
+    for _ in 0..2000 {
+        let ty = glib::VariantType::new(&random_type(&mut rng, 3)).unwrap();
+        let (value, expected) = random_value(&mut rng, &ty, 2);
+        let node = VariantTypeNode::new(Cow::Borrowed(&*ty), []);
+        let data = to_bytes_with_type(&value, &node).unwrap();
+        assert_eq!(
+            data,
+            expected.data(),
+            "Data for {} differs from {}",
+            ty,
+            expected.as_serializable()
+        );
+    }
+}
+
+#[derive(Debug, PartialEq, glib_serde::VariantType, serde::Serialize, serde::Deserialize)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rect(i32, i32, u32, u32),
+    Path {
+        points: Vec<(f64, f64)>,
+        closed: bool,
+    },
+}
+
+#[derive(Debug, PartialEq, glib_serde::VariantType, serde::Serialize, serde::Deserialize)]
+struct Drawing {
+    name: String,
+    layers: BTreeMap<String, Vec<Shape>>,
+    tags: Option<Vec<String>>,
+    extra: HashMap<String, Variant>,
+}
+
+#[test]
+fn encode_structs() {
+    let drawing = Drawing {
+        name: String::from("test"),
+        layers: BTreeMap::from([
+            (String::from("bg"), vec![Shape::Empty, Shape::Circle(2.5)]),
+            (
+                String::from("fg"),
+                vec![
+                    Shape::Rect(-1, 2, 3, 4),
+                    Shape::Path {
+                        points: vec![(0.0, 1.0), (2.0, 3.0)],
+                        closed: true,
+                    },
+                ],
+            ),
+        ]),
+        tags: Some(vec![String::from("a"), String::new()]),
+        extra: HashMap::from([(String::from("k"), Variant::from(7u8.to_variant()))]),
+    };
+    let expected = to_variant(&drawing).unwrap();
+    assert_eq!(to_bytes(&drawing).unwrap(), expected.data());
+    let variant = to_variant_via_bytes(&drawing).unwrap();
+    assert_eq!(variant.type_(), expected.type_());
+    assert_eq!(variant, expected);
+
+    // wide framing offsets
+    let strings: Vec<String> = (0..10000).map(|i| i.to_string()).collect();
+    assert_eq!(
+        to_bytes(&strings).unwrap(),
+        to_variant(&strings).unwrap().data()
+    );
+
+    let err = to_bytes_with_type(
+        &1u32,
+        &VariantTypeNode::new(Cow::Borrowed(VariantTy::INT32), []),
+    )
+    .unwrap_err();
+    assert!(matches!(err, glib_serde::Error::Mismatch(_)));
+}
+
+/// Changes `data` so that it is usually no longer in normal form.
+fn mangle(rng: &mut Rng, data: &mut Vec<u8>) {
+    match rng.below(5) {
+        0 => {}
+        1 if !data.is_empty() => {
+            let i = rng.below(data.len() as u64) as usize;
+            data[i] = rng.next() as u8;
+        }
+        2 if !data.is_empty() => {
+            let len = rng.below(data.len() as u64) as usize;
+            data.truncate(len);
+        }
+        3 => data.extend((0..rng.below(4)).map(|_| rng.next() as u8)),
+        _ => {
+            let len = data.len();
+            data.clear();
+            data.extend((0..len).map(|_| rng.below(4) as u8));
+        }
+    }
+}
+
+#[test]
+fn decode_random_values() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    for _ in 0..2000 {
+        let ty = glib::VariantType::new(&random_type(&mut rng, 3)).unwrap();
+        let (_, variant) = random_value(&mut rng, &ty, 2);
+        let mut data = variant.data().to_vec();
+        mangle(&mut rng, &mut data);
+        let expected =
+            glib::Variant::from_bytes_with_type(&glib::Bytes::from(&data[..]), &ty).normal_form();
+        let node = VariantTypeNode::new(Cow::Borrowed(&*ty), []);
+        let value: Value = from_bytes_with_type(&data, &node).unwrap();
+        check_value(&value, &expected);
+    }
+}
+
+#[test]
+fn decode_structs() {
+    let drawing = Drawing {
+        name: String::from("test"),
+        layers: BTreeMap::from([(
+            String::from("fg"),
+            vec![
+                Shape::Empty,
+                Shape::Rect(-1, 2, 3, 4),
+                Shape::Path {
+                    points: vec![(0.0, 1.0), (2.0, 3.0)],
+                    closed: true,
+                },
+            ],
+        )]),
+        tags: None,
+        extra: HashMap::from([(String::from("k"), Variant::from("v".to_variant()))]),
+    };
+    let data = to_bytes(&drawing).unwrap();
+    assert_eq!(from_bytes::<Drawing>(&data).unwrap(), drawing);
+
+    // strings and bytes are borrowed
+    let data = to_bytes(&("abc", &b"xyz"[..])).unwrap();
+    let (s, bytes): (&str, &[u8]) = from_bytes(&data).unwrap();
+    assert_eq!((s, bytes), ("abc", &b"xyz"[..]));
+    assert!(data.as_ptr_range().contains(&s.as_ptr()));
+    assert!(data.as_ptr_range().contains(&bytes.as_ptr()));
+
+    // non-normal data reads as defaults
+    assert_eq!(from_bytes::<u32>(&[1, 2]).unwrap(), 0);
+    assert_eq!(from_bytes::<(u8, u32)>(&[1, 0, 0]).unwrap(), (0, 0));
+    assert_eq!(from_bytes::<String>(b"abc").unwrap(), "");
+    assert_eq!(from_bytes::<String>(b"a\0c\0").unwrap(), "");
+    let node = VariantTypeNode::new(Cow::Borrowed(VariantTy::OBJECT_PATH), []);
+    assert_eq!(from_bytes_with_type::<&str>(b"x/\0", &node).unwrap(), "/");
+    let node = VariantTypeNode::new(Cow::Borrowed(VariantTy::STRING), []);
+    assert_eq!(from_bytes_with_type::<char>(b"x\0", &node).unwrap(), 'x');
+    from_bytes_with_type::<char>(b"xy\0", &node).unwrap_err();
+    assert_eq!(
+        from_bytes::<Vec<String>>(b"a\0b\0\x02\xff").unwrap(),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        from_bytes::<Vec<String>>(b"a\0b\0\x05\x04").unwrap(),
+        vec![String::new(), String::new()]
+    );
+    // a child ending inside the framing offsets is out of bounds, like in GLib
+    let data = b"ab\0\x04";
+    let ty = VariantTy::new("(ays)").unwrap();
+    let expected = glib::Variant::from_bytes_with_type(&glib::Bytes::from(&data[..]), ty);
+    let value = (Vec::<u8>::new(), String::new());
+    assert_eq!(expected.get::<(Vec<u8>, String)>().unwrap(), value);
+    assert_eq!(from_bytes::<(Vec<u8>, String)>(data).unwrap(), value);
+    assert_eq!(from_bytes::<Option<u16>>(&[1]).unwrap(), None);
+    let variant = from_bytes::<Variant>(b"\x01\0\0\0\0x").unwrap();
+    assert_eq!(variant.type_(), VariantTy::UNIT);
+
+    let node = VariantTypeNode::new(Cow::Borrowed(VariantTy::UINT32), []);
+    let err = from_bytes_with_type::<String>(&[0; 4], &node).unwrap_err();
+    assert!(matches!(err, glib_serde::Error::StrMismatch(_)));
+}
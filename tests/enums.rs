@@ -38,9 +38,9 @@ fn deserialize_enum() {
     assert_eq!(e, MyEnum::ValWithCustomNameAndNick);
 
     let err = serde_json::from_str::<'_, MyEnum>("\"nothing\"").unwrap_err();
-    assert!(err
-        .to_string()
-        .contains("expected a valid enum value for MyEnum"));
+    assert!(err.to_string().contains(
+        "Invalid enum value `nothing`, expected one of `val`, `val-with-custom-name`, `other`"
+    ));
 }
 
 #[derive(glib_serde::FlagsSerialize, glib_serde::FlagsDeserialize)]
@@ -90,7 +90,7 @@ fn deserialize_flags() {
     let err = serde_json::from_str::<'_, MyFlags>("\"nick-a|b|bad|c\"").unwrap_err();
     assert!(err
         .to_string()
-        .contains("expected a valid flags value for MyFlags"));
+        .contains("Invalid flag value `bad`, expected one of `nick-a`, `b`, `c`"));
 }
 
 #[derive(
@@ -152,3 +152,342 @@ fn deserialize_repr() {
     let f: MyFlags2 = serde_json::from_str("3").unwrap();
     assert_eq!(f, MyFlags2::AB);
 }
+
+#[test]
+fn dyn_values() {
+    use glib::{StaticType, ToVariant};
+    use glib_serde::{DynEnumValue, DynFlagsValue, GType};
+
+    let ty = MyEnum::static_type();
+    let json = serde_json::to_string(&GType::from(ty)).unwrap();
+    assert_eq!(json, "\"MyEnum\"");
+    let value: GType = serde_json::from_str(&json).unwrap();
+    assert_eq!(*value, ty);
+    let err = serde_json::from_str::<'_, GType>("\"NotARegisteredType\"").unwrap_err();
+    assert!(err.to_string().contains("expected a registered GType name"));
+
+    let value = DynEnumValue::new(ty, 2);
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#"["MyEnum","other"]"#);
+    let value2: DynEnumValue = serde_json::from_str(&json).unwrap();
+    assert_eq!(value, value2);
+    let variant = glib_serde::to_variant(&value).unwrap();
+    assert_eq!(variant.type_(), "(ss)");
+    assert_eq!(variant.to_string(), "('MyEnum', 'other')");
+    let value2: DynEnumValue = glib_serde::from_variant(&variant).unwrap();
+    assert_eq!(value, value2);
+
+    let ty = MyFlags::static_type();
+    let value = DynFlagsValue::new(ty, (MyFlags::A | MyFlags::C).bits());
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#"["MyFlags","nick-a|c"]"#);
+    let value2: DynFlagsValue = serde_json::from_str(&json).unwrap();
+    assert_eq!(value, value2);
+    let variant = glib_serde::to_variant(&value).unwrap();
+    assert_eq!(variant.to_string(), "('MyFlags', 'nick-a|c')");
+
+    let err = serde_json::from_str::<'_, DynEnumValue>(r#"["MyFlags","nick-a"]"#).unwrap_err();
+    assert!(err.to_string().contains("No such enum MyFlags"));
+    let err = serde_json::from_str::<'_, DynEnumValue>(r#"["NoSuchType","val"]"#).unwrap_err();
+    assert!(err.to_string().contains("expected a registered GType name"));
+
+    // types that aren't enums or flags fall back to the integer, and read it back
+    let value = DynEnumValue::new(glib::Type::STRING, 3);
+    assert_eq!(value.to_string(), "3");
+    assert_eq!(value.to_variant().to_string(), "('gchararray', '3')");
+    assert!(value.try_to_variant().is_err());
+    assert_eq!(value.to_variant().get::<DynEnumValue>(), Some(value));
+    let variant = glib_serde::to_variant(&value).unwrap();
+    assert_eq!(variant, value.to_variant());
+    assert_eq!(
+        glib_serde::from_variant::<DynEnumValue>(&variant).unwrap(),
+        value
+    );
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#"["gchararray","3"]"#);
+    assert_eq!(serde_json::from_str::<DynEnumValue>(&json).unwrap(), value);
+    let value = DynFlagsValue::new(glib::Type::STRING, 0x12);
+    assert_eq!(value.to_string(), "0x12");
+    assert_eq!(value.to_variant().to_string(), "('gchararray', '0x12')");
+    assert_eq!(value.to_variant().get::<DynFlagsValue>(), Some(value));
+    let variant = glib_serde::to_variant(&value).unwrap();
+    assert_eq!(
+        glib_serde::from_variant::<DynFlagsValue>(&variant).unwrap(),
+        value
+    );
+    let value = DynFlagsValue::new(glib::Type::STRING, 0);
+    assert_eq!(value.to_string(), "");
+    assert_eq!(value.to_variant().get::<DynFlagsValue>(), Some(value));
+}
+
+#[test]
+fn unknown_values() {
+    use glib::ToVariant;
+    use glib_serde::{EnumReprValue, EnumValue, FlagsReprValue, FlagsValue};
+
+    let value = EnumValue::<MyEnum>::from_raw(42);
+    assert_eq!(value.try_value(), None);
+    assert_eq!(value.to_string(), "42");
+    assert_eq!(value.to_variant().to_string(), "'42'");
+    assert!(value.try_to_variant().is_err());
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, "\"42\"");
+    let value: EnumValue<MyEnum> = serde_json::from_str(&json).unwrap();
+    assert_eq!(value.raw_value(), 42);
+    let value: EnumValue<MyEnum> = "other".parse().unwrap();
+    assert_eq!(value.value(), MyEnum::ValWithCustomNameAndNick);
+    assert_eq!(value.try_value(), Some(MyEnum::ValWithCustomNameAndNick));
+    assert_eq!(value.try_to_variant().unwrap().to_string(), "'other'");
+
+    let value = EnumReprValue::<MyEnum>::from_raw(42);
+    assert_eq!(value.try_value(), None);
+    assert_eq!(value.to_variant().to_string(), "42");
+    assert!(value.try_to_variant().is_err());
+    let variant = glib_serde::to_variant(&value).unwrap();
+    let value: EnumReprValue<MyEnum> = glib_serde::from_variant(&variant).unwrap();
+    assert_eq!(value.raw_value(), 42);
+
+    let value = FlagsValue::<MyFlags>::from_raw(0b10001);
+    assert_eq!(value.try_value(), None);
+    assert_eq!(value.to_string(), "nick-a|0x10");
+    assert!(value.try_to_variant().is_err());
+    let variant = glib_serde::to_variant(&value).unwrap();
+    assert_eq!(variant.to_string(), "'nick-a|0x10'");
+    let value: FlagsValue<MyFlags> = glib_serde::from_variant(&variant).unwrap();
+    assert_eq!(value.raw_value(), 0b10001);
+    let value: FlagsValue<MyFlags> = "b|0x100|c".parse().unwrap();
+    assert_eq!(value.raw_value(), 0b100000110);
+
+    let value = FlagsReprValue::<MyFlags>::from_raw(0b10001);
+    assert_eq!(value.try_value(), None);
+    assert_eq!(value.to_variant().to_string(), "17");
+    assert!(value.try_to_variant().is_err());
+    assert!(FlagsReprValue::<MyFlags>::from_raw(0b101)
+        .try_to_variant()
+        .is_ok());
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    glib::Enum,
+    glib_serde::EnumSerialize,
+    glib_serde::EnumDeserialize,
+)]
+#[enum_type(name = "MyEnum3")]
+enum MyEnum3 {
+    Val,
+    #[glib_serde(other)]
+    Unknown,
+}
+
+#[test]
+fn deserialize_other() {
+    let e: MyEnum3 = serde_json::from_str("\"val\"").unwrap();
+    assert_eq!(e, MyEnum3::Val);
+
+    let e: MyEnum3 = serde_json::from_str("\"0\"").unwrap();
+    assert_eq!(e, MyEnum3::Val);
+
+    let e: MyEnum3 = serde_json::from_str("\"42\"").unwrap();
+    assert_eq!(e, MyEnum3::Unknown);
+
+    let e: MyEnum3 = serde_json::from_str("\"from-the-future\"").unwrap();
+    assert_eq!(e, MyEnum3::Unknown);
+
+    let err = serde_json::from_str::<'_, MyEnum>("\"42\"").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected a valid enum value for MyEnum"));
+}
+
+#[derive(glib_serde::VariantType, glib_serde::FlagsSerialize, glib_serde::FlagsDeserialize)]
+#[glib::flags(name = "MyFlags4")]
+#[glib_serde_repr(array)]
+enum MyFlags4 {
+    #[flags_value(name = "Flag A", nick = "nick-a")]
+    A = 0b00000001,
+    #[flags_value(name = "Flag B")]
+    B = 0b00000010,
+    C = 0b00000100,
+}
+
+#[test]
+fn flags_array() {
+    use glib::ToVariant;
+    use glib_serde::FlagsArrayValue;
+
+    let json = serde_json::to_string(&MyFlags4::empty()).unwrap();
+    assert_eq!(json, "[]");
+
+    let json = serde_json::to_string(&(MyFlags4::A | MyFlags4::C)).unwrap();
+    assert_eq!(json, r#"["nick-a","c"]"#);
+
+    let f: MyFlags4 = serde_json::from_str(r#"["b","nick-a"]"#).unwrap();
+    assert_eq!(f, MyFlags4::A | MyFlags4::B);
+
+    let err = serde_json::from_str::<'_, MyFlags4>(r#"["b","bad"]"#).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Invalid flag value `bad`, expected one of `nick-a`, `b`, `c`"));
+    assert!(serde_json::from_str::<'_, MyFlags4>(r#"["b|nick-a"]"#).is_err());
+
+    let variant = glib_serde::to_variant(&(MyFlags4::B | MyFlags4::C)).unwrap();
+    assert_eq!(variant.type_(), "as");
+    assert_eq!(variant.to_string(), "['b', 'c']");
+    let f: MyFlags4 = glib_serde::from_variant(&variant).unwrap();
+    assert_eq!(f, MyFlags4::B | MyFlags4::C);
+
+    let value = FlagsArrayValue::from(MyFlags::A | MyFlags::B);
+    assert_eq!(value.to_variant().to_string(), "['nick-a', 'b']");
+    let variant = glib_serde::to_variant(&value).unwrap();
+    assert_eq!(variant.type_(), "as");
+    let value: FlagsArrayValue<MyFlags> = glib_serde::from_variant(&variant).unwrap();
+    assert_eq!(value.value(), MyFlags::AB);
+
+    let value = FlagsArrayValue::<MyFlags>::from_raw(0b10100);
+    assert_eq!(value.to_variant().to_string(), "['c', '0x10']");
+    let value: FlagsArrayValue<MyFlags> = glib_serde::from_variant(&value.to_variant()).unwrap();
+    assert_eq!(value.raw_value(), 0b10100);
+    let value = FlagsArrayValue::<MyFlags>::from_strs(["nick-a", "b", "0x10"]).unwrap();
+    assert_eq!(value.raw_value(), 0b10011);
+    assert!(FlagsArrayValue::<MyFlags>::from_strs(["nick-a|b"]).is_err());
+}
+
+#[test]
+fn lenient_parsing() {
+    use glib_serde::{EnumValue, FlagsArrayValue, FlagsValue};
+
+    let value = EnumValue::<MyEnum>::from_str_lenient("My Val", false).unwrap();
+    assert_eq!(value.value(), MyEnum::ValWithCustomName);
+    let value = EnumValue::<MyEnum>::from_str_lenient("2", false).unwrap();
+    assert_eq!(value.value(), MyEnum::ValWithCustomNameAndNick);
+    assert!(EnumValue::<MyEnum>::from_str_lenient("OTHER", false).is_err());
+    let value = EnumValue::<MyEnum>::from_str_lenient("OTHER", true).unwrap();
+    assert_eq!(value.value(), MyEnum::ValWithCustomNameAndNick);
+    let value = EnumValue::<MyEnum>::from_str_lenient("my other val", true).unwrap();
+    assert_eq!(value.value(), MyEnum::ValWithCustomNameAndNick);
+
+    let err = EnumValue::<MyEnum>::from_str_lenient("bogus", true).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid enum value `bogus`, expected one of `val`, `val-with-custom-name`, `other`"
+    );
+
+    let value = FlagsValue::<MyFlags>::from_str_lenient("Flag A|B|4", true).unwrap();
+    assert_eq!(value.value(), MyFlags::all());
+    let err = FlagsValue::<MyFlags>::from_str_lenient("nick-a|bogus", false).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid flag value `bogus`, expected one of `nick-a`, `b`, `c`"
+    );
+
+    let value = FlagsArrayValue::<MyFlags>::from_str_lenient("Flag B", true).unwrap();
+    assert_eq!(value.value(), MyFlags::B);
+    assert!(FlagsArrayValue::<MyFlags>::from_str_lenient("nick-a|b", false).is_err());
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    glib::Enum,
+    glib_serde::EnumSerialize,
+    glib_serde::EnumDeserialize,
+)]
+#[enum_type(name = "MyEnum5")]
+#[glib_serde(lenient, ignore_case)]
+enum MyEnum5 {
+    Val,
+    #[enum_value(name = "My Val")]
+    ValWithCustomName,
+    #[enum_value(name = "My Other Val", nick = "other")]
+    ValWithCustomNameAndNick,
+}
+
+#[derive(glib_serde::FlagsSerialize, glib_serde::FlagsDeserialize)]
+#[glib::flags(name = "MyFlags5")]
+#[glib_serde(lenient)]
+enum MyFlags5 {
+    #[flags_value(name = "Flag A", nick = "nick-a")]
+    A = 0b00000001,
+    #[flags_value(name = "Flag B")]
+    B = 0b00000010,
+    C = 0b00000100,
+}
+
+#[test]
+fn deserialize_lenient() {
+    let e: MyEnum5 = serde_json::from_str("\"val-with-custom-name\"").unwrap();
+    assert_eq!(e, MyEnum5::ValWithCustomName);
+
+    let e: MyEnum5 = serde_json::from_str("\"MY VAL\"").unwrap();
+    assert_eq!(e, MyEnum5::ValWithCustomName);
+
+    let e: MyEnum5 = serde_json::from_str("\"Other\"").unwrap();
+    assert_eq!(e, MyEnum5::ValWithCustomNameAndNick);
+
+    let e: MyEnum5 = serde_json::from_str("0").unwrap();
+    assert_eq!(e, MyEnum5::Val);
+
+    let e: MyEnum5 = serde_json::from_str("\"2\"").unwrap();
+    assert_eq!(e, MyEnum5::ValWithCustomNameAndNick);
+
+    let err = serde_json::from_str::<'_, MyEnum5>("\"nothing\"").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected one of `val`, `val-with-custom-name`, `other`"));
+
+    let f: MyFlags5 = serde_json::from_str("\"Flag A|c\"").unwrap();
+    assert_eq!(f, MyFlags5::A | MyFlags5::C);
+
+    let f: MyFlags5 = serde_json::from_str("3").unwrap();
+    assert_eq!(f, MyFlags5::A | MyFlags5::B);
+
+    let err = serde_json::from_str::<'_, MyFlags5>("\"NICK-A\"").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected one of `nick-a`, `b`, `c`"));
+}
+
+#[test]
+fn binary_formats() {
+    use glib::StaticType;
+    use glib_serde::{DynEnumValue, EnumValue, FlagsArrayValue, FlagsValue};
+
+    let bytes = bincode::serialize(&MyEnum::ValWithCustomNameAndNick).unwrap();
+    assert_eq!(bytes, 2i32.to_le_bytes());
+    let e: MyEnum = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(e, MyEnum::ValWithCustomNameAndNick);
+
+    let bytes = bincode::serialize(&(MyFlags::A | MyFlags::C)).unwrap();
+    assert_eq!(bytes, 0b101u32.to_le_bytes());
+    let f: MyFlags = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(f, MyFlags::A | MyFlags::C);
+
+    let value = EnumValue::<MyEnum>::from(MyEnum::ValWithCustomName);
+    let bytes = bincode::serialize(&value).unwrap();
+    assert_eq!(bytes, 1i32.to_le_bytes());
+    let value2: EnumValue<MyEnum> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(value, value2);
+
+    let value = FlagsValue::<MyFlags>::from(MyFlags::B);
+    let value2: FlagsValue<MyFlags> =
+        bincode::deserialize(&bincode::serialize(&value).unwrap()).unwrap();
+    assert_eq!(value, value2);
+
+    let value = FlagsArrayValue::<MyFlags>::from(MyFlags::A | MyFlags::B);
+    let bytes = bincode::serialize(&value).unwrap();
+    assert_eq!(bytes, 0b11u32.to_le_bytes());
+    let value2: FlagsArrayValue<MyFlags> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(value, value2);
+
+    let value = DynEnumValue::new(MyEnum::static_type(), 2);
+    let value2: DynEnumValue = bincode::deserialize(&bincode::serialize(&value).unwrap()).unwrap();
+    assert_eq!(value, value2);
+}
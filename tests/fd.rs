@@ -0,0 +1,136 @@
+// SPDX-FileCopyrightText: 2021 Jason Francis <jafrancis999@gmail.com>
+// SPDX-License-Identifier: MIT
+
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::os::unix::io::{AsFd, BorrowedFd, OwnedFd};
+
+use gio::prelude::*;
+use glib_serde::{from_variant_with_fds, to_variant_with_fds, Fd};
+
+#[derive(Debug, glib_serde::VariantType, serde::Serialize, serde::Deserialize)]
+struct Pipes {
+    name: String,
+    reader: Fd,
+    writer: Fd,
+}
+
+#[test]
+fn pipes() {
+    let (reader, writer) = std::io::pipe().unwrap();
+    let pipes = Pipes {
+        name: String::from("pipe"),
+        reader: Fd::from(OwnedFd::from(reader)),
+        writer: Fd::from(OwnedFd::from(writer)),
+    };
+
+    let (variant, fds) = to_variant_with_fds(&pipes).unwrap();
+    assert_eq!(variant.type_(), "(shh)");
+    assert_eq!(variant.to_string(), "('pipe', 0, 1)");
+    assert_eq!(fds.length(), 2);
+    drop(pipes);
+
+    let pipes: Pipes = from_variant_with_fds(&variant, &fds).unwrap();
+    assert_eq!(pipes.name, "pipe");
+    drop(fds);
+    let mut writer = std::fs::File::from(pipes.writer.into_inner());
+    writer.write_all(b"hello").unwrap();
+    drop(writer);
+    let mut reader = std::fs::File::from(pipes.reader.into_inner());
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "hello");
+}
+
+#[test]
+fn missing_fds() {
+    let (reader, _writer) = std::io::pipe().unwrap();
+    let fd = Fd::from(OwnedFd::from(reader));
+    let err = glib_serde::to_variant(&fd).unwrap_err();
+    assert!(err.to_string().contains("to_variant_with_fds"));
+
+    let (variant, fds) = to_variant_with_fds(&fd).unwrap();
+    assert_eq!(variant.type_(), "h");
+    let err = glib_serde::from_variant::<Fd>(&variant).unwrap_err();
+    assert!(err.to_string().contains("from_variant_with_fds"));
+
+    let empty = gio::UnixFDList::new();
+    assert!(from_variant_with_fds::<Fd>(&variant, &empty).is_err());
+    assert!(from_variant_with_fds::<Fd>(&variant, &fds).is_ok());
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Handles {
+    #[serde(with = "glib_serde::fd_handle")]
+    owned: OwnedFd,
+    #[serde(with = "glib_serde::fd_handle")]
+    fd: Fd,
+}
+
+impl glib::StaticVariantType for Handles {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(glib::VariantTy::new("(hh)").unwrap())
+    }
+}
+
+impl glib_serde::VariantType for Handles {}
+
+#[derive(serde::Serialize)]
+struct Borrowed<'a> {
+    #[serde(serialize_with = "glib_serde::fd_handle::serialize")]
+    fd: BorrowedFd<'a>,
+}
+
+impl<'a> glib::StaticVariantType for Borrowed<'a> {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(glib::VariantTy::new("(h)").unwrap())
+    }
+}
+
+impl<'a> glib_serde::VariantType for Borrowed<'a> {}
+
+#[test]
+fn fd_handle() {
+    let (reader, writer) = std::io::pipe().unwrap();
+    let handles = Handles {
+        owned: OwnedFd::from(reader),
+        fd: Fd::from(OwnedFd::from(writer)),
+    };
+    let (variant, fds) = to_variant_with_fds(&handles).unwrap();
+    assert_eq!(variant.to_string(), "(0, 1)");
+    let handles: Handles = from_variant_with_fds(&variant, &fds).unwrap();
+
+    let borrowed = Borrowed {
+        fd: handles.owned.as_fd(),
+    };
+    let (variant, fds) = to_variant_with_fds(&borrowed).unwrap();
+    assert_eq!(variant.to_string(), "(0,)");
+    assert_eq!(fds.length(), 1);
+}
+
+struct Panics;
+
+impl serde::Serialize for Panics {
+    fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        panic!("serialize");
+    }
+}
+
+impl glib::StaticVariantType for Panics {
+    fn static_variant_type() -> std::borrow::Cow<'static, glib::VariantTy> {
+        std::borrow::Cow::Borrowed(glib::VariantTy::UNIT)
+    }
+}
+
+impl glib_serde::VariantType for Panics {}
+
+#[test]
+fn fd_list_restored_after_panic() {
+    assert!(std::panic::catch_unwind(|| to_variant_with_fds(&Panics)).is_err());
+
+    let (reader, _writer) = std::io::pipe().unwrap();
+    let fd = Fd::from(OwnedFd::from(reader));
+    let err = glib_serde::to_variant(&fd).unwrap_err();
+    assert!(err.to_string().contains("to_variant_with_fds"));
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let _ = glib_serde::object_path!("/org/gnome/");
+    let _ = glib_serde::object_path!("org/gnome/Foo");
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let _ = glib_serde::signature!("mi");
+    let _ = glib_serde::signature!("a{sv}s");
+}
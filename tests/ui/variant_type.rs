@@ -0,0 +1,4 @@
+fn main() {
+    let _ = glib_serde::variant_type!("(sa{sv}");
+    let _ = glib_serde::variant_type!("a{vs}");
+}
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 use glib::{ToVariant, VariantTy};
 use glib_serde::{
-    from_variant, prelude::*, to_variant, ObjectPath, Signature, Variant, VariantDict,
+    from_variant, prelude::*, to_variant, GError, ObjectPath, Signature, Variant, VariantDict,
 };
 
 #[test]
@@ -188,11 +188,19 @@ fn deserialize_basic_types() {
     let s = "10.1";
     let value: f64 = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
     assert_eq!(value, 10.1);
+    let value: serde_json::Value = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
+    assert_eq!(value, serde_json::json!(10.1));
 
     let s = "'123'";
     let value: String = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
     assert_eq!(value, "123");
 
+    let s = "'ü'";
+    let value: char = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
+    assert_eq!(value, 'ü');
+    from_variant::<char>(&"'ab'".parse::<Variant>().unwrap()).unwrap_err();
+    from_variant::<char>(&"''".parse::<Variant>().unwrap()).unwrap_err();
+
     let s = "objectpath '/com/org/Test'";
     let value: ObjectPath = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
     assert_eq!(value.as_str(), "/com/org/Test");
@@ -279,3 +287,574 @@ fn deserialize_container_types() {
         HashMap::from([(1i64, "Hello".into())])
     );
 }
+
+#[test]
+fn gerror() {
+    let error = GError::from(glib::Error::new(gio::IOErrorEnum::NotFound, "No such file"));
+
+    let variant = to_variant(&error).unwrap();
+    assert_eq!(variant.type_(), "(sis)");
+    assert_eq!(
+        variant.to_string(),
+        "('g-io-error-quark', 1, 'No such file')"
+    );
+    assert_eq!(error.to_variant().to_string(), variant.to_string());
+
+    let value: GError = from_variant(&variant).unwrap();
+    assert!(value.matches(gio::IOErrorEnum::NotFound));
+    assert_eq!(value.message(), "No such file");
+
+    let json = serde_json::to_string(&error).unwrap();
+    assert_eq!(json, r#"["g-io-error-quark",1,"No such file"]"#);
+    let value: GError = serde_json::from_str(&json).unwrap();
+    assert!(value.matches(gio::IOErrorEnum::NotFound));
+}
+
+#[test]
+fn human_readable() {
+    let value = Variant::from((1i32, "x").to_variant());
+
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#""<(1, 'x')>""#);
+    let value2: Variant = serde_json::from_str(&json).unwrap();
+    assert_eq!(value, value2);
+    let err = serde_json::from_str::<'_, Variant>(r#""<(1,""#).unwrap_err();
+    assert!(err.is_data());
+
+    let bytes = bincode::serialize(&value).unwrap();
+    let value2: Variant = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(value, value2);
+
+    let variant = to_variant(&value).unwrap();
+    assert_eq!(variant.type_(), "v");
+    assert_eq!(variant.to_string(), "<(1, 'x')>");
+    let value2: Variant = from_variant(&variant).unwrap();
+    assert_eq!(value, value2);
+
+    let variant = to_variant(&vec![value.clone(), value.clone()]).unwrap();
+    assert_eq!(variant.to_string(), "[<(1, 'x')>, <(1, 'x')>]");
+}
+
+#[test]
+fn json_glib() {
+    use glib_serde::json::{self, JsonVariant, JsonVariantSeed};
+    use serde::de::DeserializeSeed;
+
+    let mut dict = HashMap::new();
+    dict.insert(1u32, (Some("x"), true));
+    let variant = (
+        dict,
+        None::<i32>,
+        vec![1.5f64, 2.0],
+        42i64.to_variant(),
+        ObjectPath::new("/org/gtk").unwrap(),
+    )
+        .to_variant();
+    let json = serde_json::to_string(&JsonVariant::from(variant.clone())).unwrap();
+    assert_eq!(json, r#"[{"1":["x",true]},null,[1.5,2.0],42,"/org/gtk"]"#);
+
+    let mut de = serde_json::Deserializer::from_str(&json);
+    let value = JsonVariantSeed(variant.type_())
+        .deserialize(&mut de)
+        .unwrap();
+    assert_eq!(value, variant);
+
+    let value = json::deserialize_typed(
+        &mut serde_json::Deserializer::from_str("[1, 300]"),
+        VariantTy::new("(yq)").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(value.to_string(), "(0x01, 300)");
+    let err = json::deserialize_typed(
+        &mut serde_json::Deserializer::from_str("[1, 300]"),
+        VariantTy::new("(yy)").unwrap(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("integer of type `y`"));
+
+    let value: JsonVariant =
+        serde_json::from_str(r#"{"a": 1, "b": [true, "s", 0.5], "c": null}"#).unwrap();
+    assert_eq!(value.type_(), "a{sv}");
+    let dict = glib::VariantDict::new(Some(&value));
+    assert_eq!(dict.lookup_value("a", None).unwrap().to_string(), "1");
+    assert_eq!(
+        dict.lookup_value("b", None).unwrap().to_string(),
+        "[<true>, <'s'>, <0.5>]"
+    );
+    assert_eq!(dict.lookup_value("c", None).unwrap().type_(), "mv");
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Wrapper {
+        #[serde(with = "glib_serde::json")]
+        value: Variant,
+    }
+    let wrapper = Wrapper {
+        value: ("a", 1u8).to_variant().into(),
+    };
+    let json = serde_json::to_string(&wrapper).unwrap();
+    assert_eq!(json, r#"{"value":["a",1]}"#);
+    let wrapper: Wrapper = serde_json::from_str(&json).unwrap();
+    assert_eq!(wrapper.value.type_(), "av");
+}
+
+#[test]
+fn binary_envelope() {
+    let dict = glib::VariantDict::new(None);
+    dict.insert_value("a", &vec![1u32, 2, 3].to_variant());
+    dict.insert_value("b", &(Some("x"), 1.5f64).to_variant());
+    let value = Variant::from(dict.end());
+
+    let bytes = bincode::serialize(&value).unwrap();
+    let expected = bincode::serialize(&("a{sv}", normal_data(&value))).unwrap();
+    assert_eq!(bytes, expected);
+    let value2: Variant = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(value, value2);
+
+    let bytes = bincode::serialize(&("b", normal_data(&true.to_variant().into()))).unwrap();
+    let value: Variant = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(value.to_string(), "true");
+
+    let bytes = bincode::serialize(&("b", vec![2u8])).unwrap();
+    let err = bincode::deserialize::<Variant>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("not in normal form"));
+
+    let bytes = bincode::serialize(&("*", Vec::<u8>::new())).unwrap();
+    let err = bincode::deserialize::<Variant>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("Type not supported: '*'"));
+
+    let variant = to_variant(&value2).unwrap();
+    assert_eq!(variant.type_(), "v");
+    let value3: Variant = from_variant(&variant).unwrap();
+    assert_eq!(value2, value3);
+}
+
+fn normal_data(value: &Variant) -> Vec<u8> {
+    value.normal_form().data().to_vec()
+}
+
+#[test]
+fn dict_entries() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Entry(u32, String);
+
+    impl glib::StaticVariantType for Entry {
+        fn static_variant_type() -> std::borrow::Cow<'static, VariantTy> {
+            std::borrow::Cow::Borrowed(VariantTy::new("{us}").unwrap())
+        }
+    }
+
+    impl glib_serde::VariantType for Entry {}
+
+    let entry = Entry(1, String::from("a"));
+    let variant = to_variant(&entry).unwrap();
+    assert_eq!(variant.type_(), "{us}");
+    assert_eq!(variant.to_string(), "{1, 'a'}");
+    let value: Entry = from_variant(&variant).unwrap();
+    assert_eq!(value, entry);
+    let value: (u32, String) = from_variant(&variant).unwrap();
+    assert_eq!(value, (1, String::from("a")));
+    let value: serde_json::Value = from_variant(&variant).unwrap();
+    assert_eq!(value, serde_json::json!([1, "a"]));
+
+    let mut map = HashMap::new();
+    map.insert(2u32, "b");
+    let child = map.to_variant().child_value(0);
+    assert_eq!(child.type_(), "{us}");
+    let value: Entry = from_variant(&child).unwrap();
+    assert_eq!(value, Entry(2, String::from("b")));
+
+    let value = Variant::from(child);
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#""<{uint32 2, 'b'}>""#);
+    let value2: Variant = serde_json::from_str(&json).unwrap();
+    assert_eq!(value, value2);
+    let value2: Variant = bincode::deserialize(&bincode::serialize(&value).unwrap()).unwrap();
+    assert_eq!(value, value2);
+    let variant = to_variant(&value).unwrap();
+    assert_eq!(variant.to_string(), "<{uint32 2, 'b'}>");
+    let value2: Variant = from_variant(&variant).unwrap();
+    assert_eq!(value, value2);
+}
+
+#[test]
+fn coercion() {
+    use glib_serde::{from_variant_with_options, DeserializeOptions};
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Item {
+        id: i32,
+        count: u8,
+        tags: Vec<String>,
+        props: HashMap<String, u32>,
+    }
+
+    let variant = (7u32, 3.0f64, vec!["a", "b"].to_variant(), vec![("x", 1u32)]).to_variant();
+    assert_eq!(variant.type_(), "(udva(su))");
+    assert!(from_variant::<Item>(&variant).is_err());
+
+    let options = DeserializeOptions::lenient();
+    let item: Item = from_variant_with_options(&variant, options).unwrap();
+    let mut props = HashMap::new();
+    props.insert(String::from("x"), 1);
+    assert_eq!(
+        item,
+        Item {
+            id: 7,
+            count: 3,
+            tags: vec![String::from("a"), String::from("b")],
+            props,
+        }
+    );
+
+    let options = DeserializeOptions {
+        coerce_integers: true,
+        ..Default::default()
+    };
+    assert!(from_variant_with_options::<Item>(&variant, options).is_err());
+    let value: i16 = from_variant_with_options(&1000i64.to_variant(), options).unwrap();
+    assert_eq!(value, 1000);
+    let err = from_variant_with_options::<u8>(&1000i64.to_variant(), options).unwrap_err();
+    assert!(matches!(err, glib_serde::Error::Int(_)));
+    let err = from_variant_with_options::<u8>(&(-1i32).to_variant(), options).unwrap_err();
+    assert!(matches!(err, glib_serde::Error::Int(_)));
+
+    let options = DeserializeOptions {
+        integral_doubles: true,
+        ..Default::default()
+    };
+    let value: i64 = from_variant_with_options(&(-4.0f64).to_variant(), options).unwrap();
+    assert_eq!(value, -4);
+    let err = from_variant_with_options::<i64>(&4.5f64.to_variant(), options).unwrap_err();
+    assert!(err.to_string().contains("Expected integral value"));
+
+    let options = DeserializeOptions {
+        unbox_variants: true,
+        ..Default::default()
+    };
+    let value: Variant =
+        from_variant_with_options(&1u8.to_variant().to_variant(), options).unwrap();
+    assert_eq!(value.type_(), "y");
+
+    // Options apply to one call only, not to nested calls made while deserializing.
+    struct Nested(Result<u8, glib_serde::Error>);
+
+    impl<'de> serde::Deserialize<'de> for Nested {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let variant = <Variant as serde::Deserialize>::deserialize(deserializer)?;
+            Ok(Self(from_variant(&variant)))
+        }
+    }
+
+    let variant = (1i32.to_variant(), 2u32).to_variant();
+    let (nested, count): (Nested, u8) =
+        from_variant_with_options(&variant, DeserializeOptions::lenient()).unwrap();
+    assert!(nested.0.is_err());
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn cast_variants() {
+    use glib_serde::{cast, CastErrorKind, CastRules};
+
+    let ty = |s| VariantTy::new(s).unwrap();
+    let rules = CastRules::default();
+
+    let v = cast(&vec![1i32, -2].to_variant(), ty("ax"), rules).unwrap();
+    assert_eq!(v.to_string(), "[1, -2]");
+    assert_eq!(v.type_(), "ax");
+    let v = cast(&vec!["a", "b"].to_variant(), ty("av"), rules).unwrap();
+    assert_eq!(v.to_string(), "[<'a'>, <'b'>]");
+    let v = cast(&(1i32, "x").to_variant(), ty("a{sv}"), rules).unwrap();
+    assert_eq!(v.to_string(), "{'0': <1>, '1': <'x'>}");
+    let v = cast(&Some(5i32).to_variant(), ty("ai"), rules).unwrap();
+    assert_eq!(v.to_string(), "[5]");
+    let v = cast(&None::<i32>.to_variant(), ty("ax"), rules).unwrap();
+    assert_eq!(v.type_(), "ax");
+    assert_eq!(v.n_children(), 0);
+    let v = cast(&vec![("a", 1u8)].to_variant(), ty("a{sq}"), rules).unwrap();
+    assert_eq!(v.to_string(), "{'a': 1}");
+    let v = cast(&(1u8, "x").to_variant().to_variant(), ty("(qs)"), rules).unwrap();
+    assert_eq!(v.to_string(), "(1, 'x')");
+    let v = cast(&"/org/gtk".to_variant(), ty("mo"), rules).unwrap();
+    assert_eq!(v.to_string(), "just objectpath '/org/gtk'");
+    let v = cast(&3u32.to_variant(), ty("d"), rules).unwrap();
+    assert_eq!(v.get::<f64>(), Some(3.0));
+
+    let err = cast(&vec![1i64].to_variant(), ty("ai"), rules).unwrap_err();
+    assert_eq!(err.kind, CastErrorKind::Lossy);
+    assert_eq!(err.path, [0]);
+    assert_eq!(
+        err.to_string(),
+        "Cannot cast 'x' to 'i' at child [0]: conversion may lose data"
+    );
+    let err = cast(&"not a path".to_variant(), ty("o"), rules).unwrap_err();
+    assert_eq!(
+        err.kind,
+        CastErrorKind::InvalidString(String::from("not a path"))
+    );
+    let err = cast(&(1i32, 2i32).to_variant(), ty("(iii)"), rules).unwrap_err();
+    assert_eq!(err.kind, CastErrorKind::Incompatible);
+    let err = cast(&true.to_variant(), ty("i"), rules).unwrap_err();
+    assert_eq!(err.kind, CastErrorKind::Incompatible);
+
+    let rules = CastRules::lossy();
+    let v = cast(&vec![1i64, 2].to_variant(), ty("ay"), rules).unwrap();
+    assert_eq!(v.type_(), "ay");
+    let err = cast(&vec![1i64, 300].to_variant(), ty("ay"), rules).unwrap_err();
+    assert_eq!(err.kind, CastErrorKind::OutOfRange(300));
+    assert_eq!(err.path, [1]);
+    let v = cast(&2.0f64.to_variant(), ty("i"), rules).unwrap();
+    assert_eq!(v.get::<i32>(), Some(2));
+    let err = cast(&2.5f64.to_variant(), ty("i"), rules).unwrap_err();
+    assert_eq!(err.kind, CastErrorKind::NotIntegral(2.5));
+}
+
+#[test]
+fn lazy_sequences() {
+    use glib_serde::{iter_variant, VariantMap};
+
+    let variant = vec![("a", 1i64), ("b", 2), ("c", 3)].to_variant();
+    let seq = iter_variant::<(String, i64)>(&variant).unwrap();
+    assert_eq!(seq.len(), 3);
+    assert_eq!(seq.get(1).unwrap().unwrap(), (String::from("b"), 2));
+    assert!(seq.get(3).is_none());
+    let items = seq.rev().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(items[0], (String::from("c"), 3));
+    let mut seq = iter_variant::<(String, i64)>(&variant).unwrap();
+    assert_eq!(seq.nth(1).unwrap().unwrap().1, 2);
+    assert_eq!(seq.size_hint(), (1, Some(1)));
+    // random access is not affected by iteration
+    assert_eq!(seq.len(), 3);
+    assert_eq!(seq.get(0).unwrap().unwrap().1, 1);
+    assert_eq!(seq.next().unwrap().unwrap().1, 3);
+    assert_eq!(seq.get(2).unwrap().unwrap().1, 3);
+    iter_variant::<(String, i32)>(&variant).unwrap_err();
+
+    let dict = VariantDict::new(None);
+    dict.insert("a", &1i32);
+    dict.insert("b", &"two");
+    let map = VariantMap::<i32>::new(dict.end()).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get("a").unwrap().unwrap(), 1);
+    assert!(map.get("b").unwrap().is_err());
+    assert!(map.get("c").is_none());
+    let map = VariantMap::<Variant>::new(dict.end()).unwrap();
+    assert_eq!(map.get("b").unwrap().unwrap().str(), Some("two"));
+
+    let variant = HashMap::from([("x", 1u8)]).to_variant();
+    let map = VariantMap::<u8>::new(variant.clone()).unwrap();
+    let items = map.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(items, [(String::from("x"), 1)]);
+    VariantMap::<u8>::new(vec![1u8].to_variant()).unwrap_err();
+    VariantMap::<String>::new(variant).unwrap_err();
+}
+
+#[test]
+fn variant_builder() {
+    use glib_serde::VariantBuilder;
+
+    let mut builder = VariantBuilder::new(VariantTy::new("(sa{sv}ai)").unwrap());
+    builder.add(&"name").unwrap();
+    builder.add(&1i32).unwrap_err();
+    {
+        let mut dict = builder.open(VariantTy::VARDICT).unwrap();
+        let mut entry = dict.open(VariantTy::new("{sv}").unwrap()).unwrap();
+        entry.add(&"a").unwrap();
+        entry.add(&1i32.to_variant()).unwrap();
+        entry.add(&2i32.to_variant()).unwrap_err();
+        entry.close().unwrap();
+    }
+    builder.serialize(&vec![1i32, 2, 3]).unwrap();
+    builder.add(&vec![4i32]).unwrap_err();
+    let variant = builder.end().unwrap();
+    assert_eq!(variant.to_string(), "('name', {'a': <1>}, [1, 2, 3])");
+
+    let mut builder = VariantBuilder::new(VariantTy::new("(is)").unwrap());
+    builder.add(&1i32).unwrap();
+    let err = builder.end().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Struct/tuple length mismatch: Expected 2, got 1"
+    );
+
+    let mut builder = VariantBuilder::new(VariantTy::new("a(is)").unwrap());
+    builder
+        .open(VariantTy::new("(is)").unwrap())
+        .unwrap()
+        .add(&1i32)
+        .unwrap();
+    builder.add(&(2i32, "b")).unwrap_err();
+    builder.end().unwrap_err();
+
+    let mut builder = VariantBuilder::new(VariantTy::new("a*").unwrap());
+    builder
+        .open(VariantTy::new("m*").unwrap())
+        .unwrap()
+        .add(&1i32)
+        .unwrap();
+    builder.add(&Some("x")).unwrap_err();
+    assert!(builder.open(VariantTy::new("m*").unwrap()).is_err());
+    builder.add(&None::<i32>).unwrap();
+    let variant = builder.end().unwrap();
+    assert_eq!(variant.to_string(), "[just 1, nothing]");
+}
+
+#[test]
+fn nested_containers() {
+    let mut inner = HashMap::new();
+    inner.insert(
+        String::from("a"),
+        vec![(1i32, Some(String::from("x"))), (2, None)],
+    );
+    let value = vec![(inner, vec![vec![1u8, 2], vec![]], Some(Some(3u16)))];
+    let variant = to_variant(&value).unwrap();
+    assert_eq!(variant.type_().as_str(), "a(a{sa(ims)}aaymmq)");
+    assert_eq!(
+        variant.to_string(),
+        "[({'a': [(1, 'x'), (2, nothing)]}, [[0x01, 0x02], []], just 3)]"
+    );
+
+    let boxed = vec![
+        Variant::from(1i32.to_variant()),
+        Variant::from("s".to_variant()),
+    ];
+    let variant = to_variant(&boxed).unwrap();
+    assert_eq!(variant.to_string(), "[<1>, <'s'>]");
+}
+
+#[test]
+fn fixed_arrays() {
+    use glib_serde::{from_bytes, to_bytes, FixedArray};
+
+    #[derive(
+        Clone, Copy, Debug, PartialEq, glib_serde::VariantType, serde::Serialize, serde::Deserialize,
+    )]
+    #[glib_serde_fixed_size]
+    #[repr(C)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    #[derive(
+        Clone, Copy, Debug, PartialEq, glib_serde::VariantType, serde::Serialize, serde::Deserialize,
+    )]
+    #[glib_serde_fixed_size]
+    #[repr(C)]
+    struct Range(i32, i32);
+
+    let points = vec![Point { x: 1.0, y: 2.5 }, Point { x: -3.0, y: 0.0 }];
+    let array = FixedArray::from(points.clone());
+    let variant = to_variant(&array).unwrap();
+    assert_eq!(variant.type_().as_str(), "a(dd)");
+    assert_eq!(variant.to_string(), "[(1.0, 2.5), (-3.0, 0.0)]");
+    let data = to_variant(&points).unwrap().normal_form();
+    assert_eq!(variant.normal_form().data(), data.data());
+    assert_eq!(from_variant::<FixedArray<Point>>(&variant).unwrap(), array);
+    let bytes = to_bytes(&array).unwrap();
+    assert_eq!(bytes, to_bytes(&points).unwrap());
+    assert_eq!(from_bytes::<FixedArray<Point>>(&bytes).unwrap(), array);
+    // unaligned input is copied
+    let mut shifted = vec![0u8];
+    shifted.extend_from_slice(&bytes);
+    assert_eq!(
+        from_bytes::<FixedArray<Point>>(&shifted[1..]).unwrap(),
+        array
+    );
+
+    let value = (
+        String::from("ranges"),
+        FixedArray::from(vec![Range(1, 2), Range(3, 4)]),
+    );
+    let variant = to_variant(&value).unwrap();
+    assert_eq!(variant.type_().as_str(), "(sa(ii))");
+    assert_eq!(variant.to_string(), "('ranges', [(1, 2), (3, 4)])");
+    assert_eq!(
+        from_variant::<(String, FixedArray<Range>)>(&variant).unwrap(),
+        value
+    );
+    let bytes = to_bytes(&value).unwrap();
+    assert_eq!(
+        from_bytes::<(String, FixedArray<Range>)>(&bytes).unwrap(),
+        value
+    );
+
+    let empty = FixedArray::<u32>::default();
+    let variant = to_variant(&empty).unwrap();
+    assert_eq!(variant.to_string(), "@au []");
+    assert_eq!(from_variant::<FixedArray<u32>>(&variant).unwrap(), empty);
+    // invalid data is read as an empty array
+    assert_eq!(from_bytes::<FixedArray<u32>>(&[1, 2, 3]).unwrap(), empty);
+
+    from_variant::<FixedArray<Range>>(&vec![(1u32, 2u32)].to_variant()).unwrap_err();
+    from_variant::<FixedArray<Point>>(&vec![1.0f64].to_variant()).unwrap_err();
+
+    let json = serde_json::to_string(&array).unwrap();
+    assert_eq!(json, r#"[{"x":1.0,"y":2.5},{"x":-3.0,"y":0.0}]"#);
+    assert_eq!(
+        serde_json::from_str::<FixedArray<Point>>(&json).unwrap(),
+        array
+    );
+
+    // a FixedArray reached through other code under the same struct name keeps its own format
+    struct Sneaky(Vec<u32>);
+
+    struct SneakyData<'a>(&'a [u32]);
+
+    impl serde::Serialize for Sneaky {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_newtype_struct("glib_serde::$FixedArray", &SneakyData(&self.0))
+        }
+    }
+
+    impl<'a> serde::Serialize for SneakyData<'a> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let json = serde_json::to_string(&FixedArray::from(self.0.to_vec())).unwrap();
+            assert_eq!(json, "[1,2]");
+            let data: Vec<u8> = self.0.iter().flat_map(|v| v.to_ne_bytes()).collect();
+            serializer.serialize_bytes(&data)
+        }
+    }
+
+    impl glib::StaticVariantType for Sneaky {
+        fn static_variant_type() -> std::borrow::Cow<'static, VariantTy> {
+            std::borrow::Cow::Borrowed(VariantTy::new("au").unwrap())
+        }
+    }
+
+    impl glib_serde::VariantType for Sneaky {}
+
+    let variant = to_variant(&Sneaky(vec![1, 2])).unwrap();
+    assert_eq!(variant.to_string(), "[1, 2]");
+}
+
+#[test]
+fn literal_macros() {
+    use glib_serde::{object_path, signature, variant_type};
+
+    const TYPE: &VariantTy = variant_type!("(sa{sv})");
+    assert_eq!(TYPE, VariantTy::new("(sa{sv})").unwrap());
+    assert_eq!(variant_type!("a{?*}").as_str(), "a{?*}");
+    assert_eq!(variant_type!("mr"), VariantTy::new("mr").unwrap());
+
+    const SIG: glib_serde::SignatureRef = signature!("(asgva(in)a{sb})");
+    let sig = SIG;
+    assert_eq!(sig, Signature::new("(asgva(in)a{sb})").unwrap());
+    assert_eq!(
+        Signature::from(sig),
+        Signature::new("(asgva(in)a{sb})").unwrap()
+    );
+    let variant = to_variant(&sig).unwrap();
+    assert_eq!(variant.type_(), VariantTy::SIGNATURE);
+    assert_eq!(variant.to_string(), "'(asgva(in)a{sb})'");
+
+    assert_eq!(object_path!("/"), ObjectPath::new("/").unwrap());
+    const PATH: glib_serde::ObjectPathRef = object_path!("/com/org/Test_1");
+    const PATH_STR: &str = PATH.as_str();
+    assert_eq!(PATH_STR, "/com/org/Test_1");
+    let path = PATH;
+    assert_eq!(path, ObjectPath::new("/com/org/Test_1").unwrap());
+    let variant = to_variant(&path).unwrap();
+    assert_eq!(variant.type_(), VariantTy::OBJECT_PATH);
+    assert_eq!(variant.to_string(), "'/com/org/Test_1'");
+}
@@ -52,6 +52,19 @@ fn serialize_enum() {
         variant.to_string(),
         "('StructVariant', <([int16 3, 2, 1], {int64 0: 'Zero'})>)"
     );
+
+    let variant = to_variant(&vec![
+        MyEnum::TupleVariant(1, "One".into()),
+        MyEnum::UnitVariant,
+        MyEnum::TupleVariant(2, "Two".into()),
+    ])
+    .unwrap();
+    assert_eq!(variant.type_(), "a(sv)");
+    assert_eq!(
+        variant.to_string(),
+        "[('TupleVariant', <(byte 0x01, 'One')>), ('UnitVariant', <()>), \
+         ('TupleVariant', <(byte 0x02, 'Two')>)]"
+    );
 }
 
 #[derive(Debug, PartialEq, Eq, glib_serde::VariantType, serde::Serialize, serde::Deserialize)]
@@ -284,3 +297,384 @@ fn variant_index() {
     let value: Item = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
     assert_eq!(value, Item::D { a: 5, b: 6, c: 7 });
 }
+
+#[test]
+fn validate_values() {
+    use glib_serde::validate;
+
+    let s = "(uint64 3, 'hello', just 'world')";
+    validate::<MyTupleStruct>(&s.parse::<Variant>().unwrap()).unwrap();
+    let s = "(uint32 3, 'hello', just 'world')";
+    validate::<MyTupleStruct>(&s.parse::<Variant>().unwrap()).unwrap_err();
+
+    let s = "('TupleVariant', <(byte 0x08, 'Eight')>)";
+    validate::<MyEnum>(&s.parse::<Variant>().unwrap()).unwrap();
+    let s = "('MissingVariant', <()>)";
+    validate::<MyEnum>(&s.parse::<Variant>().unwrap()).unwrap_err();
+
+    let s = "(\
+        'not-a-file-type', \
+        4, \
+        'in', \
+        uint32 1, \
+        objectpath '/org/glib_serde/test', \
+        signature 'i', \
+        <(0, 1)>, \
+        @a{sv} {}\
+    )";
+    validate::<MyWrapperStruct>(&s.parse::<Variant>().unwrap()).unwrap_err();
+    let s = s.replace("not-a-file-type", "special");
+    validate::<MyWrapperStruct>(&s.parse::<Variant>().unwrap()).unwrap();
+    let s = s.replace("'in'", "'in|bogus'");
+    validate::<MyWrapperStruct>(&s.parse::<Variant>().unwrap()).unwrap_err();
+}
+
+#[test]
+fn validate_nested_values() {
+    use glib_serde::{validate, EnumValue};
+
+    #[derive(Debug, PartialEq, glib_serde::VariantType, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Unit {
+        First,
+        #[serde(alias = "other")]
+        SecondOne,
+    }
+
+    #[derive(Debug, PartialEq, glib_serde::VariantType, serde::Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    enum Data {
+        Files(Vec<EnumValue<gio::FileType>>),
+        NamedFiles {
+            name: String,
+            ftype: EnumValue<gio::FileType>,
+        },
+        Units(Vec<Unit>),
+    }
+
+    let ok = [
+        "('files', <['regular', 'directory']>)",
+        "('named-files', <('x', 'special')>)",
+        "('units', <['first', 'second_one', 'other']>)",
+    ];
+    for s in ok {
+        validate::<Data>(&s.parse::<Variant>().unwrap()).unwrap();
+    }
+    let bad = [
+        "('Files', <@as []>)",
+        "('files', <['regular', 'bogus']>)",
+        "('files', <@ai []>)",
+        "('named-files', <('x', 'bogus')>)",
+        "('named-files', <('x',)>)",
+        "('units', <['SecondOne']>)",
+    ];
+    for s in bad {
+        validate::<Data>(&s.parse::<Variant>().unwrap()).unwrap_err();
+    }
+    let s = "('units', <['first', 'other']>)";
+    let value: Data = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
+    assert_eq!(value, Data::Units(vec![Unit::First, Unit::SecondOne]));
+    let s = "('named-files', <('x', 'special')>)";
+    let value: Data = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
+    assert_eq!(
+        value,
+        Data::NamedFiles {
+            name: String::from("x"),
+            ftype: gio::FileType::Special.into(),
+        }
+    );
+    let s = "('files', <['regular']>)";
+    let value: Data = from_variant(&s.parse::<Variant>().unwrap()).unwrap();
+    assert_eq!(value, Data::Files(vec![gio::FileType::Regular.into()]));
+}
+
+#[test]
+fn validate_variant_names() {
+    use glib_serde::validate;
+
+    #[derive(Debug, PartialEq, glib_serde::VariantType, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    enum Event {
+        KeyPress(u32),
+        #[serde(alias = "tap")]
+        Click(i32, i32),
+        #[serde(other)]
+        Unknown,
+    }
+
+    // validation accepts exactly the names that deserialize
+    let cases = [
+        ("('keyPress', <uint32 1>)", Some(Event::KeyPress(1))),
+        ("('tap', <(2, 3)>)", Some(Event::Click(2, 3))),
+        ("('click', <(2, 3)>)", Some(Event::Click(2, 3))),
+        ("('scroll', <()>)", Some(Event::Unknown)),
+        ("('KeyPress', <uint32 1>)", None),
+        ("('click', <uint32 1>)", None),
+    ];
+    for (s, expected) in cases {
+        let variant = s.parse::<Variant>().unwrap();
+        let valid = validate::<Event>(&variant).is_ok();
+        assert_eq!(valid, expected.is_some(), "{}", s);
+        assert_eq!(from_variant::<Event>(&variant).ok(), expected, "{}", s);
+    }
+}
+
+#[test]
+fn type_mismatch() {
+    use glib::ToVariant;
+    use glib_serde::{from_variant_checked, Error};
+
+    let s = "(\
+        uint32 3050, \
+        -182.5, \
+        (uint32 99, 'Foo', @ms nothing), \
+        ('UnitVariant', <()>), \
+        @m(sv) nothing\
+    )";
+    let variant = s.parse::<Variant>().unwrap();
+    let err = from_variant_checked::<MyStruct>(&variant).unwrap_err();
+    assert!(matches!(&err, Error::TypeMismatch { path, .. } if path == &[2, 0]));
+    assert_eq!(
+        err.to_string(),
+        "Type mismatch: Expected '(ud(tsms)(sv)m(sv))', got '(ud(usms)(sv)m(sv))' \
+         at child [2, 0]: Expected 't', got 'u'"
+    );
+
+    let variant = (1u64, "hello").to_variant();
+    let err = from_variant_checked::<MyTupleStruct>(&variant).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Type mismatch: Expected '(tsms)', got '(ts)' at child [2]: Expected 'ms', got 'nothing'"
+    );
+
+    let variant = (1u64, "hello", Some("world")).to_variant();
+    let value = from_variant_checked::<MyTupleStruct>(&variant).unwrap();
+    assert_eq!(
+        value,
+        MyTupleStruct(1, "hello".into(), Some("world".into()))
+    );
+
+    // Boxed payloads are not covered by the whole-type check, so they are checked as they are read.
+    let s = "(\
+        uint32 3, \
+        0.5, \
+        (uint64 1, 'a', @ms nothing), \
+        ('NewtypeVariant', <'x'>), \
+        @m(sv) nothing\
+    )";
+    let err = from_variant_checked::<MyStruct>(&s.parse::<Variant>().unwrap()).unwrap_err();
+    assert!(matches!(err, Error::Mismatch(_)));
+}
+
+#[test]
+fn update_struct() {
+    use glib::ToVariant;
+    use glib_serde::update_from_variant;
+
+    let mut value = MyStruct {
+        id: 1,
+        position: 2.5,
+        my_tuple: MyTupleStruct(3, "Foo".into(), None),
+        my_enum: MyEnum::UnitVariant,
+        my_enum2: None,
+    };
+    let dict = glib::VariantDict::new(None);
+    dict.insert("id", &1u32);
+    dict.insert("position", &-4.0f64);
+    dict.insert_value("my_tuple", &(3u64, "Bar", None::<String>).to_variant());
+    let changed = update_from_variant(&mut value, &dict.end()).unwrap();
+    assert_eq!(changed, ["position", "my_tuple"]);
+    assert_eq!(value.id, 1);
+    assert_eq!(value.position, -4.0);
+    assert_eq!(value.my_tuple, MyTupleStruct(3, "Bar".into(), None));
+    assert_eq!(value.my_enum, MyEnum::UnitVariant);
+
+    let dict = glib::VariantDict::new(None);
+    dict.insert("id", &"wrong");
+    update_from_variant(&mut value, &dict.end()).unwrap_err();
+    let dict = glib::VariantDict::new(None);
+    dict.insert("missing", &1u32);
+    update_from_variant(&mut value, &dict.end()).unwrap_err();
+    assert_eq!(value.id, 1);
+}
+
+#[test]
+fn update_keeps_skipped_fields() {
+    use glib_serde::update_from_variant;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Settings {
+        id: u32,
+        name: String,
+        #[serde(skip)]
+        cache: Option<u32>,
+    }
+
+    impl StaticVariantType for Settings {
+        fn static_variant_type() -> std::borrow::Cow<'static, VariantTy> {
+            std::borrow::Cow::Borrowed(VariantTy::new("(us)").unwrap())
+        }
+    }
+
+    impl glib_serde::VariantType for Settings {}
+
+    let mut value = Settings {
+        id: 1,
+        name: "a".into(),
+        cache: Some(5),
+    };
+    let dict = glib::VariantDict::new(None);
+    dict.insert("name", &"b");
+    let changed = update_from_variant(&mut value, &dict.end()).unwrap();
+    assert_eq!(changed, ["name"]);
+    assert_eq!(
+        value,
+        Settings {
+            id: 1,
+            name: "b".into(),
+            cache: Some(5),
+        }
+    );
+}
+
+#[test]
+fn update_single_field_struct() {
+    use glib_serde::update_from_variant;
+
+    #[derive(Debug, PartialEq, glib_serde::VariantType, serde::Serialize, serde::Deserialize)]
+    struct Title {
+        title: String,
+    }
+
+    let mut value = Title { title: "a".into() };
+    let changed = update_from_variant(&mut value, &glib::VariantDict::new(None).end()).unwrap();
+    assert!(changed.is_empty());
+    assert_eq!(value.title, "a");
+
+    let dict = glib::VariantDict::new(None);
+    dict.insert("title", &"b");
+    let changed = update_from_variant(&mut value, &dict.end()).unwrap();
+    assert_eq!(changed, ["title"]);
+    assert_eq!(value.title, "b");
+}
+
+#[test]
+fn lazy_variant() {
+    use glib_serde::LazyVariant;
+
+    #[derive(glib_serde::VariantType, serde::Serialize, serde::Deserialize)]
+    struct Record {
+        id: u32,
+        details: LazyVariant<MyStruct>,
+    }
+
+    let s = "(\
+        uint32 7, \
+        (uint32 3050, -182.5, (uint64 99, 'Foo', @ms nothing), ('UnitVariant', <()>), @m(sv) nothing)\
+    )";
+    let variant = s.parse::<Variant>().unwrap();
+    let mut record: Record = from_variant(&variant).unwrap();
+    assert_eq!(record.id, 7);
+    assert!(!record.details.is_decoded());
+    assert_eq!(record.details.variant().unwrap(), &variant.child_value(1));
+    assert_eq!(record.details.get().unwrap().id, 3050);
+    assert!(record.details.is_decoded());
+    assert_eq!(to_variant(&record).unwrap(), *variant);
+
+    record.details.get_mut().unwrap().id = 3051;
+    assert!(record.details.variant().is_none());
+    let variant = to_variant(&record).unwrap();
+    assert_eq!(variant.type_(), "(u(ud(tsms)(sv)m(sv)))");
+    assert_eq!(
+        variant.child_value(1).child_value(0).get::<u32>(),
+        Some(3051)
+    );
+
+    let json = serde_json::to_string(&record).unwrap();
+    let record: Record = serde_json::from_str(&json).unwrap();
+    assert_eq!(record.details.get().unwrap().id, 3051);
+}
+
+#[test]
+fn cached_type_nodes() {
+    use glib_serde::VariantType;
+    use std::borrow::Cow;
+
+    type Records = Vec<(String, Option<MyStruct>, HashMap<String, Variant>)>;
+
+    let node = Records::variant_type();
+    assert!(matches!(node, Cow::Borrowed(_)));
+    assert_eq!(node.type_().as_str(), "a(sm(ud(tsms)(sv)m(sv))a{sv})");
+    assert!(std::ptr::eq(node.deref(), Records::variant_type().deref()));
+    let value = &node.child_types()[0].child_types()[2];
+    assert_eq!(value.type_().as_str(), "a{sv}");
+    assert_eq!(value.child_types().len(), 2);
+    assert!(std::ptr::eq(
+        value.deref(),
+        glib_serde::VariantDict::variant_type().deref()
+    ));
+    assert!(std::ptr::eq(
+        <Option<u32>>::variant_type().deref(),
+        <Option<&u32>>::variant_type().deref()
+    ));
+    assert!(!std::ptr::eq(
+        <Vec<u32>>::variant_type().deref(),
+        <Option<u32>>::variant_type().deref()
+    ));
+
+    let addr = |node: Cow<'static, glib_serde::VariantTypeNode<'static>>| {
+        node.deref() as *const glib_serde::VariantTypeNode as usize
+    };
+    let other = std::thread::spawn(move || addr(Records::variant_type()));
+    assert_eq!(other.join().unwrap(), addr(Records::variant_type()));
+}
+
+#[test]
+fn const_type_strings() {
+    use glib_serde::{FixedArray, VariantType};
+    use std::{borrow::Cow, collections::BTreeMap};
+
+    const TUPLE_TYPE: &str = MyTupleStruct::VARIANT_TYPE_STR;
+    assert_eq!(TUPLE_TYPE, "(tsms)");
+    assert_eq!(MyNewtypeStruct::VARIANT_TYPE_STR, "i");
+    assert_eq!(MyEnum::VARIANT_TYPE_STR, "(sv)");
+    assert_eq!(MyStruct::VARIANT_TYPE_STR, "(ud(tsms)(sv)m(sv))");
+    assert_eq!(
+        <MyStruct as VariantType>::TYPE_STR,
+        Some("(ud(tsms)(sv)m(sv))")
+    );
+    assert!(matches!(MyStruct::static_variant_type(), Cow::Borrowed(_)));
+
+    #[allow(dead_code)]
+    #[derive(glib_serde::VariantType)]
+    struct Containers {
+        bytes: Vec<u8>,
+        dict: HashMap<String, Variant>,
+        pair: Option<(u32, bool)>,
+        path: glib_serde::ObjectPath,
+    }
+    assert_eq!(Containers::VARIANT_TYPE_STR, "(aya{sv}m(ub)o)");
+    assert_eq!(*Containers::static_variant_type(), "(aya{sv}m(ub)o)");
+
+    type Nested = Option<Vec<(u32, BTreeMap<String, FixedArray<u8>>)>>;
+    const NESTED: Option<&str> = <Nested as VariantType>::TYPE_STR;
+    assert_eq!(NESTED, Some("ma(ua{say})"));
+
+    struct Runtime;
+    impl glib::StaticVariantType for Runtime {
+        fn static_variant_type() -> Cow<'static, glib::VariantTy> {
+            Cow::Borrowed(glib::VariantTy::UINT32)
+        }
+    }
+    impl VariantType for Runtime {}
+
+    #[allow(dead_code)]
+    #[derive(glib_serde::VariantType)]
+    struct Fallback {
+        name: String,
+        values: Vec<Runtime>,
+    }
+    assert_eq!(<Vec<Runtime> as VariantType>::TYPE_STR, None);
+    assert_eq!(<Fallback as VariantType>::TYPE_STR, None);
+    assert_eq!(*Fallback::static_variant_type(), "(sau)");
+}